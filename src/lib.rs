@@ -1,2 +1,24 @@
+//! Google Authenticator 互換のワンタイムコードを扱うライブラリ
+//!
+//! 他のツールから使う場合は [`Vault`] を入口にする。[`Vault`] は表示も入力も行わない。
+//!
+//! ```
+//! use gauth::otp::CredentialKind;
+//! use gauth::secrets::Credential;
+//! use gauth::storage::MemoryStorage;
+//! use gauth::Vault;
+//! use std::sync::Arc;
+//!
+//! let mut vault = Vault::open_with(Arc::new(MemoryStorage::new()), "password")?;
+//! vault.add(Credential::new(
+//!     "example".into(),
+//!     CredentialKind::Totp,
+//!     b"12345678901234567890".to_vec(),
+//! ))?;
+//! assert_eq!(vault.code_at("example", 59)?.code, "287082");
+//! vault.save()?;
+//! # Ok::<(), gauth::error::AppError>(())
+//! ```
 mod modules;
 pub use modules::*;
+pub use modules::api::{Code, Vault};
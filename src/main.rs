@@ -1,28 +1,155 @@
-use clap::Parser;
+use clap::{CommandFactory, Parser};
 use gauth::error::AppError;
-use gauth::{args, auth, ui};
+use gauth::{
+    args, auth, completions, export, history, import, recovery, sync, team, time, ui, verify,
+};
 
-fn main() -> Result<(), AppError> {
+fn main() {
+    // シェルの補完から呼ばれた場合は候補を出力して終了する
+    clap_complete::CompleteEnv::with_factory(args::Args::command)
+        .var(completions::ENV_VAR)
+        .complete();
+    if let Err(e) = run() {
+        eprintln!("Error: {}", e);
+        if let Some(hint) = e.hint() {
+            eprintln!("Hint: {}", hint);
+        }
+        std::process::exit(e.exit_code());
+    }
+}
+
+fn run() -> Result<(), AppError> {
     let args = args::Args::parse();
 
     match args.command {
         args::Commands::Auth { action } => match action {
-            args::AuthAction::Add { name, key } => {
-                auth::add(name, key)?;
+            args::AuthAction::Add {
+                name,
+                key,
+                kind,
+                yes,
+            } => {
+                auth::add(name, key, kind, yes)?;
             }
-            args::AuthAction::List => {
-                auth::list()?;
+            args::AuthAction::List { no_unlock, format } => {
+                auth::list(no_unlock, format)?;
             }
             args::AuthAction::Del { name } => {
                 auth::del(name)?;
             }
-            args::AuthAction::Show { name } => {
-                auth::show(name)?;
+            args::AuthAction::Show {
+                names,
+                once,
+                count,
+                until_change,
+            } => {
+                auth::show(names, auth::ShowLimit::from_args(once, count, until_change))?;
             }
+            args::AuthAction::Recovery { action } => match action {
+                args::RecoveryCodeAction::Add { name, codes } => {
+                    auth::recovery_add(name, codes)?;
+                }
+                args::RecoveryCodeAction::List { name } => {
+                    auth::recovery_list(name)?;
+                }
+                args::RecoveryCodeAction::Use { name, code } => {
+                    auth::recovery_use(name, code)?;
+                }
+            },
         },
         args::Commands::Ui => {
             ui::run_ui_mode()?;
         }
+        args::Commands::Verify {
+            name,
+            code,
+            window,
+            record,
+        } => {
+            let outcome = verify::verify(name, code, window, record)?;
+            std::process::exit(outcome.exit_code());
+        }
+        args::Commands::Import {
+            from,
+            file,
+            dry_run,
+            on_conflict,
+        } => {
+            import::import(from, file, dry_run, on_conflict)?;
+        }
+        args::Commands::Export {
+            to,
+            file,
+            encrypt,
+            paper,
+        } => match (paper, file) {
+            (Some(paper), _) => {
+                export::export_paper(paper)?;
+            }
+            (None, Some(file)) => {
+                export::export(to, file, encrypt)?;
+            }
+            (None, None) => {
+                return Err(AppError::InvalidInput("No output file given".into()));
+            }
+        },
+        args::Commands::Sync { action } => match action {
+            None => {
+                sync::run_sync()?;
+            }
+            Some(args::SyncAction::Status) => {
+                sync::run_status()?;
+            }
+        },
+        args::Commands::History { action } => match action {
+            None => {
+                history::list()?;
+            }
+            Some(args::HistoryAction::Restore { name, revision }) => {
+                history::restore(name, revision)?;
+            }
+        },
+        args::Commands::Vault { action } => match action {
+            args::VaultAction::Member { action } => match action {
+                args::MemberAction::Add { name, public_key } => {
+                    team::add_member(name, public_key)?;
+                }
+                args::MemberAction::Remove { name } => {
+                    team::remove_member(name)?;
+                }
+                args::MemberAction::List => {
+                    team::list_members()?;
+                }
+            },
+            args::VaultAction::Identity => {
+                team::identity()?;
+            }
+        },
+        args::Commands::Recovery { action } => match action {
+            args::RecoveryAction::Split { shares, threshold } => {
+                recovery::split(shares, threshold)?;
+            }
+            args::RecoveryAction::Combine { member } => {
+                recovery::combine(member)?;
+            }
+        },
+        args::Commands::Time { action } => match action {
+            args::TimeAction::Calibrate { name, code } => {
+                time::calibrate(name, code)?;
+            }
+            args::TimeAction::Show => {
+                time::show()?;
+            }
+            args::TimeAction::Set { seconds } => {
+                time::set(seconds)?;
+            }
+            args::TimeAction::Reset => {
+                time::reset()?;
+            }
+        },
+        args::Commands::Completions { shell } => {
+            completions::run(shell)?;
+        }
     }
     Ok(())
 }
@@ -1,5 +1,23 @@
+pub mod aegis;
+pub mod api;
 pub mod args;
 pub mod auth;
+pub mod completions;
+pub mod config;
 pub mod error;
+pub mod export;
+pub mod history;
+pub mod import;
+pub mod index;
+pub mod merge;
+pub mod otp;
+pub mod random;
+pub mod recovery;
 pub mod secrets;
+pub mod select;
+pub mod storage;
+pub mod sync;
+pub mod team;
+pub mod time;
 pub mod ui;
+pub mod verify;
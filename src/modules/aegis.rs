@@ -0,0 +1,291 @@
+//! Aegis Authenticator のバックアップ形式
+//!
+//! 暗号化されたバックアップでは、データベースをランダムなマスターキーで AES-256-GCM 暗号化し、
+//! マスターキー自体をパスワード (scrypt) から導出した鍵で「スロット」ごとにラップする。
+
+use crate::error::AppError;
+use crate::random::Random;
+use aes_gcm::{
+    Aes256Gcm, Key, Nonce,
+    aead::{Aead, KeyInit},
+};
+use serde::{Deserialize, Serialize};
+
+/// パスワードスロットの種類
+pub const SLOT_TYPE_PASSWORD: u8 = 1;
+
+const TAG_LENGTH: usize = 16;
+/// Aegis が新しいパスワードスロットに使う scrypt のパラメーター
+const SCRYPT_N: u64 = 1 << 15;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Vault {
+    pub version: u32,
+    pub header: Header,
+    /// 平文の場合は `Db` オブジェクト、暗号化されている場合は Base64 文字列
+    pub db: serde_json::Value,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Header {
+    pub slots: Option<Vec<Slot>>,
+    pub params: Option<KeyParams>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Slot {
+    #[serde(rename = "type")]
+    pub slot_type: u8,
+    pub uuid: String,
+    /// ラップされたマスターキー (16進)
+    pub key: String,
+    pub key_params: KeyParams,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub n: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub p: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub salt: Option<String>,
+    #[serde(default)]
+    pub repaired: bool,
+    #[serde(default)]
+    pub is_backup: bool,
+}
+
+/// AES-GCM の nonce と認証タグ (いずれも16進)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct KeyParams {
+    pub nonce: String,
+    pub tag: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Db {
+    pub version: u32,
+    pub entries: Vec<Entry>,
+    #[serde(default)]
+    pub groups: Vec<Group>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Entry {
+    #[serde(rename = "type")]
+    pub entry_type: String,
+    pub uuid: String,
+    pub name: String,
+    pub issuer: String,
+    #[serde(default)]
+    pub note: String,
+    #[serde(default)]
+    pub favorite: bool,
+    #[serde(default)]
+    pub icon: Option<String>,
+    pub info: Info,
+    /// db.version 2 以前のグループ名
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// db.version 3 以降のグループ UUID
+    #[serde(default)]
+    pub groups: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Info {
+    /// Base32 のシークレット
+    pub secret: String,
+    pub algo: String,
+    pub digits: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub period: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub counter: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pin: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Group {
+    pub uuid: String,
+    pub name: String,
+}
+
+fn decode_hex(value: &str) -> Result<Vec<u8>, AppError> {
+    hex::decode(value).map_err(|_| AppError::ImportError("Invalid hex value in Aegis vault".into()))
+}
+
+/// AES-256-GCM で復号 (Aegis は暗号文と認証タグを分けて保存する)
+fn open(key: &[u8], params: &KeyParams, ciphertext: &[u8]) -> Result<Vec<u8>, AppError> {
+    let nonce = decode_hex(&params.nonce)?;
+    let tag = decode_hex(&params.tag)?;
+    if key.len() != 32 || nonce.len() != 12 || tag.len() != TAG_LENGTH {
+        return Err(AppError::DecryptionError);
+    }
+    let mut payload = ciphertext.to_vec();
+    payload.extend_from_slice(&tag);
+
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    cipher
+        .decrypt(Nonce::from_slice(&nonce), payload.as_slice())
+        .map_err(|_| AppError::DecryptionError)
+}
+
+/// AES-256-GCM で暗号化し、暗号文と nonce・認証タグを返す
+fn seal(
+    key: &[u8],
+    plaintext: &[u8],
+    random: &dyn Random,
+) -> Result<(Vec<u8>, KeyParams), AppError> {
+    let mut nonce = [0u8; 12];
+    random.fill(&mut nonce);
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    let mut ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext)
+        .map_err(|_| AppError::EncryptionError)?;
+    let tag = ciphertext.split_off(ciphertext.len() - TAG_LENGTH);
+    Ok((
+        ciphertext,
+        KeyParams {
+            nonce: hex::encode(nonce),
+            tag: hex::encode(tag),
+        },
+    ))
+}
+
+/// ランダムな UUID (v4) を生成
+pub fn random_uuid(random: &dyn Random) -> String {
+    let mut bytes = [0u8; 16];
+    random.fill(&mut bytes);
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = hex::encode(bytes);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+/// scrypt でパスワードからスロット鍵を導出
+pub(crate) fn derive_slot_key(
+    password: &str,
+    salt: &[u8],
+    n: u64,
+    r: u32,
+    p: u32,
+) -> Result<Vec<u8>, AppError> {
+    if !n.is_power_of_two() || n < 2 {
+        return Err(AppError::ImportError(format!(
+            "Invalid scrypt parameter N={}",
+            n
+        )));
+    }
+    let params = scrypt::Params::new(n.trailing_zeros() as u8, r, p, 32)
+        .map_err(|e| AppError::ImportError(format!("Invalid scrypt parameters: {}", e)))?;
+    let mut key = vec![0u8; 32];
+    scrypt::scrypt(password.as_bytes(), salt, &params, &mut key)
+        .map_err(|e| AppError::ImportError(format!("scrypt failed: {}", e)))?;
+    Ok(key)
+}
+
+impl Vault {
+    /// 暗号化しないバックアップを作成
+    pub fn plain(db: &Db) -> Result<Self, AppError> {
+        Ok(Vault {
+            version: 1,
+            header: Header::default(),
+            db: serde_json::to_value(db)?,
+        })
+    }
+
+    /// パスワードスロットを1つ持つ暗号化バックアップを作成
+    pub fn encrypt(db: &Db, password: &str, random: &dyn Random) -> Result<Self, AppError> {
+        let mut master_key = [0u8; 32];
+        random.fill(&mut master_key);
+
+        let mut salt = [0u8; 32];
+        random.fill(&mut salt);
+        let slot_key = derive_slot_key(password, &salt, SCRYPT_N, SCRYPT_R, SCRYPT_P)?;
+        let (wrapped_key, key_params) = seal(&slot_key, &master_key, random)?;
+        let slot = Slot {
+            slot_type: SLOT_TYPE_PASSWORD,
+            uuid: random_uuid(random),
+            key: hex::encode(wrapped_key),
+            key_params,
+            n: Some(SCRYPT_N),
+            r: Some(SCRYPT_R),
+            p: Some(SCRYPT_P),
+            salt: Some(hex::encode(salt)),
+            repaired: true,
+            is_backup: false,
+        };
+
+        let (encrypted_db, params) = seal(&master_key, &serde_json::to_vec(db)?, random)?;
+        Ok(Vault {
+            version: 1,
+            header: Header {
+                slots: Some(vec![slot]),
+                params: Some(params),
+            },
+            db: serde_json::Value::String(base64_encode(&encrypted_db)),
+        })
+    }
+
+    /// ヘッダーにスロットがあれば暗号化されている
+    pub fn is_encrypted(&self) -> bool {
+        self.header.slots.is_some()
+    }
+
+    /// パスワードスロットでマスターキーを取り出し、データベースを復号する
+    pub fn decrypt_db(&self, password: &str) -> Result<Db, AppError> {
+        let (Some(slots), Some(params)) = (&self.header.slots, &self.header.params) else {
+            return Ok(serde_json::from_value(self.db.clone())?);
+        };
+        let encrypted_db = self
+            .db
+            .as_str()
+            .ok_or_else(|| AppError::ImportError("Encrypted Aegis db must be a string".into()))?;
+        let encrypted_db = base64_decode(encrypted_db)?;
+
+        let mut password_slots = slots
+            .iter()
+            .filter(|slot| slot.slot_type == SLOT_TYPE_PASSWORD)
+            .peekable();
+        if password_slots.peek().is_none() {
+            return Err(AppError::ImportError(
+                "The Aegis vault has no password slot".into(),
+            ));
+        }
+        for slot in password_slots {
+            let (Some(n), Some(r), Some(p), Some(salt)) = (slot.n, slot.r, slot.p, &slot.salt)
+            else {
+                continue;
+            };
+            let slot_key = derive_slot_key(password, &decode_hex(salt)?, n, r, p)?;
+            let Ok(master_key) = open(&slot_key, &slot.key_params, &decode_hex(&slot.key)?) else {
+                continue;
+            };
+            let db_bytes = open(&master_key, params, &encrypted_db)?;
+            return Ok(serde_json::from_slice(&db_bytes)?);
+        }
+        Err(AppError::DecryptionError)
+    }
+}
+
+fn base64_encode(value: &[u8]) -> String {
+    use base64::Engine;
+    base64::engine::general_purpose::STANDARD.encode(value)
+}
+
+fn base64_decode(value: &str) -> Result<Vec<u8>, AppError> {
+    use base64::Engine;
+    base64::engine::general_purpose::STANDARD
+        .decode(value)
+        .map_err(|_| AppError::ImportError("Invalid base64 value in Aegis vault".into()))
+}
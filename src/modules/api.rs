@@ -0,0 +1,162 @@
+//! 他のツールに組み込むためのライブラリ API
+//!
+//! ここにある関数は標準出力への表示も、パスワードなどの入力も行わない。
+//! 保管庫のパスワードは呼び出し側が渡し、移行やマージの結果は戻り値で返す。
+//! CLI の auth サブコマンドもこの API の上に実装している。
+
+use crate::error::AppError;
+use crate::index;
+use crate::merge::MergeConflict;
+use crate::otp;
+use crate::secrets::{Credential, Migration, SecretManager, Sources};
+use crate::storage::{self, Storage};
+use std::fmt;
+use std::sync::Arc;
+
+/// ある時刻におけるワンタイムコード
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Code {
+    pub code: String,
+    /// コードを生成した UNIX 時刻 (秒)
+    pub timestamp: u64,
+    /// コードが切り替わる間隔 (秒)
+    pub period: u64,
+    /// 次のコードに切り替わるまでの秒数
+    pub remaining: u64,
+}
+
+/// パスワードで開いた保管庫
+pub struct Vault {
+    manager: SecretManager,
+    password: String,
+    migration: Option<Migration>,
+}
+
+/// パスワードや認証情報のシークレットをログなどに出さない
+impl fmt::Debug for Vault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Vault")
+            .field("password", &"<redacted>")
+            .field("credentials", &self.manager.credentials().len())
+            .field("migration", &self.migration)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Vault {
+    /// config.toml で選択された保存先の保管庫を開く
+    ///
+    /// 名前の索引が有効なら、開いたときと保存したときに書き出す。
+    pub fn open(password: &str) -> Result<Self, AppError> {
+        let mut vault = Vault::open_with(storage::from_config()?, password)?;
+        vault.manager.set_index_path(index::configured_path()?);
+        Ok(vault)
+    }
+
+    /// 指定した保存先の保管庫を開く (まだ何も保存されていなければ空の保管庫)
+    pub fn open_with(storage: Arc<dyn Storage>, password: &str) -> Result<Self, AppError> {
+        Vault::open_with_sources(storage, password, Sources::default())
+    }
+
+    /// 時計と乱数の取得元を指定して開く (テストでは [`Sources::fixed`] を使う)
+    pub fn open_with_sources(
+        storage: Arc<dyn Storage>,
+        password: &str,
+        sources: Sources,
+    ) -> Result<Self, AppError> {
+        let (manager, migration) = SecretManager::open_with(storage, password, sources)?;
+        Ok(Vault {
+            manager,
+            password: password.to_string(),
+            migration,
+        })
+    }
+
+    /// 開いたときに旧形式から移行した場合はその情報
+    pub fn migration(&self) -> Option<&Migration> {
+        self.migration.as_ref()
+    }
+
+    /// 最後に開いた・保存したときに名前の索引を書き出せなかった場合はその理由
+    ///
+    /// 索引は補助のため、書き出せなくても開く・保存する操作は成功する。
+    pub fn index_warning(&self) -> Option<&str> {
+        self.manager.index_warning()
+    }
+
+    /// 名前順の認証情報
+    pub fn credentials(&self) -> Vec<&Credential> {
+        self.manager.credentials()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Credential> {
+        self.manager.get_credential(name)
+    }
+
+    /// 保管庫に保存されている時刻オフセット (秒)
+    pub fn time_offset(&self) -> i64 {
+        self.manager.time_offset()
+    }
+
+    /// 保管庫の時計に時刻オフセットを適用した現在の UNIX 時刻
+    pub fn now(&self) -> Result<u64, AppError> {
+        self.manager.now()
+    }
+
+    /// 時刻オフセットを適用した現在の UNIX 時刻 (ミリ秒)
+    pub fn now_millis(&self) -> Result<u64, AppError> {
+        self.manager.now_millis()
+    }
+
+    /// 指定した UNIX 時刻 (秒) のコード (時刻オフセットは適用しない)
+    pub fn code_at(&self, name: &str, timestamp: u64) -> Result<Code, AppError> {
+        let credential = self
+            .get(name)
+            .ok_or_else(|| AppError::CredentialNotFound(name.to_string()))?;
+        let generator = otp::generator_for(credential)?;
+        let period = generator.period();
+        Ok(Code {
+            code: generator.generate(timestamp),
+            timestamp,
+            period,
+            remaining: period - timestamp % period,
+        })
+    }
+
+    /// 保管庫の時計に時刻オフセットを適用した現在のコード
+    pub fn code(&self, name: &str) -> Result<Code, AppError> {
+        self.code_at(name, self.now()?)
+    }
+
+    /// 認証情報を追加する (同名のものがあればエラー)
+    pub fn add(&mut self, credential: Credential) -> Result<(), AppError> {
+        if self.get(&credential.name).is_some() {
+            return Err(AppError::CredentialExists(credential.name));
+        }
+        self.insert(credential)?;
+        Ok(())
+    }
+
+    /// 認証情報を追加し、同名のものがあれば置き換えて返す
+    ///
+    /// シークレットと PIN でコードを生成できない場合はエラー。
+    pub fn insert(&mut self, credential: Credential) -> Result<Option<Credential>, AppError> {
+        otp::generator_for(&credential)?;
+        let previous = self.manager.delete_credential(&credential.name);
+        self.manager.insert_credential(credential);
+        Ok(previous)
+    }
+
+    /// 認証情報を削除して返す
+    pub fn remove(&mut self, name: &str) -> Result<Credential, AppError> {
+        self.manager
+            .delete_credential(name)
+            .ok_or_else(|| AppError::CredentialNotFound(name.to_string()))
+    }
+
+    /// 変更を保存し、他のプロセスの変更とマージして衝突した認証情報を返す
+    pub fn save(&mut self) -> Result<Vec<MergeConflict>, AppError> {
+        let message = self.manager.describe_changes();
+        self.manager.commit(&self.password, &message)
+    }
+}
@@ -1,5 +1,13 @@
+use crate::auth::ListFormat;
+use crate::completions;
+use crate::export::ExportFormat;
+use crate::import::{ConflictPolicy, ImportFormat};
+use crate::otp::CredentialKind;
+use crate::verify;
 use clap::builder::styling::{AnsiColor, Styles};
 use clap::{ColorChoice, Parser, Subcommand};
+use clap_complete::Shell;
+use std::path::PathBuf;
 
 // 独自のカラースタイルを定義
 fn styles() -> Styles {
@@ -32,6 +40,85 @@ pub enum Commands {
     },
     /// TUI (Ratatui) によるリアルタイム表示モード
     Ui,
+    /// 入力されたコードを検証 (0: 有効, 2: 無効, 3: リプレイ)
+    Verify {
+        /// 識別用の名前
+        #[arg(add = completions::credential_names())]
+        name: String,
+        /// 検証するコード
+        code: String,
+        /// 前後に許容するタイムステップ数 (最大 20)
+        #[arg(
+            short,
+            long,
+            default_value_t = 1,
+            value_parser = clap::value_parser!(u64).range(0..=verify::MAX_WINDOW)
+        )]
+        window: u64,
+        /// 受理したステップを記録し、以降のリプレイを拒否する
+        #[arg(long)]
+        record: bool,
+    },
+    /// 他の認証アプリのバックアップから取り込む
+    Import {
+        /// バックアップの形式
+        #[arg(long, value_enum)]
+        from: ImportFormat,
+        /// バックアップファイル
+        file: PathBuf,
+        /// 取り込み内容を表示するだけで保存しない
+        #[arg(long)]
+        dry_run: bool,
+        /// 既存の名前と重複した場合の扱い
+        #[arg(long, value_enum, default_value_t)]
+        on_conflict: ConflictPolicy,
+    },
+    /// 他の認証アプリ向けに書き出す
+    Export {
+        /// 書き出す形式
+        #[arg(long, value_enum, default_value = "aegis")]
+        to: ExportFormat,
+        /// 出力ファイル
+        #[arg(required_unless_present = "paper")]
+        file: Option<PathBuf>,
+        /// 書き出し用のパスワードで暗号化する
+        #[arg(long)]
+        encrypt: bool,
+        /// 印刷用の紙のバックアップ (.html / .pdf) を書き出す
+        #[arg(long, value_name = "FILE", conflicts_with_all = ["to", "file", "encrypt"])]
+        paper: Option<PathBuf>,
+    },
+    /// 同期先と保管庫を同期 (status で変更の有無だけを表示)
+    Sync {
+        #[command(subcommand)]
+        action: Option<SyncAction>,
+    },
+    /// 保管庫の変更履歴を表示 (restore で削除した認証情報を復元, git 保存先のみ)
+    History {
+        #[command(subcommand)]
+        action: Option<HistoryAction>,
+    },
+    /// 共有保管庫のメンバーと識別子の管理 (member, identity)
+    Vault {
+        #[command(subcommand)]
+        action: VaultAction,
+    },
+    /// 保管庫の鍵の分散と復元 (split, combine)
+    Recovery {
+        #[command(subcommand)]
+        action: RecoveryAction,
+    },
+    /// 時刻オフセットの管理 (calibrate, show, set, reset)
+    Time {
+        #[command(subcommand)]
+        action: TimeAction,
+    },
+    /// シェルの補完スクリプトを出力 (名前の補完には config.toml の [index] が必要)
+    Completions {
+        /// 補完するシェル
+        #[arg(value_enum)]
+        shell: Shell,
+    },
 }
 
 #[derive(Subcommand, Debug)]
@@ -40,13 +127,162 @@ pub enum AuthAction {
     Add {
         /// 識別用の名前
         name: Option<String>,
-        /// Base32のシークレットキー
+        /// Base32のシークレットキー (otpauth:// / steam:// の URI も可)
         key: Option<String>,
+        /// 認証情報の種類
+        #[arg(long, value_enum, default_value_t)]
+        kind: CredentialKind,
+        /// 最初のコードを確認せずに保存する
+        #[arg(short, long)]
+        yes: bool,
     },
     /// 登録済みの認証一覧を表示
-    List,
+    List {
+        /// マスターパスワードを尋ねずに名前の索引から表示する (config.toml の [index] が必要)
+        #[arg(long)]
+        no_unlock: bool,
+        /// 出力形式
+        #[arg(long, value_enum, default_value_t)]
+        format: ListFormat,
+    },
     /// 指定した認証情報を削除
-    Del { name: String },
-    /// 特定のキーの現在のコードを表示
-    Show { name: String },
+    Del {
+        #[arg(add = completions::credential_names())]
+        name: String,
+    },
+    /// 特定のキーの現在のコードを表示 (複数指定・glob・tag:work で一度に表示)
+    Show {
+        /// 名前・名前の先頭部分・glob (aws-*)・タグ (tag:work)
+        #[arg(required = true, add = completions::credential_names())]
+        names: Vec<String>,
+        /// 現在のコードを一度だけ表示して終了する
+        #[arg(long, conflicts_with_all = ["count", "until_change"])]
+        once: bool,
+        /// N 個のコードを表示したら終了する
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+        count: Option<u64>,
+        /// コードが切り替わり、新しいコードを表示したら終了する
+        #[arg(long, conflicts_with = "count")]
+        until_change: bool,
+    },
+    /// サービスのリカバリーコードの管理 (add, list, use)
+    Recovery {
+        #[command(subcommand)]
+        action: RecoveryCodeAction,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum RecoveryCodeAction {
+    /// リカバリーコードを登録 (省略時は1行ずつ入力)
+    Add {
+        /// 識別用の名前
+        #[arg(add = completions::credential_names())]
+        name: String,
+        /// 登録するコード
+        codes: Vec<String>,
+    },
+    /// リカバリーコードと使用状況を表示
+    List {
+        /// 識別用の名前
+        #[arg(add = completions::credential_names())]
+        name: String,
+    },
+    /// リカバリーコードを使用済みにする (省略時は未使用の最初のコードを表示して使う)
+    Use {
+        /// 識別用の名前
+        #[arg(add = completions::credential_names())]
+        name: String,
+        /// 使ったコード
+        code: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SyncAction {
+    /// 前回の同期からの変更の有無を表示
+    Status,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum HistoryAction {
+    /// 過去のリビジョンから認証情報を復元
+    Restore {
+        /// 識別用の名前
+        #[arg(add = completions::credential_names())]
+        name: String,
+        /// 復元元のリビジョン (省略時はその名前を含む最新のリビジョン)
+        #[arg(long)]
+        revision: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum VaultAction {
+    /// 保管庫を開けるメンバーの管理 (add, remove, list)
+    Member {
+        #[command(subcommand)]
+        action: MemberAction,
+    },
+    /// この端末用の識別子を作成し、公開鍵を表示
+    Identity,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum MemberAction {
+    /// メンバーを追加 (公開鍵を省略するとメンバーのパスワードを尋ねる)
+    Add {
+        /// メンバーの名前
+        name: String,
+        /// メンバーの公開鍵 (gauth vault identity で表示される gauth-pk-...)
+        #[arg(long)]
+        public_key: Option<String>,
+    },
+    /// メンバーを削除し、保管庫の鍵を入れ替える
+    Remove {
+        /// メンバーの名前
+        name: String,
+    },
+    /// メンバーの一覧を表示
+    List,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum RecoveryAction {
+    /// 保管庫の鍵を印刷用の分散片に分ける
+    Split {
+        /// 分散片の数
+        #[arg(long, default_value_t = 5)]
+        shares: u8,
+        /// 復元に必要な分散片の数
+        #[arg(long, default_value_t = 3)]
+        threshold: u8,
+    },
+    /// 分散片から保管庫を開き、新しいマスターパスワードを設定
+    Combine {
+        /// パスワードを設定し直すメンバー (既定は owner)
+        #[arg(long)]
+        member: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TimeAction {
+    /// 既知の正しいコードから時刻オフセットを算出
+    Calibrate {
+        /// 識別用の名前
+        #[arg(add = completions::credential_names())]
+        name: String,
+        /// 信頼できる端末に表示されているコード
+        code: Option<String>,
+    },
+    /// 現在の時刻オフセットを表示
+    Show,
+    /// 時刻オフセットを秒単位で設定
+    Set {
+        #[arg(allow_hyphen_values = true)]
+        seconds: i64,
+    },
+    /// 時刻オフセットを解除
+    Reset,
 }
@@ -1,8 +1,37 @@
 mod add;
 mod del;
 mod list;
+mod recovery;
 mod show;
 pub use add::add;
 pub use del::del;
-pub use list::list;
-pub use show::show;
+pub use list::{ListFormat, list};
+pub use recovery::{recovery_add, recovery_list, recovery_use};
+pub use show::{ShowEnd, ShowLimit, Wait, plain_line, show, until_next_second, watch};
+
+use crate::api::Vault;
+use crate::merge::MergeConflict;
+
+/// 保管庫を開いたときに旧形式から移行していれば知らせる
+fn report_migration(vault: &Vault) {
+    if let Some(migration) = vault.migration() {
+        eprintln!("{}", migration);
+    }
+}
+
+/// 名前の索引を書き出せなかった場合に知らせる (開いた後と保存した後に呼ぶ)
+fn report_index(vault: &Vault) {
+    if let Some(warning) = vault.index_warning() {
+        eprintln!("Warning: {}", warning);
+    }
+}
+
+/// 保存時のマージで衝突した認証情報を知らせる
+fn report_conflicts(conflicts: &[MergeConflict]) {
+    for conflict in conflicts {
+        eprintln!(
+            "Conflicting changes to '{}'; kept the local version as '{}'",
+            conflict.name, conflict.kept_as
+        );
+    }
+}
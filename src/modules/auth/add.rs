@@ -1,40 +1,97 @@
-use dialoguer::{Input, Password, theme::ColorfulTheme};
+use dialoguer::{Confirm, Input, Password, theme::ColorfulTheme};
+use std::io::IsTerminal;
 
+use crate::api::Vault;
 use crate::error::AppError;
-use crate::secrets::{self, SecretManager};
+use crate::otp::{self, CredentialKind, OtpParams};
+use crate::secrets::{self, Credential};
 
-pub fn add(name: Option<String>, key: Option<String>) -> Result<(), AppError> {
+use super::{report_conflicts, report_index, report_migration};
+
+pub fn add(
+    name: Option<String>,
+    key: Option<String>,
+    kind: CredentialKind,
+    yes: bool,
+) -> Result<(), AppError> {
     let name = match name {
         Some(name) => name,
         None => Input::with_theme(&ColorfulTheme::default())
             .with_prompt("Name")
-            .interact_text()
-            .unwrap(),
+            .interact_text()?,
     };
     let key = match key {
         Some(key) => key,
         None => Password::with_theme(&ColorfulTheme::default())
             .with_prompt("Key")
-            .interact()
-            .unwrap(),
+            .interact()?,
+    };
+    // otpauth:// / steam:// の URI が渡された場合は種類も URI から判定する
+    let (kind, params, issuer, bin) = if otp::is_uri(key.trim()) {
+        let parsed = otp::parse_uri(key.trim())?;
+        (parsed.kind, parsed.params, parsed.issuer, parsed.secret)
+    } else if kind == CredentialKind::Motp {
+        // mOTP のシークレットは16進文字列のまま扱う
+        let key = key.trim().replace(" ", "").to_lowercase();
+        (kind, OtpParams::default(), None, key.into_bytes())
+    } else {
+        let normalized = otp::normalize_base32(&key)?;
+        for correction in &normalized.corrections {
+            eprintln!(
+                "Warning: read '{}' as '{}' at position {} of the key",
+                correction.from, correction.to, correction.position
+            );
+        }
+        (kind, OtpParams::default(), None, normalized.secret)
     };
-    // -- add.rs --
-    let key = key.trim().replace(" ", "").to_uppercase(); // 大文字に統一
-    let bin = base32::decode(
-        base32::Alphabet::Rfc4648 { padding: true }, // 標準的な設定（padding: trueで両方対応可）
-        &key,
-    )
-    .ok_or(AppError::InvalidKey)?;
+    otp::check_secret_length(kind, params, &bin)?;
+    let pin = if kind.requires_pin() {
+        Some(
+            Password::with_theme(&ColorfulTheme::default())
+                .with_prompt("PIN")
+                .interact()?,
+        )
+    } else {
+        None
+    };
+    // 保存前にシークレットと PIN でコードを生成できるか確認する
+    let generator = otp::generator(kind, params, bin.clone(), pin.clone())?;
 
     let master_password = secrets::get_master_password()?;
-    let master_password_bytes = master_password.as_bytes();
+    let mut vault = Vault::open(&master_password)?;
+    report_migration(&vault);
+    report_index(&vault);
+
+    let checksum = otp::secret_checksum(&bin);
+    let mut credential = Credential::new(name.clone(), kind, bin);
+    credential.issuer = issuer;
+    credential.params = params;
+    credential.pin = pin;
 
-    let (ciphertext, nonce) = secrets::encrypt_data(master_password_bytes, &bin)?;
+    // サービス側で受け付けられるか確かめられるよう、保存前に最初のコードを表示する
+    let now = vault.now()?;
+    let period = generator.period();
+    println!(
+        "Current code for {}: {} (valid for {}s)",
+        name,
+        otp::format_code(&generator.generate(now)),
+        period - now % period
+    );
+    if !yes
+        && std::io::stdin().is_terminal()
+        && !Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt("Does the service accept this code? Save it")
+            .default(true)
+            .interact()?
+    {
+        println!("Not saved.");
+        return Ok(());
+    }
 
-    let mut secret_manager = SecretManager::load_secrets(&master_password)?;
-    secret_manager.add_credential(name.clone(), ciphertext, nonce);
-    secret_manager.save_secrets(&master_password)?;
+    vault.insert(credential)?;
+    report_conflicts(&vault.save()?);
+    report_index(&vault);
 
-    println!("Successfully added auth: {}", name);
+    println!("Successfully added auth: {} (checksum {})", name, checksum);
     Ok(())
 }
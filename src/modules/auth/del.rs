@@ -1,16 +1,41 @@
+use crate::api::Vault;
 use crate::error::AppError;
-use crate::secrets::{self, SecretManager};
+use crate::secrets;
+use crate::select;
+use dialoguer::{Confirm, theme::ColorfulTheme};
+use std::io::IsTerminal;
+
+use super::{report_conflicts, report_index, report_migration};
 
 pub fn del(name: String) -> Result<(), AppError> {
     let master_password = secrets::get_master_password()?;
 
-    let mut secret_manager = SecretManager::load_secrets(&master_password)?;
-    if secret_manager.delete_credential(&name).is_some() {
-        secret_manager.save_secrets(&master_password)?;
-        println!("Successfully deleted auth: {}", name);
-    } else {
-        println!("Auth '{}' not found.", name);
+    let mut vault = Vault::open(&master_password)?;
+    report_migration(&vault);
+    report_index(&vault);
+    let selected = select::one(&vault.credentials(), &name)?;
+    // 先頭部分などで選んだ場合は確認してから削除する (端末でなければ完全な名前を求める)
+    if selected != name {
+        if !std::io::stdin().is_terminal() {
+            return Err(AppError::InvalidInput(format!(
+                "'{}' is not an exact name; give the full name to delete '{}'",
+                name, selected
+            )));
+        }
+        if !Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt(format!("Delete '{}'", selected))
+            .default(false)
+            .interact()?
+        {
+            println!("Not deleted.");
+            return Ok(());
+        }
     }
+    let name = selected;
+    vault.remove(&name)?;
+    report_conflicts(&vault.save()?);
+    report_index(&vault);
+    println!("Successfully deleted auth: {}", name);
 
     Ok(())
 }
@@ -1,20 +1,96 @@
+use crate::api::Vault;
 use crate::error::AppError;
-use crate::secrets::{self, SecretManager};
+use crate::index::{self, IndexEntry, NameIndex};
+use crate::secrets::{self, LOW_RECOVERY_CODES};
+use clap::ValueEnum;
+
+use super::{report_index, report_migration};
+
+/// 一覧の出力形式
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ListFormat {
+    /// 見出し付きの一覧
+    #[default]
+    Text,
+    /// 1行に1つの名前だけ (fzf / rofi などの選択プロンプト向け)
+    Names,
+    /// 名前・発行者・タグの JSON (ステータスバーのウィジェット向け)
+    Json,
+}
+
+/// `no_unlock` の場合はマスターパスワードを尋ねずに名前の索引から表示する
+pub fn list(no_unlock: bool, format: ListFormat) -> Result<(), AppError> {
+    if no_unlock {
+        let index = index::load()?;
+        return print_entries(&index, format, |_| None);
+    }
 
-pub fn list() -> Result<(), AppError> {
     let master_password = secrets::get_master_password()?;
-    let secret_manager = SecretManager::load_secrets(&master_password)?;
+    let vault = Vault::open(&master_password)?;
+    report_migration(&vault);
+    report_index(&vault);
 
-    let credentials = secret_manager.list_credentials();
+    let credentials = vault.credentials();
+    let index = NameIndex::from_credentials(credentials.iter().copied());
+    print_entries(&index, format, |name| {
+        let credential = vault.get(name)?;
+        if credential.recovery_codes.is_empty() {
+            return None;
+        }
+        let remaining = credential.remaining_recovery_codes();
+        Some(format!(
+            "{} recovery code(s) left{}",
+            remaining,
+            if remaining <= LOW_RECOVERY_CODES {
+                ", running low"
+            } else {
+                ""
+            }
+        ))
+    })
+}
 
-    if credentials.is_empty() {
-        println!("No credentials found.");
-    } else {
-        println!("Available credentials:");
-        for name in credentials {
-            println!("  - {}", name);
+/// `note` は名前ごとの補足 (保管庫を開いたときだけ分かるリカバリーコードの残りなど)
+fn print_entries(
+    index: &NameIndex,
+    format: ListFormat,
+    note: impl Fn(&str) -> Option<String>,
+) -> Result<(), AppError> {
+    match format {
+        ListFormat::Names => {
+            for name in index.names() {
+                println!("{}", name);
+            }
+        }
+        ListFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(&index.credentials)?);
+        }
+        ListFormat::Text if index.credentials.is_empty() => {
+            println!("No credentials found.");
+        }
+        ListFormat::Text => {
+            println!("Available credentials:");
+            for entry in &index.credentials {
+                println!("  - {}", describe(entry, note(&entry.name)));
+            }
         }
     }
-
     Ok(())
 }
+
+/// "github (GitHub) [work, dev] (3 recovery code(s) left)" の形にする
+fn describe(entry: &IndexEntry, note: Option<String>) -> String {
+    let mut line = entry.name.clone();
+    if let Some(issuer) = &entry.issuer
+        && issuer != &entry.name
+    {
+        line.push_str(&format!(" ({})", issuer));
+    }
+    if !entry.tags.is_empty() {
+        line.push_str(&format!(" [{}]", entry.tags.join(", ")));
+    }
+    if let Some(note) = note {
+        line.push_str(&format!(" ({})", note));
+    }
+    line
+}
@@ -0,0 +1,115 @@
+use dialoguer::{Input, theme::ColorfulTheme};
+
+use crate::error::AppError;
+use crate::import;
+use crate::secrets::{self, Credential, LOW_RECOVERY_CODES, SecretManager};
+use crate::select;
+
+/// 残りが少なくなったら新しいコードの発行を促す
+fn warn_if_low(credential: &Credential) {
+    let remaining = credential.remaining_recovery_codes();
+    if remaining <= LOW_RECOVERY_CODES {
+        eprintln!(
+            "Warning: only {} recovery code(s) left for '{}'; generate new ones at the service.",
+            remaining, credential.name
+        );
+    }
+}
+
+pub fn recovery_add(name: String, codes: Vec<String>) -> Result<(), AppError> {
+    let codes = if codes.is_empty() {
+        println!("Enter one recovery code per line (empty line to finish).");
+        let mut codes = Vec::new();
+        loop {
+            let code: String = Input::with_theme(&ColorfulTheme::default())
+                .with_prompt(format!("Code {}", codes.len() + 1))
+                .allow_empty(true)
+                .interact_text()?;
+            if code.trim().is_empty() {
+                break;
+            }
+            codes.push(code);
+        }
+        codes
+    } else {
+        codes
+    };
+
+    let master_password = secrets::get_master_password()?;
+    let mut secret_manager = SecretManager::load_secrets(&master_password)?;
+    let name = select::one(&secret_manager.credentials(), &name)?;
+    let credential = secret_manager
+        .get_credential_mut(&name)
+        .ok_or_else(|| AppError::CredentialNotFound(name.clone()))?;
+    let added = credential.add_recovery_codes(&codes);
+    let remaining = credential.remaining_recovery_codes();
+    if added > 0 {
+        secret_manager.save_with_message(
+            &master_password,
+            &format!("Add recovery codes for {}", name),
+        )?;
+    }
+    println!(
+        "Added {} recovery code(s) to {} ({} unused)",
+        added, name, remaining
+    );
+    Ok(())
+}
+
+pub fn recovery_list(name: String) -> Result<(), AppError> {
+    let master_password = secrets::get_master_password()?;
+    let secret_manager = SecretManager::load_secrets(&master_password)?;
+    let name = select::one(&secret_manager.credentials(), &name)?;
+    let credential = secret_manager
+        .get_credential(&name)
+        .ok_or_else(|| AppError::CredentialNotFound(name.clone()))?;
+    if credential.recovery_codes.is_empty() {
+        println!("No recovery codes stored for {}.", name);
+        return Ok(());
+    }
+    let rows: Vec<Vec<String>> = credential
+        .recovery_codes
+        .iter()
+        .enumerate()
+        .map(|(index, code)| {
+            vec![
+                (index + 1).to_string(),
+                code.code.clone(),
+                if code.used { "used" } else { "unused" }.into(),
+            ]
+        })
+        .collect();
+    import::print_table(&["#", "Code", "Status"], &rows);
+    println!(
+        "{} of {} unused",
+        credential.remaining_recovery_codes(),
+        credential.recovery_codes.len()
+    );
+    warn_if_low(credential);
+    Ok(())
+}
+
+pub fn recovery_use(name: String, code: Option<String>) -> Result<(), AppError> {
+    let master_password = secrets::get_master_password()?;
+    let mut secret_manager = SecretManager::load_secrets(&master_password)?;
+    let name = select::one(&secret_manager.credentials(), &name)?;
+    let credential = secret_manager
+        .get_credential_mut(&name)
+        .ok_or_else(|| AppError::CredentialNotFound(name.clone()))?;
+    let used = credential.use_recovery_code(code.as_deref())?;
+    let credential = credential.clone();
+    secret_manager.save_with_message(
+        &master_password,
+        &format!("Use a recovery code for {}", name),
+    )?;
+
+    if code.is_none() {
+        println!("{}", used);
+    }
+    println!(
+        "Marked the recovery code as used ({} left)",
+        credential.remaining_recovery_codes()
+    );
+    warn_if_low(&credential);
+    Ok(())
+}
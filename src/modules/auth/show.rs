@@ -1,72 +1,233 @@
+use crate::api::{Code, Vault};
 use crate::error::AppError;
-use crate::secrets::{self, SecretManager};
-use indicatif::{ProgressBar, ProgressStyle};
+use crate::secrets;
+use crate::select;
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::io::{IsTerminal, Write};
+use std::sync::mpsc::{self, RecvTimeoutError};
 use std::thread;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
-use totp_rs::{Algorithm, TOTP};
+use std::time::Duration;
 
-pub fn show(name: String) -> Result<(), AppError> {
+use super::{report_index, report_migration};
+
+/// 残りがこの秒数以下になったらバーを赤くする
+const EXPIRY_WARNING_SECONDS: u64 = 10;
+
+/// Ctrl-C で中断したときの終了コード (128 + SIGINT)
+const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+/// 表示を終える条件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShowLimit {
+    /// Ctrl-C で中断するまで表示し続ける
+    Forever,
+    /// 認証情報ごとにこの数のコードを表示したら終える
+    Codes(u64),
+}
+
+impl ShowLimit {
+    /// `--once` / `--count N` / `--until-change` から決める
+    ///
+    /// `--until-change` は今のコードと、切り替わった次のコードまでを表示して終える。
+    pub fn from_args(once: bool, count: Option<u64>, until_change: bool) -> Self {
+        match (once, count, until_change) {
+            (true, _, _) => ShowLimit::Codes(1),
+            (_, Some(count), _) => ShowLimit::Codes(count),
+            (_, _, true) => ShowLimit::Codes(2),
+            _ => ShowLimit::Forever,
+        }
+    }
+
+    /// すべての認証情報が `shown` 個以上のコードを表示し終えたか
+    pub fn reached(&self, shown: u64) -> bool {
+        match self {
+            ShowLimit::Forever => false,
+            ShowLimit::Codes(count) => shown >= *count,
+        }
+    }
+}
+
+/// 次に時計の秒が切り替わるまでの時間
+pub fn until_next_second(millis: u64) -> Duration {
+    Duration::from_millis(1000 - millis % 1000)
+}
+
+/// 端末でない場合に、コードが切り替わるたびに出力する1行
+pub fn plain_line(name: &str, current: &Code, next: &str) -> String {
+    format!(
+        "{}: {} ({}s left, next {})",
+        name, current.code, current.remaining, next
+    )
+}
+
+/// 表示中の認証情報
+struct Entry {
+    name: String,
+    bar: Option<ProgressBar>,
+    /// 最後に表示したコードのタイムステップ
+    step: Option<u64>,
+    /// 表示したコードの数
+    shown: u64,
+    /// バーに設定した色が期限切れ間近のものか (未設定なら `None`)
+    expiring: Option<bool>,
+}
+
+/// 秒の境界までの待機の結果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wait {
+    Elapsed,
+    /// 待機中に Ctrl-C を受け取った
+    Interrupted,
+}
+
+/// 表示を終えた理由
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShowEnd {
+    /// `ShowLimit` の数のコードを表示し終えた
+    Completed,
+    Interrupted,
+}
+
+/// 選択に一致する認証情報のコードを、認証情報ごとに1本のバーで表示する
+///
+/// バーは時計の秒の境界に合わせて更新し、次のコードも併せて表示する。
+/// 標準出力が端末でない場合はバーを使わず、コードが切り替わるたびに1行ずつ出力する。
+pub fn show(selectors: Vec<String>, limit: ShowLimit) -> Result<(), AppError> {
     let master_password = secrets::get_master_password()?;
-    let master_password_bytes = master_password.as_bytes();
-
-    let secret_manager = SecretManager::load_secrets(&master_password)?;
-
-    match secret_manager.get_credential(&name) {
-        Some(credential) => {
-            let plaintext_bytes = secrets::decrypt_data(
-                master_password_bytes,
-                &credential.ciphertext,
-                &credential.nonce,
-            )?;
-            let totp_instance = TOTP::new(Algorithm::SHA1, 6, 1, 30, plaintext_bytes)
-                .map_err(|e| AppError::GeneralError(format!("Failed to create TOTP: {}", e)))?;
-
-            loop {
-                let current_timestamp = SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .map_err(|e| AppError::GeneralError(format!("SystemTime error: {}", e)))?
-                    .as_secs();
-                let time_until_next_code = 30 - (current_timestamp % 30);
-
-                let code = totp_instance.generate_current().map_err(|e| {
-                    AppError::GeneralError(format!("Failed to generate TOTP code: {}", e))
-                })?;
-
-                let default_style = ProgressStyle::default_bar()
-                    .template("{msg} {bar:40.cyan/blue}")
-                    .unwrap()
-                    .progress_chars("##-");
-
-                let red_style = ProgressStyle::default_bar()
-                    .template("{msg} {bar:40.red/blue}")
-                    .unwrap()
-                    .progress_chars("##-");
-
-                let pb = ProgressBar::new(30);
-                pb.set_style(default_style.clone()); // Start with default style
-
-                for i in (0..=time_until_next_code).rev() {
-                    if i <= 10 && i > 0 {
-                        // Apply red style if 10s or less remain (excluding 0s for message display)
-                        pb.set_style(red_style.clone());
-                    } else {
-                        // Otherwise, ensure default style is used
-                        pb.set_style(default_style.clone());
-                    }
-
-                    pb.set_message(format!("TOTP Code for {}: {} ({}s)", name, code, i));
-                    pb.set_position(i);
-                    if i > 0 {
-                        thread::sleep(Duration::from_secs(1));
-                    }
+
+    let vault = Vault::open(&master_password)?;
+    report_migration(&vault);
+    report_index(&vault);
+
+    let names = select::all(&vault.credentials(), &selectors)?;
+
+    // 判定と同じ標準出力にバーを描く (既定の標準エラー出力では、リダイレクトした場合に食い違う)
+    let multi = std::io::stdout()
+        .is_terminal()
+        .then(|| MultiProgress::with_draw_target(ProgressDrawTarget::stdout()));
+
+    // Ctrl-C は待機中に受け取り、バーを消してから終了する
+    let (interrupt, interrupted) = mpsc::channel();
+    let _ = ctrlc::set_handler(move || {
+        let _ = interrupt.send(());
+    });
+    let mut wait = |duration| match interrupted.recv_timeout(duration) {
+        Ok(()) => Wait::Interrupted,
+        Err(RecvTimeoutError::Timeout) => Wait::Elapsed,
+        // ハンドラを登録できなかった場合は Ctrl-C の既定の動作に任せて待つ
+        Err(RecvTimeoutError::Disconnected) => {
+            thread::sleep(duration);
+            Wait::Elapsed
+        }
+    };
+
+    let end = watch(
+        &vault,
+        names,
+        limit,
+        multi.as_ref(),
+        &mut std::io::stdout().lock(),
+        &mut wait,
+    )?;
+    if end == ShowEnd::Interrupted {
+        if let Some(multi) = &multi {
+            multi.clear()?;
+        }
+        std::process::exit(INTERRUPTED_EXIT_CODE);
+    }
+    Ok(())
+}
+
+/// 保管庫の時計で `names` のコードを表示し、`limit` に達するか中断されるまで繰り返す
+///
+/// `multi` がなければバーを使わず、コードが切り替わるたびに `out` へ1行ずつ書き出す。
+/// 次の秒の境界までの待機は `wait` に任せる。
+pub fn watch(
+    vault: &Vault,
+    names: Vec<String>,
+    limit: ShowLimit,
+    multi: Option<&MultiProgress>,
+    out: &mut dyn Write,
+    wait: &mut dyn FnMut(Duration) -> Wait,
+) -> Result<ShowEnd, AppError> {
+    let width = names
+        .iter()
+        .map(|name| name.chars().count())
+        .max()
+        .unwrap_or_default();
+
+    let default_style = ProgressStyle::default_bar()
+        .template("{msg} {bar:40.cyan/blue}")
+        .unwrap()
+        .progress_chars("##-");
+
+    let red_style = ProgressStyle::default_bar()
+        .template("{msg} {bar:40.red/blue}")
+        .unwrap()
+        .progress_chars("##-");
+
+    let mut entries: Vec<Entry> = names
+        .into_iter()
+        .map(|name| Entry {
+            bar: multi.map(|multi| multi.add(ProgressBar::new(0))),
+            name,
+            step: None,
+            shown: 0,
+            expiring: None,
+        })
+        .collect();
+
+    loop {
+        let millis = vault.now_millis()?;
+        let timestamp = millis / 1000;
+        for entry in &mut entries {
+            let current = vault.code_at(&entry.name, timestamp)?;
+            let next = vault.code_at(&entry.name, timestamp + current.remaining)?;
+            let step = timestamp / current.period;
+            let changed = entry.step != Some(step);
+            if changed {
+                entry.step = Some(step);
+                entry.shown += 1;
+            }
+
+            let Some(bar) = &entry.bar else {
+                if changed {
+                    writeln!(out, "{}", plain_line(&entry.name, &current, &next.code))?;
                 }
-                pb.finish_and_clear();
+                continue;
+            };
+            // スタイルは毎秒差し替える必要がないため、色が変わるときだけ設定する
+            let expiring = current.remaining <= EXPIRY_WARNING_SECONDS;
+            if entry.expiring != Some(expiring) {
+                entry.expiring = Some(expiring);
+                bar.set_style(if expiring {
+                    red_style.clone()
+                } else {
+                    default_style.clone()
+                });
             }
+            // 秒未満まで含めた残り時間でバーの長さを決める
+            let period_millis = current.period * 1000;
+            bar.set_length(period_millis);
+            bar.set_position(period_millis - millis % period_millis);
+            bar.set_message(format!(
+                "TOTP Code for {:width$}: {} ({:>2}s, next {})",
+                entry.name, current.code, current.remaining, next.code,
+            ));
         }
-        None => {
-            println!("Auth '{}' not found.", name);
+
+        let shown = entries.iter().map(|entry| entry.shown).min();
+        if limit.reached(shown.unwrap_or_default()) {
+            // 最後に表示したコードは画面に残す
+            for bar in entries.iter().filter_map(|entry| entry.bar.as_ref()) {
+                bar.abandon();
+            }
+            return Ok(ShowEnd::Completed);
         }
-    }
 
-    Ok(())
+        out.flush()?;
+        if wait(until_next_second(vault.now_millis()?)) == Wait::Interrupted {
+            return Ok(ShowEnd::Interrupted);
+        }
+    }
 }
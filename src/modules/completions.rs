@@ -0,0 +1,58 @@
+//! シェルの補完
+//!
+//! `gauth completions <shell>` が出力するスクリプトは補完のたびに
+//! `COMPLETE=<shell> gauth` を呼び出し、引数の候補を clap から受け取る。
+//! 認証情報の名前は保管庫を開かずに名前の索引 (src/modules/index.rs) から読むため、
+//! 索引が無効なら名前は補完されない。
+
+use crate::error::AppError;
+use crate::index;
+use crate::select;
+use clap::builder::StyledStr;
+use clap_complete::Shell;
+use clap_complete::engine::{ArgValueCandidates, CompletionCandidate};
+use clap_complete::env::Shells;
+use std::io::Write;
+
+/// 補完の要求を受け取る環境変数
+pub const ENV_VAR: &str = "COMPLETE";
+
+/// シェルに登録するスクリプトを書き出す
+pub fn write_registration(shell: Shell, out: &mut dyn Write) -> Result<(), AppError> {
+    let shells = Shells::builtins();
+    let completer = shells
+        .completer(&shell.to_string())
+        .ok_or_else(|| AppError::InvalidInput(format!("Unsupported shell: {}", shell)))?;
+    completer.write_registration(ENV_VAR, "gauth", "gauth", "gauth", out)?;
+    Ok(())
+}
+
+/// `gauth completions <shell>`
+pub fn run(shell: Shell) -> Result<(), AppError> {
+    write_registration(shell, &mut std::io::stdout().lock())
+}
+
+/// 認証情報の名前を取る引数の候補 (発行者を説明として添える)
+///
+/// 名前のほかに、索引にあるタグを `tag:work` の形で候補に加える。
+pub fn credential_names() -> ArgValueCandidates {
+    ArgValueCandidates::new(|| {
+        // 補完中はエラーを表示できないため、索引を読めなければ候補なしとする
+        let index = index::load().unwrap_or_default();
+        let mut tags: Vec<&String> = index.credentials.iter().flat_map(|e| &e.tags).collect();
+        tags.sort();
+        tags.dedup();
+        let tags = tags
+            .into_iter()
+            .map(|tag| CompletionCandidate::new(format!("{}{}", select::TAG_PREFIX, tag)));
+        index
+            .credentials
+            .iter()
+            .map(|entry| {
+                CompletionCandidate::new(&entry.name)
+                    .help(entry.issuer.clone().map(StyledStr::from))
+            })
+            .chain(tags)
+            .collect::<Vec<_>>()
+    })
+}
@@ -0,0 +1,104 @@
+//! 設定ファイル (`config.toml`)
+//!
+//! ```toml
+//! [storage]
+//! backend = "file"           # file | keyring | git | webdav
+//! path = "/path/to/vault"    # file のみ。省略時は設定ディレクトリの credentials.toml
+//!
+//! # git の場合 (path はリポジトリのディレクトリ、省略時は設定ディレクトリの vault)
+//! # backend = "git"
+//! # remote = "git@example.com:me/gauth-vault.git"
+//!
+//! [sync]                     # gauth sync の同期先 (storage と同じ形式)
+//! backend = "webdav"
+//! url = "https://dav.example.com/gauth/credentials.toml"
+//! username = "alice"
+//! password = "app-password"
+//!
+//! [index]                    # 名前の索引 (補完用、src/modules/index.rs を参照)
+//! enabled = true
+//! ```
+
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Config {
+    /// 保管庫の保存先
+    #[serde(default)]
+    pub storage: StorageConfig,
+    /// `gauth sync` の同期先
+    #[serde(default)]
+    pub sync: Option<StorageConfig>,
+    /// 暗号化しない名前の索引
+    #[serde(default)]
+    pub index: IndexConfig,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct IndexConfig {
+    /// 既定では無効 (名前を平文で書き出すため、利用者が明示的に有効にする)
+    #[serde(default)]
+    pub enabled: bool,
+    /// 省略時は設定ディレクトリの names.toml
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "backend", rename_all = "lowercase")]
+pub enum StorageConfig {
+    /// ローカルファイル
+    File {
+        #[serde(default)]
+        path: Option<PathBuf>,
+    },
+    /// OS のキーリング (Secret Service など) に暗号化した保管庫ごと保存する
+    Keyring,
+    /// git リポジトリ (保存のたびにコミットし、remote と同期する)
+    Git {
+        /// リポジトリのディレクトリ。省略時は設定ディレクトリの vault
+        #[serde(default)]
+        path: Option<PathBuf>,
+        #[serde(default)]
+        remote: Option<String>,
+    },
+    /// WebDAV サーバー上のファイル
+    Webdav {
+        url: String,
+        #[serde(default)]
+        username: Option<String>,
+        #[serde(default)]
+        password: Option<String>,
+    },
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        StorageConfig::File { path: None }
+    }
+}
+
+impl Config {
+    /// 設定ファイルを読み込む (存在しなければ既定値)
+    pub fn load() -> Result<Self, AppError> {
+        let path = get_config_path()?;
+        if !path.exists() {
+            return Ok(Config::default());
+        }
+        Ok(toml::from_str(&fs::read_to_string(path)?)?)
+    }
+}
+
+/// gauth の設定ディレクトリ
+pub fn get_config_dir() -> Result<PathBuf, AppError> {
+    let mut path = dirs::config_dir().ok_or(AppError::ConfigDirNotFound)?;
+    path.push("gauth");
+    Ok(path)
+}
+
+pub fn get_config_path() -> Result<PathBuf, AppError> {
+    Ok(get_config_dir()?.join("config.toml"))
+}
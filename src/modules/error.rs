@@ -1,17 +1,39 @@
 use thiserror::Error;
 
+/// 終了コード (README の「終了コード」を参照)
+///
+/// 2 と 3 は verify の結果とコマンドラインの誤り (clap) に使われている。
+pub mod exit_code {
+    /// 分類されないエラー
+    pub const GENERAL: i32 = 1;
+    /// 認証情報やメンバーなどが見つからない
+    pub const NOT_FOUND: i32 = 4;
+    /// マスターパスワード (またはバックアップのパスワード) が違う
+    pub const WRONG_PASSWORD: i32 = 5;
+    /// 保管庫が壊れている
+    pub const CORRUPTED: i32 = 6;
+    /// OS のキーリングが使えない
+    pub const KEYRING: i32 = 7;
+    /// 入力が不正
+    pub const INVALID_INPUT: i32 = 8;
+    /// ファイル・保存先・同期先の入出力に失敗
+    pub const IO: i32 = 9;
+}
+
 #[derive(Error, Debug)]
 pub enum AppError {
-    #[error("General error: {0}")]
+    #[error("{0}")]
     GeneralError(String),
     #[error("Secret manager error: {0}")]
     SecretManagerError(String),
-    #[error("Io Error: {0}")]
+    #[error("I/O error: {0}")]
     IoError(#[from] std::io::Error),
-    #[error("Keyring Error: {0}")]
+    #[error("The OS keyring is unavailable: {0}")]
     KeyringError(#[from] keyring::Error),
     #[error("Invalid key")]
     InvalidKey,
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
     #[error("Keyring Search Error: {0}")]
     KeyringSearchError(String),
     #[error("Config directory not found")]
@@ -22,10 +44,109 @@ pub enum AppError {
     EncryptionError,
     #[error("Decryption failed")]
     DecryptionError,
+    #[error("The master password does not open the vault")]
+    WrongMasterPassword,
+    #[error("The vault file is corrupted ({0}); restore it from a backup")]
+    VaultCorrupted(String),
     #[error("TOML deserialization error: {0}")]
     TomlDeError(#[from] toml::de::Error),
     #[error("TOML serialization error: {0}")]
     TomlSerError(#[from] toml::ser::Error),
-    #[error("Dialoguer error: {0}")]
+    #[error("Prompt failed: {0}")]
     DialoguerError(#[from] dialoguer::Error),
+    #[error("JSON error: {0}")]
+    JsonError(#[from] serde_json::Error),
+    #[error("Auth '{0}' not found")]
+    CredentialNotFound(String),
+    #[error("{0}")]
+    NotFound(String),
+    #[error("'{0}' matches several credentials: {1}")]
+    AmbiguousSelector(String, String),
+    #[error("Revision '{0}' matches several commits: {1}")]
+    AmbiguousRevision(String, String),
+    #[error("Auth '{0}' already exists")]
+    CredentialExists(String),
+    #[error("Import error: {0}")]
+    ImportError(String),
+    #[error("The vault was changed by another process; run the command again")]
+    StorageConflict,
+    #[error(
+        "Another process changed the vault's members or key; run the command again to apply your change on top"
+    )]
+    VaultKeyChanged,
+    #[error("The vault is locked by another process (remove {0} if it is stale)")]
+    StorageLocked(String),
+    #[error("Sync error: {0}")]
+    SyncError(String),
+    #[error("Git error: {0}")]
+    GitError(String),
+    #[error("Recovery error: {0}")]
+    RecoveryError(String),
+    #[error("The name index is not available: {0}")]
+    IndexUnavailable(String),
+}
+
+impl AppError {
+    /// プロセスの終了コード
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            AppError::CredentialNotFound(_) | AppError::NotFound(_) => exit_code::NOT_FOUND,
+            AppError::WrongMasterPassword | AppError::DecryptionError => exit_code::WRONG_PASSWORD,
+            AppError::VaultCorrupted(_) => exit_code::CORRUPTED,
+            AppError::KeyringError(_) | AppError::KeyringSearchError(_) => exit_code::KEYRING,
+            AppError::InvalidKey
+            | AppError::InvalidInput(_)
+            | AppError::MasterPasswordMismatch
+            | AppError::AmbiguousSelector(..)
+            | AppError::AmbiguousRevision(..)
+            | AppError::CredentialExists(_) => exit_code::INVALID_INPUT,
+            AppError::IoError(_)
+            | AppError::ConfigDirNotFound
+            | AppError::StorageConflict
+            | AppError::VaultKeyChanged
+            | AppError::StorageLocked(_)
+            | AppError::SyncError(_)
+            | AppError::GitError(_) => exit_code::IO,
+            _ => exit_code::GENERAL,
+        }
+    }
+
+    /// 利用者が次に試せること
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            AppError::CredentialNotFound(_) => {
+                Some("Run `gauth auth list` to see the registered names.")
+            }
+            AppError::AmbiguousSelector(..) => {
+                Some("Give more of the name, or run the command in a terminal to pick one.")
+            }
+            AppError::AmbiguousRevision(..) => {
+                Some("Give more characters of the revision; `gauth history` lists them.")
+            }
+            AppError::CredentialExists(_) => {
+                Some("Choose another name, or remove the existing one with `gauth auth del`.")
+            }
+            AppError::WrongMasterPassword => Some(
+                "If the password is lost, restore access with `gauth recovery combine` and your recovery shares.",
+            ),
+            AppError::DecryptionError => Some("Check the password of the file."),
+            AppError::VaultCorrupted(_) => Some(
+                "Restore credentials.toml from a backup, or use `gauth history restore` with the git backend.",
+            ),
+            AppError::KeyringError(_) => Some(
+                "Make sure the OS keyring (Keychain, Credential Manager or Secret Service) is running and unlocked.",
+            ),
+            AppError::InvalidKey => {
+                Some("Keys are Base32 (A-Z and 2-7); an otpauth:// URI can be pasted instead.")
+            }
+            AppError::MasterPasswordMismatch => Some("Type the same password twice."),
+            AppError::IndexUnavailable(_) => Some(
+                "Set `enabled = true` under [index] in config.toml, then run `gauth auth list` once to write the index.",
+            ),
+            AppError::ConfigDirNotFound => {
+                Some("Set HOME (or APPDATA on Windows) so the config directory can be found.")
+            }
+            _ => None,
+        }
+    }
 }
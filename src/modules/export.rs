@@ -0,0 +1,120 @@
+mod aegis;
+pub mod paper;
+pub use aegis::AegisExporter;
+
+use crate::error::AppError;
+use crate::random::Random;
+use crate::secrets::{self, Credential, SecretManager};
+use clap::ValueEnum;
+use dialoguer::{Password, theme::ColorfulTheme};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// 書き出し先の形式
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Aegis Authenticator (平文 / 暗号化 JSON)
+    Aegis,
+}
+
+/// 形式ごとの書き出し処理
+pub trait Exporter {
+    /// 復号済みの認証情報を書き出す。`password` が指定されていれば暗号化する
+    ///
+    /// 識別子や鍵に使う乱数は `random` から取る。
+    fn export(
+        &self,
+        credentials: &[Credential],
+        password: Option<&str>,
+        random: &dyn Random,
+    ) -> Result<Vec<u8>, AppError>;
+}
+
+pub fn exporter(format: ExportFormat) -> Box<dyn Exporter> {
+    match format {
+        ExportFormat::Aegis => Box::new(AegisExporter),
+    }
+}
+
+/// "issuer:account" 形式の名前から発行者を取り除いたアカウント名
+pub fn account_name(credential: &Credential) -> &str {
+    credential
+        .issuer
+        .as_deref()
+        .and_then(|issuer| credential.name.strip_prefix(issuer))
+        .and_then(|rest| rest.strip_prefix(':'))
+        .unwrap_or(&credential.name)
+}
+
+/// 所有者のみ読み書きできる権限でファイルを書き込む
+pub fn write_private(path: &Path, data: &[u8]) -> Result<(), AppError> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    file.write_all(data)?;
+    Ok(())
+}
+
+pub fn export(to: ExportFormat, file: PathBuf, encrypt: bool) -> Result<(), AppError> {
+    let master_password = secrets::get_master_password()?;
+    let secret_manager = SecretManager::load_secrets(&master_password)?;
+    let credentials = secret_manager
+        .credentials()
+        .into_iter()
+        .cloned()
+        .collect::<Vec<_>>();
+
+    let password = if encrypt {
+        Some(
+            Password::with_theme(&ColorfulTheme::default())
+                .with_prompt("Export password")
+                .with_confirmation("Confirm export password", "Passwords do not match")
+                .interact()?,
+        )
+    } else {
+        None
+    };
+    let data = exporter(to).export(
+        &credentials,
+        password.as_deref(),
+        &*secret_manager.sources().random,
+    )?;
+    write_private(&file, &data)?;
+
+    println!(
+        "Exported {} credential(s) to {}",
+        credentials.len(),
+        file.display()
+    );
+    if !encrypt {
+        eprintln!("Warning: the export is not encrypted. Store it securely or use --encrypt.");
+    }
+    Ok(())
+}
+
+/// 印刷用の紙のバックアップを書き出す (形式は拡張子で選ぶ)
+pub fn export_paper(file: PathBuf) -> Result<(), AppError> {
+    let format = paper::PaperFormat::from_path(&file)?;
+    let master_password = secrets::get_master_password()?;
+    let secret_manager = SecretManager::load_secrets(&master_password)?;
+    let credentials = secret_manager
+        .credentials()
+        .into_iter()
+        .cloned()
+        .collect::<Vec<_>>();
+
+    write_private(&file, &paper::render(format, &credentials)?)?;
+    println!(
+        "Wrote a paper backup of {} credential(s) to {}",
+        credentials.len(),
+        file.display()
+    );
+    eprintln!("Warning: the file contains unencrypted secrets. Delete it after printing.");
+    Ok(())
+}
@@ -0,0 +1,385 @@
+//! 金庫などに保管する紙のバックアップ (HTML / PDF)
+//!
+//! 認証情報ごとに名前・発行者・区切ったシークレット・パラメーター・チェックサムと、
+//! otpauth:// URI の QR コードを並べる。PDF は外部のライブラリを使わずに直接組み立てる。
+
+use crate::error::AppError;
+use crate::otp::{self, CredentialKind};
+use crate::secrets::Credential;
+use qrcode::{Color, QrCode};
+use std::path::Path;
+
+/// 紙のバックアップの形式 (出力ファイルの拡張子で選ぶ)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaperFormat {
+    Html,
+    Pdf,
+}
+
+impl PaperFormat {
+    pub fn from_path(path: &Path) -> Result<Self, AppError> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_lowercase);
+        match extension.as_deref() {
+            Some("html" | "htm") => Ok(PaperFormat::Html),
+            Some("pdf") => Ok(PaperFormat::Pdf),
+            _ => Err(AppError::InvalidInput(format!(
+                "Cannot tell the paper format from {}; use a .html or .pdf file",
+                path.display()
+            ))),
+        }
+    }
+}
+
+/// 1件分の印刷内容
+#[derive(Debug, Clone)]
+pub struct PaperEntry {
+    pub name: String,
+    pub issuer: Option<String>,
+    /// 4文字ごとに区切ったシークレット
+    pub secret_groups: Vec<String>,
+    pub parameters: String,
+    pub checksum: String,
+    pub uri: String,
+    pub pin_required: bool,
+}
+
+impl PaperEntry {
+    pub fn new(credential: &Credential) -> Self {
+        let secret = otp::secret_text(credential);
+        let parameters = match credential.kind {
+            CredentialKind::Totp => format!("TOTP {}", credential.params),
+            CredentialKind::Steam => "Steam Guard (SHA1/5/30s)".into(),
+            CredentialKind::Motp => "mOTP (MD5/6/10s, hex secret)".into(),
+            CredentialKind::Yandex => "Yandex Key (SHA256/8/30s)".into(),
+        };
+        PaperEntry {
+            name: credential.name.clone(),
+            issuer: credential.issuer.clone(),
+            secret_groups: secret
+                .as_bytes()
+                .chunks(4)
+                .map(|chunk| String::from_utf8_lossy(chunk).into_owned())
+                .collect(),
+            parameters,
+            checksum: otp::secret_checksum(&credential.secret),
+            uri: otp::to_uri(credential),
+            pin_required: credential.kind.requires_pin(),
+        }
+    }
+
+    /// QR コードのモジュール (true が黒) と一辺のモジュール数
+    fn qr_modules(&self) -> Result<(Vec<bool>, usize), AppError> {
+        let code = QrCode::new(self.uri.as_bytes())
+            .map_err(|e| AppError::GeneralError(format!("Cannot encode QR code: {}", e)))?;
+        let width = code.width();
+        let modules = code
+            .to_colors()
+            .into_iter()
+            .map(|color| color == Color::Dark)
+            .collect();
+        Ok((modules, width))
+    }
+}
+
+const TITLE: &str = "gauth paper backup";
+/// PDF でパーセントエンコードして印刷した名前に添える注記
+const NON_ASCII_NOTE: &str = "! Non-ASCII text is percent-encoded (UTF-8) as in the QR code";
+const INSTRUCTIONS: [&str; 2] = [
+    "Scan a QR code, or type the secret into `gauth auth add`.",
+    "The checksum printed by gauth after adding must match the one on this sheet.",
+];
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// QR コードを SVG で描く (周囲に4モジュールの余白を付ける)
+fn qr_svg(modules: &[bool], width: usize) -> String {
+    let size = width + 8;
+    let mut path = String::new();
+    for (index, _) in modules.iter().enumerate().filter(|(_, dark)| **dark) {
+        path.push_str(&format!(
+            "M{},{}h1v1h-1z",
+            index % width + 4,
+            index / width + 4
+        ));
+    }
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {size} {size}\" shape-rendering=\"crispEdges\">\
+         <rect width=\"{size}\" height=\"{size}\" fill=\"#fff\"/><path d=\"{path}\" fill=\"#000\"/></svg>"
+    )
+}
+
+pub fn render_html(entries: &[PaperEntry]) -> Result<String, AppError> {
+    let mut html = String::new();
+    html.push_str(&format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{TITLE}</title>\n<style>\n\
+         body {{ font-family: sans-serif; margin: 2em; }}\n\
+         .entry {{ display: flex; gap: 1.5em; border: 1px solid #999; padding: 1em; margin-bottom: 1em; break-inside: avoid; }}\n\
+         .entry svg {{ width: 4cm; height: 4cm; flex: none; }}\n\
+         .secret {{ font-family: monospace; font-size: 1.2em; word-spacing: 0.3em; }}\n\
+         .checksum {{ font-family: monospace; font-weight: bold; }}\n\
+         </style>\n</head>\n<body>\n<h1>{TITLE}</h1>\n<p>{}</p>\n",
+        escape_html(&INSTRUCTIONS.join(" "))
+    ));
+    for entry in entries {
+        let (modules, width) = entry.qr_modules()?;
+        html.push_str("<section class=\"entry\">\n");
+        html.push_str(&qr_svg(&modules, width));
+        html.push_str(&format!("\n<div>\n<h2>{}</h2>\n", escape_html(&entry.name)));
+        if let Some(issuer) = &entry.issuer {
+            html.push_str(&format!("<p>Issuer: {}</p>\n", escape_html(issuer)));
+        }
+        html.push_str(&format!(
+            "<p class=\"secret\">{}</p>\n<p>{}</p>\n<p>Checksum: <span class=\"checksum\">{}</span></p>\n",
+            escape_html(&entry.secret_groups.join(" ")),
+            escape_html(&entry.parameters),
+            entry.checksum
+        ));
+        if entry.pin_required {
+            html.push_str("<p>PIN required (not printed)</p>\n");
+        }
+        html.push_str("</div>\n</section>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+    Ok(html)
+}
+
+/// A4 (ポイント単位)
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 50.0;
+const QR_SIZE: f32 = 130.0;
+const ENTRY_HEIGHT: f32 = 165.0;
+const ENTRIES_PER_PAGE: usize = 4;
+const GROUPS_PER_LINE: usize = 8;
+
+/// PDF の文字列リテラル (標準フォントで表示できない文字は ? にする)
+fn pdf_text(value: &str) -> String {
+    let mut text = String::new();
+    for c in value.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                text.push('\\');
+                text.push(c);
+            }
+            ' '..='~' => text.push(c),
+            _ => text.push('?'),
+        }
+    }
+    text
+}
+
+/// 名前や発行者を PDF に印刷する形にする
+///
+/// 標準フォントで表示できない文字を含む場合は、otpauth:// URI のラベルと同じ
+/// パーセントエンコードで印刷し、`true` を返す (用紙に注記を添える)。
+fn pdf_label(value: &str) -> (String, bool) {
+    if value.chars().all(|c| matches!(c, ' '..='~')) {
+        (value.to_string(), false)
+    } else {
+        (otp::percent_encode(value), true)
+    }
+}
+
+/// 長い名前を枠に収まるよう切り詰める
+fn truncate(value: &str, limit: usize) -> String {
+    if value.chars().count() <= limit {
+        value.to_string()
+    } else {
+        let mut truncated: String = value.chars().take(limit - 3).collect();
+        truncated.push_str("...");
+        truncated
+    }
+}
+
+fn draw_text(content: &mut String, font: &str, size: f32, x: f32, y: f32, text: &str) {
+    content.push_str(&format!(
+        "BT /{} {} Tf {:.2} {:.2} Td ({}) Tj ET\n",
+        font,
+        size,
+        x,
+        y,
+        pdf_text(text)
+    ));
+}
+
+fn draw_entry(content: &mut String, entry: &PaperEntry, top: f32) -> Result<(), AppError> {
+    let (modules, width) = entry.qr_modules()?;
+    let module = QR_SIZE / (width + 8) as f32;
+    let qr_x = MARGIN;
+    let qr_top = top;
+    content.push_str("0 g\n");
+    for (index, _) in modules.iter().enumerate().filter(|(_, dark)| **dark) {
+        let x = qr_x + (index % width + 4) as f32 * module;
+        let y = qr_top - (index / width + 5) as f32 * module;
+        content.push_str(&format!(
+            "{:.2} {:.2} {:.2} {:.2} re\n",
+            x, y, module, module
+        ));
+    }
+    content.push_str("f\n");
+
+    let text_x = MARGIN + QR_SIZE + 20.0;
+    let mut y = top - 20.0;
+    let (name, mut encoded) = pdf_label(&entry.name);
+    draw_text(content, "F2", 14.0, text_x, y, &truncate(&name, 40));
+    if let Some(issuer) = &entry.issuer {
+        let (issuer, issuer_encoded) = pdf_label(issuer);
+        encoded |= issuer_encoded;
+        y -= 18.0;
+        draw_text(
+            content,
+            "F1",
+            10.0,
+            text_x,
+            y,
+            &format!("Issuer: {}", truncate(&issuer, 50)),
+        );
+    }
+    if encoded {
+        y -= 14.0;
+        draw_text(content, "F2", 9.0, text_x, y, NON_ASCII_NOTE);
+    }
+    y -= 6.0;
+    for line in entry.secret_groups.chunks(GROUPS_PER_LINE) {
+        y -= 16.0;
+        draw_text(content, "F3", 12.0, text_x, y, &line.join(" "));
+    }
+    y -= 18.0;
+    draw_text(content, "F1", 10.0, text_x, y, &entry.parameters);
+    y -= 16.0;
+    draw_text(
+        content,
+        "F2",
+        11.0,
+        text_x,
+        y,
+        &format!("Checksum: {}", entry.checksum),
+    );
+    if entry.pin_required {
+        y -= 16.0;
+        draw_text(content, "F1", 10.0, text_x, y, "PIN required (not printed)");
+    }
+
+    let bottom = top - ENTRY_HEIGHT + 10.0;
+    content.push_str(&format!(
+        "0.6 G 0.5 w {:.2} {:.2} m {:.2} {:.2} l S\n",
+        MARGIN,
+        bottom,
+        PAGE_WIDTH - MARGIN,
+        bottom
+    ));
+    Ok(())
+}
+
+pub fn render_pdf(entries: &[PaperEntry]) -> Result<Vec<u8>, AppError> {
+    let mut pages = Vec::new();
+    let chunks: Vec<&[PaperEntry]> = if entries.is_empty() {
+        vec![&[]]
+    } else {
+        entries.chunks(ENTRIES_PER_PAGE).collect()
+    };
+    let page_count = chunks.len();
+    for (number, chunk) in chunks.into_iter().enumerate() {
+        let mut content = String::new();
+        let mut top = PAGE_HEIGHT - MARGIN;
+        if number == 0 {
+            draw_text(&mut content, "F2", 18.0, MARGIN, top - 18.0, TITLE);
+            for (line, text) in INSTRUCTIONS.iter().enumerate() {
+                draw_text(
+                    &mut content,
+                    "F1",
+                    9.0,
+                    MARGIN,
+                    top - 36.0 - line as f32 * 12.0,
+                    text,
+                );
+            }
+            top -= 67.0;
+        }
+        for entry in chunk {
+            draw_entry(&mut content, entry, top)?;
+            top -= ENTRY_HEIGHT;
+        }
+        draw_text(
+            &mut content,
+            "F1",
+            9.0,
+            PAGE_WIDTH - MARGIN - 40.0,
+            MARGIN / 2.0,
+            &format!("{} / {}", number + 1, page_count),
+        );
+        pages.push(content);
+    }
+
+    // 1: カタログ, 2: ページツリー, 3-5: フォント, 6 以降: ページと内容のストリーム
+    let fonts = ["Helvetica", "Helvetica-Bold", "Courier"];
+    let mut objects: Vec<String> = Vec::new();
+    objects.push("<< /Type /Catalog /Pages 2 0 R >>".into());
+    let kids: Vec<String> = (0..pages.len())
+        .map(|index| format!("{} 0 R", 6 + index * 2))
+        .collect();
+    objects.push(format!(
+        "<< /Type /Pages /Kids [{}] /Count {} >>",
+        kids.join(" "),
+        pages.len()
+    ));
+    for font in fonts {
+        objects.push(format!(
+            "<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>",
+            font
+        ));
+    }
+    for (index, content) in pages.iter().enumerate() {
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+             /Resources << /Font << /F1 3 0 R /F2 4 0 R /F3 5 0 R >> >> /Contents {} 0 R >>",
+            PAGE_WIDTH,
+            PAGE_HEIGHT,
+            7 + index * 2
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{}endstream",
+            content.len(),
+            content
+        ));
+    }
+
+    let mut pdf = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (index, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.push_str(&format!("{} 0 obj\n{}\nendobj\n", index + 1, object));
+    }
+    let xref = pdf.len();
+    pdf.push_str(&format!(
+        "xref\n0 {}\n0000000000 65535 f \n",
+        objects.len() + 1
+    ));
+    for offset in offsets {
+        pdf.push_str(&format!("{:010} 00000 n \n", offset));
+    }
+    pdf.push_str(&format!(
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        xref
+    ));
+    Ok(pdf.into_bytes())
+}
+
+/// 形式に応じて紙のバックアップを作成する
+pub fn render(format: PaperFormat, credentials: &[Credential]) -> Result<Vec<u8>, AppError> {
+    let entries: Vec<PaperEntry> = credentials.iter().map(PaperEntry::new).collect();
+    match format {
+        PaperFormat::Html => Ok(render_html(&entries)?.into_bytes()),
+        PaperFormat::Pdf => render_pdf(&entries),
+    }
+}
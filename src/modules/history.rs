@@ -0,0 +1,142 @@
+//! git リポジトリの保管庫の変更履歴 (gauth history)
+
+use crate::error::AppError;
+use crate::import;
+use crate::index;
+use crate::secrets::{self, Credential, SecretManager};
+use crate::storage::{self, GitStorage, Revision};
+
+fn require_git(storage: &dyn storage::Storage) -> Result<&GitStorage, AppError> {
+    storage.as_git().ok_or_else(|| {
+        AppError::GitError(
+            "History requires the git storage backend; set backend = \"git\" in config.toml".into(),
+        )
+    })
+}
+
+/// 過去のリビジョンから認証情報を探す
+///
+/// `revision` を省略した場合は、その認証情報を含む最も新しいリビジョンを使う。
+/// 探索中に復号できないリビジョン (マスターパスワードを変更する前のものなど) は飛ばす。
+pub fn find_credential(
+    repo: &GitStorage,
+    name: &str,
+    revision: Option<&str>,
+    master_password: &str,
+) -> Result<Option<(Revision, Credential)>, AppError> {
+    let revisions = repo.log()?;
+    if let Some(revision) = revision {
+        let revision = resolve_revision(&revisions, revision)?;
+        let Some(data) = repo.show(&revision.id)? else {
+            return Ok(None);
+        };
+        let manager = SecretManager::decode(&data, master_password)?;
+        return Ok(manager
+            .get_credential(name)
+            .map(|credential| (revision.clone(), credential.clone())));
+    }
+    for candidate in &revisions {
+        let Some(data) = repo.show(&candidate.id)? else {
+            continue;
+        };
+        let Ok(manager) = SecretManager::decode(&data, master_password) else {
+            continue;
+        };
+        if let Some(credential) = manager.get_credential(name) {
+            return Ok(Some((candidate.clone(), credential.clone())));
+        }
+    }
+    Ok(None)
+}
+
+/// 指定されたリビジョン (短縮形・完全なハッシュ) に一致するリビジョンを1つに絞る
+fn resolve_revision<'a>(
+    revisions: &'a [Revision],
+    revision: &str,
+) -> Result<&'a Revision, AppError> {
+    let matches: Vec<&Revision> = revisions
+        .iter()
+        .filter(|candidate| {
+            !revision.is_empty()
+                && (revision.starts_with(&candidate.id) || candidate.id.starts_with(revision))
+        })
+        .collect();
+    match matches.as_slice() {
+        [] => Err(AppError::NotFound(format!(
+            "Revision '{}' was not found in the vault history",
+            revision
+        ))),
+        [only] => Ok(only),
+        _ => Err(AppError::AmbiguousRevision(
+            revision.to_string(),
+            matches
+                .iter()
+                .map(|candidate| candidate.id.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+        )),
+    }
+}
+
+/// 削除した認証情報を過去のリビジョンから復元する
+pub fn restore_credential(
+    manager: &mut SecretManager,
+    repo: &GitStorage,
+    name: &str,
+    revision: Option<&str>,
+    master_password: &str,
+) -> Result<Revision, AppError> {
+    if manager.get_credential(name).is_some() {
+        return Err(AppError::SecretManagerError(format!(
+            "'{}' already exists in the vault",
+            name
+        )));
+    }
+    let (revision, credential) = find_credential(repo, name, revision, master_password)?
+        .ok_or_else(|| {
+            AppError::NotFound(format!("'{}' was not found in the vault history", name))
+        })?;
+    manager.insert_credential(credential);
+    manager.save_with_message(
+        master_password,
+        &format!("Restore {} from {}", name, revision.id),
+    )?;
+    Ok(revision)
+}
+
+pub fn list() -> Result<(), AppError> {
+    let storage = storage::from_config()?;
+    let repo = require_git(storage.as_ref())?;
+    let revisions = repo.log()?;
+    if revisions.is_empty() {
+        println!("No history yet.");
+        return Ok(());
+    }
+    let rows: Vec<Vec<String>> = revisions
+        .into_iter()
+        .map(|revision| vec![revision.id, revision.date, revision.message])
+        .collect();
+    import::print_table(&["Revision", "Date", "Change"], &rows);
+    Ok(())
+}
+
+pub fn restore(name: String, revision: Option<String>) -> Result<(), AppError> {
+    let storage = storage::from_config()?;
+    let repo = require_git(storage.as_ref())?;
+    let master_password = secrets::get_master_password()?;
+    let mut manager = SecretManager::load_from(storage.clone(), &master_password)?;
+    manager.set_index_path(index::configured_path()?);
+    manager.report_index_warning();
+    let restored = restore_credential(
+        &mut manager,
+        repo,
+        &name,
+        revision.as_deref(),
+        &master_password,
+    )?;
+    println!(
+        "Restored '{}' from {} ({})",
+        name, restored.id, restored.message
+    );
+    Ok(())
+}
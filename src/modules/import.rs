@@ -0,0 +1,337 @@
+mod aegis;
+mod andotp;
+mod bitwarden;
+mod freeotp;
+mod keepass;
+mod onepassword;
+mod twofas;
+pub use aegis::AegisImporter;
+pub use andotp::AndOtpImporter;
+pub use bitwarden::BitwardenImporter;
+pub use freeotp::FreeOtpPlusImporter;
+pub use keepass::KeePassImporter;
+pub use onepassword::OnePasswordImporter;
+pub use twofas::TwoFasImporter;
+
+use crate::error::AppError;
+use crate::otp::{self, CredentialKind, OtpParams};
+use crate::secrets::{self, Credential, SecretManager};
+use clap::ValueEnum;
+use dialoguer::{Password, theme::ColorfulTheme};
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+
+/// 取り込み元の形式
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// Aegis Authenticator (平文 / 暗号化 JSON)
+    Aegis,
+    /// andOTP (平文 / 暗号化 JSON)
+    Andotp,
+    /// 2FAS (.2fas)
+    #[value(name = "2fas")]
+    TwoFas,
+    /// FreeOTP+ (JSON)
+    #[value(name = "freeotp-plus")]
+    FreeOtpPlus,
+    /// Bitwarden (暗号化されていない JSON エクスポート)
+    Bitwarden,
+    /// KeePassXC / KeePass (XML エクスポート)
+    Keepass,
+    /// 1Password (CSV エクスポート)
+    #[value(name = "1password")]
+    OnePassword,
+}
+
+/// 既存の認証情報と名前が重複した場合の扱い
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// 取り込まない
+    #[default]
+    Skip,
+    /// "name (2)" のように番号を付けて取り込む
+    Rename,
+    /// 既存の認証情報を置き換える
+    Overwrite,
+}
+
+/// 取り込めなかった項目と理由
+#[derive(Debug, Clone)]
+pub struct SkippedEntry {
+    pub name: String,
+    pub reason: String,
+}
+
+/// 解析結果
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub credentials: Vec<Credential>,
+    pub skipped: Vec<SkippedEntry>,
+}
+
+impl ImportReport {
+    pub fn skip(&mut self, name: impl Into<String>, reason: impl Into<String>) {
+        self.skipped.push(SkippedEntry {
+            name: name.into(),
+            reason: reason.into(),
+        });
+    }
+}
+
+/// 形式ごとの取り込み処理
+pub trait Importer {
+    /// ファイル内容を解析する。暗号化されている場合のみ `password` でパスワードを取得する
+    fn parse(
+        &self,
+        data: &[u8],
+        password: &mut dyn FnMut() -> Result<String, AppError>,
+    ) -> Result<ImportReport, AppError>;
+}
+
+pub fn importer(format: ImportFormat) -> Box<dyn Importer> {
+    match format {
+        ImportFormat::Aegis => Box::new(AegisImporter),
+        ImportFormat::Andotp => Box::new(AndOtpImporter),
+        ImportFormat::TwoFas => Box::new(TwoFasImporter),
+        ImportFormat::FreeOtpPlus => Box::new(FreeOtpPlusImporter),
+        ImportFormat::Bitwarden => Box::new(BitwardenImporter),
+        ImportFormat::Keepass => Box::new(KeePassImporter),
+        ImportFormat::OnePassword => Box::new(OnePasswordImporter),
+    }
+}
+
+/// 発行者とアカウント名から表示名を組み立てる ("issuer:account")
+pub fn display_name(issuer: &str, account: &str) -> String {
+    match (issuer.trim(), account.trim()) {
+        ("", account) => account.to_string(),
+        (issuer, "") => issuer.to_string(),
+        (issuer, account) => format!("{}:{}", issuer, account),
+    }
+}
+
+/// 各アプリの種類表記 ("TOTP", "steam" など) を変換
+pub fn kind_from_type(entry_type: &str) -> Option<CredentialKind> {
+    match entry_type.to_lowercase().as_str() {
+        "totp" => Some(CredentialKind::Totp),
+        "steam" => Some(CredentialKind::Steam),
+        "motp" => Some(CredentialKind::Motp),
+        "yandex" => Some(CredentialKind::Yandex),
+        _ => None,
+    }
+}
+
+/// パスワードマネージャーの OTP 欄 (otpauth:// / steam:// の URI または Base32) を解釈する
+pub fn credential_from_otp_field(
+    name: String,
+    tags: Vec<String>,
+    value: &str,
+) -> Result<Credential, AppError> {
+    let value = value.trim();
+    if otp::is_uri(value) {
+        let parsed = otp::parse_uri(value)?;
+        Ok(Credential {
+            name,
+            issuer: parsed.issuer,
+            kind: parsed.kind,
+            params: parsed.params,
+            secret: parsed.secret,
+            pin: None,
+            tags,
+            last_step: None,
+            recovery_codes: Vec::new(),
+        })
+    } else {
+        Ok(Credential {
+            name,
+            issuer: None,
+            kind: CredentialKind::Totp,
+            params: OtpParams::default(),
+            secret: otp::decode_base32(value)?,
+            pin: None,
+            tags,
+            last_step: None,
+            recovery_codes: Vec::new(),
+        })
+    }
+}
+
+/// 各項目の取り込み方法
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Action {
+    Add,
+    Rename(String),
+    Overwrite,
+    Skip,
+}
+
+/// 既存の名前と照らし合わせて取り込み方法を決める
+fn plan(
+    existing: &HashSet<String>,
+    credentials: Vec<Credential>,
+    policy: ConflictPolicy,
+) -> Vec<(Credential, Action)> {
+    let mut taken = existing.clone();
+    let mut imported = HashSet::new();
+    credentials
+        .into_iter()
+        .map(|credential| {
+            let action = if !taken.contains(&credential.name) {
+                Action::Add
+            } else {
+                match policy {
+                    ConflictPolicy::Skip => Action::Skip,
+                    // 同じファイル内で重複した名前は上書きせず番号を付ける
+                    ConflictPolicy::Overwrite if !imported.contains(&credential.name) => {
+                        Action::Overwrite
+                    }
+                    _ => {
+                        let renamed = (2..)
+                            .map(|i| format!("{} ({})", credential.name, i))
+                            .find(|candidate| !taken.contains(candidate))
+                            .unwrap_or_default();
+                        Action::Rename(renamed)
+                    }
+                }
+            };
+            let final_name = match &action {
+                Action::Rename(renamed) => renamed.clone(),
+                _ => credential.name.clone(),
+            };
+            if action != Action::Skip {
+                taken.insert(final_name.clone());
+                imported.insert(final_name);
+            }
+            (credential, action)
+        })
+        .collect()
+}
+
+/// 列幅を揃えて表を表示
+pub fn print_table(headers: &[&str], rows: &[Vec<String>]) {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.chars().count()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let format_row = |cells: Vec<String>| {
+        cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+    println!(
+        "{}",
+        format_row(headers.iter().map(|h| h.to_string()).collect())
+    );
+    println!(
+        "{}",
+        format_row(widths.iter().map(|w| "-".repeat(*w)).collect())
+    );
+    for row in rows {
+        println!("{}", format_row(row.clone()));
+    }
+}
+
+pub fn import(
+    format: ImportFormat,
+    file: PathBuf,
+    dry_run: bool,
+    on_conflict: ConflictPolicy,
+) -> Result<(), AppError> {
+    let data = fs::read(&file)?;
+    let mut password = || -> Result<String, AppError> {
+        Ok(Password::with_theme(&ColorfulTheme::default())
+            .with_prompt("Backup password")
+            .interact()?)
+    };
+    let report = importer(format).parse(&data, &mut password)?;
+
+    // コードを生成できない項目は取り込まない
+    let mut skipped = report.skipped;
+    let mut credentials = Vec::new();
+    for credential in report.credentials {
+        match otp::generator(
+            credential.kind,
+            credential.params,
+            credential.secret.clone(),
+            credential.pin.clone(),
+        ) {
+            Ok(_) => credentials.push(credential),
+            Err(e) => skipped.push(SkippedEntry {
+                name: credential.name,
+                reason: e.to_string(),
+            }),
+        }
+    }
+
+    let master_password = secrets::get_master_password()?;
+    let mut secret_manager = SecretManager::load_secrets(&master_password)?;
+    let existing: HashSet<String> = secret_manager
+        .list_credentials()
+        .into_iter()
+        .cloned()
+        .collect();
+    let planned = plan(&existing, credentials, on_conflict);
+
+    let rows: Vec<Vec<String>> = planned
+        .iter()
+        .map(|(credential, action)| {
+            vec![
+                credential.name.clone(),
+                credential.issuer.clone().unwrap_or_default(),
+                format!("{:?}", credential.kind).to_lowercase(),
+                credential.params.to_string(),
+                credential.tags.join(","),
+                match action {
+                    Action::Add => "add".to_string(),
+                    Action::Rename(renamed) => format!("rename -> {}", renamed),
+                    Action::Overwrite => "overwrite".to_string(),
+                    Action::Skip => "skip (exists)".to_string(),
+                },
+            ]
+        })
+        .collect();
+    print_table(
+        &["NAME", "ISSUER", "TYPE", "PARAMS", "TAGS", "ACTION"],
+        &rows,
+    );
+    if !skipped.is_empty() {
+        println!();
+        println!("Skipped entries:");
+        for entry in &skipped {
+            println!("  - {}: {}", entry.name, entry.reason);
+        }
+    }
+
+    if dry_run {
+        println!();
+        println!("Dry run: nothing was imported.");
+        return Ok(());
+    }
+
+    let mut imported = 0;
+    for (credential, action) in planned {
+        let name = match action {
+            Action::Skip => continue,
+            Action::Rename(renamed) => renamed,
+            Action::Add | Action::Overwrite => credential.name,
+        };
+        secret_manager.insert_credential(Credential { name, ..credential });
+        imported += 1;
+    }
+    secret_manager.save_secrets(&master_password)?;
+
+    println!();
+    println!(
+        "Imported {} credential(s), skipped {}.",
+        imported,
+        rows.len() - imported + skipped.len()
+    );
+    Ok(())
+}
@@ -0,0 +1,79 @@
+use super::{ImportReport, Importer, display_name, kind_from_type};
+use crate::aegis::Vault;
+use crate::error::AppError;
+use crate::otp::{self, CredentialKind, HashAlgorithm, OtpParams};
+use crate::secrets::Credential;
+use std::collections::HashMap;
+
+/// Aegis Authenticator のバックアップ (平文 / パスワードスロットで暗号化)
+pub struct AegisImporter;
+
+impl Importer for AegisImporter {
+    fn parse(
+        &self,
+        data: &[u8],
+        password: &mut dyn FnMut() -> Result<String, AppError>,
+    ) -> Result<ImportReport, AppError> {
+        let vault: Vault = serde_json::from_slice(data)?;
+        let db = if vault.is_encrypted() {
+            vault.decrypt_db(&password()?)?
+        } else {
+            vault.decrypt_db("")?
+        };
+
+        let groups: HashMap<&str, &str> = db
+            .groups
+            .iter()
+            .map(|group| (group.uuid.as_str(), group.name.as_str()))
+            .collect();
+
+        let mut report = ImportReport::default();
+        for entry in &db.entries {
+            let name = display_name(&entry.issuer, &entry.name);
+            let Some(kind) = kind_from_type(&entry.entry_type) else {
+                report.skip(name, format!("unsupported type '{}'", entry.entry_type));
+                continue;
+            };
+            let Ok(secret) = otp::decode_base32(&entry.info.secret) else {
+                report.skip(name, "invalid secret");
+                continue;
+            };
+            // mOTP のシークレットは16進文字列として保存する
+            let secret = match kind {
+                CredentialKind::Motp => hex::encode(secret).into_bytes(),
+                _ => secret,
+            };
+            let Some(algorithm) = HashAlgorithm::parse(&entry.info.algo).or(match kind {
+                CredentialKind::Totp => None,
+                _ => Some(HashAlgorithm::Sha1),
+            }) else {
+                report.skip(name, format!("unsupported algorithm '{}'", entry.info.algo));
+                continue;
+            };
+
+            let mut tags: Vec<String> = entry
+                .groups
+                .iter()
+                .filter_map(|uuid| groups.get(uuid.as_str()).map(|name| name.to_string()))
+                .collect();
+            tags.extend(entry.group.clone());
+
+            report.credentials.push(Credential {
+                name,
+                issuer: Some(entry.issuer.clone()).filter(|issuer| !issuer.is_empty()),
+                kind,
+                params: OtpParams {
+                    algorithm,
+                    digits: entry.info.digits,
+                    period: entry.info.period.unwrap_or(30),
+                },
+                secret,
+                pin: entry.info.pin.clone(),
+                tags,
+                last_step: None,
+                recovery_codes: Vec::new(),
+            });
+        }
+        Ok(report)
+    }
+}
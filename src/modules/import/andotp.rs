@@ -0,0 +1,133 @@
+use super::{ImportReport, Importer, display_name, kind_from_type};
+use crate::error::AppError;
+use crate::otp::{self, HashAlgorithm, OtpParams};
+use crate::secrets::Credential;
+use aes_gcm::{
+    Aes256Gcm, Key, Nonce,
+    aead::{Aead, KeyInit},
+};
+use serde::Deserialize;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+const INT_LENGTH: usize = 4;
+const SALT_LENGTH: usize = 12;
+const NONCE_LENGTH: usize = 12;
+
+#[derive(Deserialize)]
+struct Entry {
+    secret: String,
+    #[serde(default)]
+    issuer: String,
+    #[serde(default)]
+    label: String,
+    #[serde(default = "default_digits")]
+    digits: usize,
+    #[serde(rename = "type")]
+    entry_type: String,
+    #[serde(default = "default_algorithm")]
+    algorithm: String,
+    #[serde(default)]
+    period: Option<u64>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+fn default_digits() -> usize {
+    6
+}
+
+fn default_algorithm() -> String {
+    "SHA1".into()
+}
+
+/// andOTP のバックアップ (平文 JSON / 暗号化 .json.aes)
+pub struct AndOtpImporter;
+
+impl AndOtpImporter {
+    /// 現行形式: 反復回数(4) + ソルト(12) + nonce(12) + 暗号文、鍵は PBKDF2-HMAC-SHA1
+    fn decrypt_pbkdf2(data: &[u8], password: &str) -> Option<Vec<u8>> {
+        if data.len() <= INT_LENGTH + SALT_LENGTH + NONCE_LENGTH {
+            return None;
+        }
+        let (iterations, rest) = data.split_at(INT_LENGTH);
+        let (salt, rest) = rest.split_at(SALT_LENGTH);
+        let (nonce, ciphertext) = rest.split_at(NONCE_LENGTH);
+        let iterations = u32::from_be_bytes(iterations.try_into().ok()?);
+        if iterations == 0 {
+            return None;
+        }
+        let mut key = [0u8; 32];
+        pbkdf2::pbkdf2_hmac::<Sha1>(password.as_bytes(), salt, iterations, &mut key);
+        Self::open(&key, nonce, ciphertext)
+    }
+
+    /// 旧形式: nonce(12) + 暗号文、鍵はパスワードの SHA-256
+    fn decrypt_legacy(data: &[u8], password: &str) -> Option<Vec<u8>> {
+        if data.len() <= NONCE_LENGTH {
+            return None;
+        }
+        let (nonce, ciphertext) = data.split_at(NONCE_LENGTH);
+        let key = Sha256::digest(password.as_bytes());
+        Self::open(&key, nonce, ciphertext)
+    }
+
+    fn open(key: &[u8], nonce: &[u8], ciphertext: &[u8]) -> Option<Vec<u8>> {
+        Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key))
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .ok()
+    }
+}
+
+impl Importer for AndOtpImporter {
+    fn parse(
+        &self,
+        data: &[u8],
+        password: &mut dyn FnMut() -> Result<String, AppError>,
+    ) -> Result<ImportReport, AppError> {
+        let entries: Vec<Entry> = match serde_json::from_slice(data) {
+            Ok(entries) => entries,
+            // JSON として読めない場合は暗号化されたバックアップとして扱う
+            Err(_) => {
+                let password = password()?;
+                let plaintext = Self::decrypt_pbkdf2(data, &password)
+                    .or_else(|| Self::decrypt_legacy(data, &password))
+                    .ok_or(AppError::DecryptionError)?;
+                serde_json::from_slice(&plaintext)?
+            }
+        };
+
+        let mut report = ImportReport::default();
+        for entry in entries {
+            let name = display_name(&entry.issuer, &entry.label);
+            let Some(kind) = kind_from_type(&entry.entry_type) else {
+                report.skip(name, format!("unsupported type '{}'", entry.entry_type));
+                continue;
+            };
+            let Ok(secret) = otp::decode_base32(&entry.secret) else {
+                report.skip(name, "invalid secret");
+                continue;
+            };
+            let Some(algorithm) = HashAlgorithm::parse(&entry.algorithm) else {
+                report.skip(name, format!("unsupported algorithm '{}'", entry.algorithm));
+                continue;
+            };
+            report.credentials.push(Credential {
+                name,
+                issuer: Some(entry.issuer).filter(|issuer| !issuer.is_empty()),
+                kind,
+                params: OtpParams {
+                    algorithm,
+                    digits: entry.digits,
+                    period: entry.period.unwrap_or(30),
+                },
+                secret,
+                pin: None,
+                tags: entry.tags,
+                last_step: None,
+                recovery_codes: Vec::new(),
+            });
+        }
+        Ok(report)
+    }
+}
@@ -0,0 +1,87 @@
+use super::{ImportReport, Importer, credential_from_otp_field, display_name};
+use crate::error::AppError;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Export {
+    #[serde(default)]
+    encrypted: bool,
+    #[serde(default)]
+    folders: Vec<Folder>,
+    #[serde(default)]
+    items: Vec<Item>,
+}
+
+#[derive(Deserialize)]
+struct Folder {
+    id: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Item {
+    name: String,
+    #[serde(default)]
+    folder_id: Option<String>,
+    #[serde(default)]
+    login: Option<Login>,
+}
+
+#[derive(Deserialize)]
+struct Login {
+    #[serde(default)]
+    username: Option<String>,
+    #[serde(default)]
+    totp: Option<String>,
+}
+
+/// Bitwarden の JSON エクスポート (login.totp を持つ項目のみ)
+pub struct BitwardenImporter;
+
+impl Importer for BitwardenImporter {
+    fn parse(
+        &self,
+        data: &[u8],
+        _password: &mut dyn FnMut() -> Result<String, AppError>,
+    ) -> Result<ImportReport, AppError> {
+        let export: Export = serde_json::from_slice(data)?;
+        if export.encrypted {
+            return Err(AppError::ImportError(
+                "Encrypted Bitwarden exports are not supported; export as unencrypted JSON".into(),
+            ));
+        }
+        let folders: HashMap<&str, &str> = export
+            .folders
+            .iter()
+            .map(|folder| (folder.id.as_str(), folder.name.as_str()))
+            .collect();
+
+        let mut report = ImportReport::default();
+        for item in &export.items {
+            let login = item.login.as_ref();
+            let username = login.and_then(|login| login.username.as_deref());
+            let name = display_name(&item.name, username.unwrap_or(""));
+            let Some(totp) = login
+                .and_then(|login| login.totp.as_deref())
+                .filter(|totp| !totp.trim().is_empty())
+            else {
+                report.skip(name, "no OTP data");
+                continue;
+            };
+            let tags = item
+                .folder_id
+                .as_deref()
+                .and_then(|id| folders.get(id))
+                .map(|folder| vec![folder.to_string()])
+                .unwrap_or_default();
+            match credential_from_otp_field(name.clone(), tags, totp) {
+                Ok(credential) => report.credentials.push(credential),
+                Err(e) => report.skip(name, e.to_string()),
+            }
+        }
+        Ok(report)
+    }
+}
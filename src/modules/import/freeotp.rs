@@ -0,0 +1,83 @@
+use super::{ImportReport, Importer, display_name, kind_from_type};
+use crate::error::AppError;
+use crate::otp::{HashAlgorithm, OtpParams};
+use crate::secrets::Credential;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct Backup {
+    tokens: Vec<Token>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Token {
+    #[serde(default = "default_algorithm")]
+    algo: String,
+    #[serde(default = "default_digits")]
+    digits: usize,
+    #[serde(default)]
+    issuer_ext: String,
+    #[serde(default)]
+    label: String,
+    #[serde(default = "default_period")]
+    period: u64,
+    /// Java の符号付きバイト配列
+    secret: Vec<i8>,
+    #[serde(rename = "type")]
+    token_type: String,
+}
+
+fn default_algorithm() -> String {
+    "SHA1".into()
+}
+
+fn default_digits() -> usize {
+    6
+}
+
+fn default_period() -> u64 {
+    30
+}
+
+/// FreeOTP+ のバックアップ (JSON)
+pub struct FreeOtpPlusImporter;
+
+impl Importer for FreeOtpPlusImporter {
+    fn parse(
+        &self,
+        data: &[u8],
+        _password: &mut dyn FnMut() -> Result<String, AppError>,
+    ) -> Result<ImportReport, AppError> {
+        let backup: Backup = serde_json::from_slice(data)?;
+
+        let mut report = ImportReport::default();
+        for token in backup.tokens {
+            let name = display_name(&token.issuer_ext, &token.label);
+            let Some(kind) = kind_from_type(&token.token_type) else {
+                report.skip(name, format!("unsupported type '{}'", token.token_type));
+                continue;
+            };
+            let Some(algorithm) = HashAlgorithm::parse(&token.algo) else {
+                report.skip(name, format!("unsupported algorithm '{}'", token.algo));
+                continue;
+            };
+            report.credentials.push(Credential {
+                name,
+                issuer: Some(token.issuer_ext).filter(|issuer| !issuer.is_empty()),
+                kind,
+                params: OtpParams {
+                    algorithm,
+                    digits: token.digits,
+                    period: token.period,
+                },
+                secret: token.secret.iter().map(|b| *b as u8).collect(),
+                pin: None,
+                tags: Vec::new(),
+                last_step: None,
+                recovery_codes: Vec::new(),
+            });
+        }
+        Ok(report)
+    }
+}
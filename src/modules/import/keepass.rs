@@ -0,0 +1,176 @@
+use super::{ImportReport, Importer, credential_from_otp_field, display_name};
+use crate::error::AppError;
+use crate::otp::{self, CredentialKind, OtpParams};
+use crate::secrets::Credential;
+use roxmltree::{Document, Node};
+
+/// KeePassXC / KeePass の XML エクスポート
+///
+/// KeePassXC の `otp` 属性 (otpauth:// または KeeOTP 形式) と、
+/// 旧来の `TOTP Seed` / `TOTP Settings` 属性に対応する。
+pub struct KeePassImporter;
+
+/// エントリーの String 要素から値を取り出す
+fn field<'a>(entry: Node<'a, 'a>, key: &str) -> Option<&'a str> {
+    entry
+        .children()
+        .filter(|node| node.has_tag_name("String"))
+        .find(|node| {
+            node.children()
+                .any(|child| child.has_tag_name("Key") && child.text() == Some(key))
+        })
+        .and_then(|node| node.children().find(|child| child.has_tag_name("Value")))
+        .and_then(|value| value.text())
+}
+
+/// 旧来の `TOTP Settings` ("30;6" や Steam の "30;S") を解釈する
+fn legacy_credential(
+    name: String,
+    tags: Vec<String>,
+    seed: &str,
+    settings: Option<&str>,
+) -> Result<Credential, AppError> {
+    let mut kind = CredentialKind::Totp;
+    let mut params = OtpParams::default();
+    if let Some(settings) = settings {
+        let mut parts = settings.split(';');
+        if let Some(period) = parts.next() {
+            params.period = period.trim().parse().map_err(|_| {
+                AppError::ImportError(format!("invalid TOTP Settings '{}'", settings))
+            })?;
+        }
+        match parts.next().map(str::trim) {
+            Some("S") => kind = CredentialKind::Steam,
+            Some(digits) => {
+                params.digits = digits.parse().map_err(|_| {
+                    AppError::ImportError(format!("invalid TOTP Settings '{}'", settings))
+                })?
+            }
+            None => {}
+        }
+    }
+    Ok(Credential {
+        name,
+        issuer: None,
+        kind,
+        params,
+        secret: otp::decode_base32(seed)?,
+        pin: None,
+        tags,
+        last_step: None,
+        recovery_codes: Vec::new(),
+    })
+}
+
+/// KeeOTP 形式 ("key=...&step=30&size=6") を解釈する
+fn keeotp_credential(name: String, tags: Vec<String>, value: &str) -> Result<Credential, AppError> {
+    let mut secret = None;
+    let mut params = OtpParams::default();
+    for pair in value.split('&') {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        match key {
+            "key" => secret = Some(otp::decode_base32(value)?),
+            "step" => params.period = value.parse().unwrap_or(params.period),
+            "size" => params.digits = value.parse().unwrap_or(params.digits),
+            "otpHashMode" => {
+                params.algorithm = otp::HashAlgorithm::parse(value).ok_or_else(|| {
+                    AppError::ImportError(format!("unsupported algorithm '{}'", value))
+                })?
+            }
+            _ => {}
+        }
+    }
+    Ok(Credential {
+        name,
+        issuer: None,
+        kind: CredentialKind::Totp,
+        params,
+        secret: secret.ok_or(AppError::InvalidKey)?,
+        pin: None,
+        tags,
+        last_step: None,
+        recovery_codes: Vec::new(),
+    })
+}
+
+impl KeePassImporter {
+    fn walk(group: Node, path: &mut Vec<String>, report: &mut ImportReport) {
+        let group_name = group
+            .children()
+            .find(|node| node.has_tag_name("Name"))
+            .and_then(|node| node.text())
+            .unwrap_or("");
+        path.push(group_name.to_string());
+
+        for child in group.children() {
+            if child.has_tag_name("Group") {
+                Self::walk(child, path, report);
+            } else if child.has_tag_name("Entry") {
+                Self::entry(child, path, report);
+            }
+        }
+        path.pop();
+    }
+
+    fn entry(entry: Node, path: &[String], report: &mut ImportReport) {
+        let title = field(entry, "Title").unwrap_or("");
+        let name = display_name(title, field(entry, "UserName").unwrap_or(""));
+        // ルートグループ名 (データベース名) はタグに含めない
+        let tags = path
+            .iter()
+            .skip(1)
+            .filter(|name| !name.is_empty())
+            .cloned()
+            .collect::<Vec<_>>();
+        let tags = if tags.is_empty() {
+            Vec::new()
+        } else {
+            vec![tags.join("/")]
+        };
+
+        let result = match (
+            field(entry, "otp"),
+            field(entry, "TOTP Seed"),
+            field(entry, "TOTP Settings"),
+        ) {
+            (Some(otp), _, _) if otp.trim().contains("key=") && !otp::is_uri(otp.trim()) => {
+                keeotp_credential(name.clone(), tags, otp.trim())
+            }
+            (Some(otp), _, _) => credential_from_otp_field(name.clone(), tags, otp),
+            (None, Some(seed), settings) => legacy_credential(name.clone(), tags, seed, settings),
+            (None, None, _) => {
+                report.skip(name, "no OTP data");
+                return;
+            }
+        };
+        match result {
+            Ok(credential) => report.credentials.push(credential),
+            Err(e) => report.skip(name, e.to_string()),
+        }
+    }
+}
+
+impl Importer for KeePassImporter {
+    fn parse(
+        &self,
+        data: &[u8],
+        _password: &mut dyn FnMut() -> Result<String, AppError>,
+    ) -> Result<ImportReport, AppError> {
+        let text = std::str::from_utf8(data)
+            .map_err(|_| AppError::ImportError("KeePass XML must be UTF-8".into()))?;
+        let document = Document::parse(text)
+            .map_err(|e| AppError::ImportError(format!("Invalid KeePass XML: {}", e)))?;
+        let root = document
+            .root_element()
+            .children()
+            .find(|node| node.has_tag_name("Root"))
+            .ok_or_else(|| AppError::ImportError("KeePass XML has no Root element".into()))?;
+
+        // History 内の過去のエントリーは Entry の子要素なので対象にならない
+        let mut report = ImportReport::default();
+        for group in root.children().filter(|node| node.has_tag_name("Group")) {
+            Self::walk(group, &mut Vec::new(), &mut report);
+        }
+        Ok(report)
+    }
+}
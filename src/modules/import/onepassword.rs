@@ -0,0 +1,69 @@
+use super::{ImportReport, Importer, credential_from_otp_field, display_name};
+use crate::error::AppError;
+
+/// タイトル列として扱う見出し
+const TITLE_HEADERS: &[&str] = &["title", "name"];
+/// ユーザー名列として扱う見出し
+const USERNAME_HEADERS: &[&str] = &["username", "user name"];
+/// OTP 列として扱う見出し (1Password のバージョンによって異なる)
+const OTP_HEADERS: &[&str] = &["otpauth", "one-time password", "otp", "totp"];
+const TAG_HEADERS: &[&str] = &["tags"];
+
+/// 1Password の CSV エクスポート
+pub struct OnePasswordImporter;
+
+fn column(headers: &csv::StringRecord, candidates: &[&str]) -> Option<usize> {
+    headers
+        .iter()
+        .position(|header| candidates.contains(&header.trim().to_lowercase().as_str()))
+}
+
+impl Importer for OnePasswordImporter {
+    fn parse(
+        &self,
+        data: &[u8],
+        _password: &mut dyn FnMut() -> Result<String, AppError>,
+    ) -> Result<ImportReport, AppError> {
+        let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(data);
+        let headers = reader
+            .headers()
+            .map_err(|e| AppError::ImportError(format!("Invalid CSV: {}", e)))?
+            .clone();
+        let title_column = column(&headers, TITLE_HEADERS)
+            .ok_or_else(|| AppError::ImportError("CSV has no Title column".into()))?;
+        let otp_column = column(&headers, OTP_HEADERS)
+            .ok_or_else(|| AppError::ImportError("CSV has no OTP column".into()))?;
+        let username_column = column(&headers, USERNAME_HEADERS);
+        let tag_column = column(&headers, TAG_HEADERS);
+
+        let mut report = ImportReport::default();
+        for record in reader.records() {
+            let record =
+                record.map_err(|e| AppError::ImportError(format!("Invalid CSV: {}", e)))?;
+            let title = record.get(title_column).unwrap_or("");
+            let username = username_column
+                .and_then(|index| record.get(index))
+                .unwrap_or("");
+            let name = display_name(title, username);
+            let Some(otp) = record.get(otp_column).filter(|otp| !otp.trim().is_empty()) else {
+                report.skip(name, "no OTP data");
+                continue;
+            };
+            let tags = tag_column
+                .and_then(|index| record.get(index))
+                .map(|tags| {
+                    tags.split([',', ';'])
+                        .map(str::trim)
+                        .filter(|tag| !tag.is_empty())
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default();
+            match credential_from_otp_field(name.clone(), tags, otp) {
+                Ok(credential) => report.credentials.push(credential),
+                Err(e) => report.skip(name, e.to_string()),
+            }
+        }
+        Ok(report)
+    }
+}
@@ -0,0 +1,153 @@
+use super::{ImportReport, Importer, display_name, kind_from_type};
+use crate::error::AppError;
+use crate::otp::{self, HashAlgorithm, OtpParams};
+use crate::secrets::Credential;
+use aes_gcm::{
+    Aes256Gcm, Key, Nonce,
+    aead::{Aead, KeyInit},
+};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use serde::Deserialize;
+use sha2::Sha256;
+use std::collections::HashMap;
+
+/// 暗号化されたバックアップの PBKDF2 反復回数
+const PBKDF2_ITERATIONS: u32 = 10_000;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Backup {
+    #[serde(default)]
+    services: Vec<Service>,
+    #[serde(default)]
+    groups: Vec<Group>,
+    /// "暗号文:ソルト:nonce" (いずれも Base64)
+    #[serde(default)]
+    services_encrypted: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Service {
+    name: String,
+    secret: String,
+    #[serde(default)]
+    otp: Otp,
+    #[serde(default)]
+    group_id: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct Otp {
+    #[serde(default)]
+    account: Option<String>,
+    #[serde(default)]
+    issuer: Option<String>,
+    #[serde(default)]
+    digits: Option<usize>,
+    #[serde(default)]
+    period: Option<u64>,
+    #[serde(default)]
+    algorithm: Option<String>,
+    #[serde(default)]
+    token_type: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Group {
+    id: String,
+    name: String,
+}
+
+/// 2FAS のバックアップ (.2fas)
+pub struct TwoFasImporter;
+
+impl TwoFasImporter {
+    fn decrypt_services(encrypted: &str, password: &str) -> Result<Vec<Service>, AppError> {
+        let parts: Vec<Vec<u8>> = encrypted
+            .split(':')
+            .map(|part| STANDARD.decode(part))
+            .collect::<Result<_, _>>()
+            .map_err(|_| AppError::ImportError("Invalid 2FAS encrypted payload".into()))?;
+        let [ciphertext, salt, nonce] = parts.as_slice() else {
+            return Err(AppError::ImportError(
+                "Invalid 2FAS encrypted payload".into(),
+            ));
+        };
+        if nonce.len() != 12 {
+            return Err(AppError::DecryptionError);
+        }
+        let mut key = [0u8; 32];
+        pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), salt, PBKDF2_ITERATIONS, &mut key);
+        let plaintext = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))
+            .decrypt(Nonce::from_slice(nonce), ciphertext.as_slice())
+            .map_err(|_| AppError::DecryptionError)?;
+        Ok(serde_json::from_slice(&plaintext)?)
+    }
+}
+
+impl Importer for TwoFasImporter {
+    fn parse(
+        &self,
+        data: &[u8],
+        password: &mut dyn FnMut() -> Result<String, AppError>,
+    ) -> Result<ImportReport, AppError> {
+        let backup: Backup = serde_json::from_slice(data)?;
+        let services = match &backup.services_encrypted {
+            Some(encrypted) if backup.services.is_empty() => {
+                Self::decrypt_services(encrypted, &password()?)?
+            }
+            _ => backup.services,
+        };
+        let groups: HashMap<&str, &str> = backup
+            .groups
+            .iter()
+            .map(|group| (group.id.as_str(), group.name.as_str()))
+            .collect();
+
+        let mut report = ImportReport::default();
+        for service in services {
+            let issuer = service.otp.issuer.clone().unwrap_or(service.name.clone());
+            let name = display_name(&issuer, service.otp.account.as_deref().unwrap_or(""));
+            let token_type = service.otp.token_type.as_deref().unwrap_or("TOTP");
+            let Some(kind) = kind_from_type(token_type) else {
+                report.skip(name, format!("unsupported type '{}'", token_type));
+                continue;
+            };
+            let Ok(secret) = otp::decode_base32(&service.secret) else {
+                report.skip(name, "invalid secret");
+                continue;
+            };
+            let algorithm_name = service.otp.algorithm.as_deref().unwrap_or("SHA1");
+            let Some(algorithm) = HashAlgorithm::parse(algorithm_name) else {
+                report.skip(name, format!("unsupported algorithm '{}'", algorithm_name));
+                continue;
+            };
+            let tags = service
+                .group_id
+                .as_deref()
+                .and_then(|id| groups.get(id))
+                .map(|group| vec![group.to_string()])
+                .unwrap_or_default();
+
+            report.credentials.push(Credential {
+                name,
+                issuer: Some(issuer).filter(|issuer| !issuer.is_empty()),
+                kind,
+                params: OtpParams {
+                    algorithm,
+                    digits: service.otp.digits.unwrap_or(6),
+                    period: service.otp.period.unwrap_or(30),
+                },
+                secret,
+                pin: None,
+                tags,
+                last_step: None,
+                recovery_codes: Vec::new(),
+            });
+        }
+        Ok(report)
+    }
+}
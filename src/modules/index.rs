@@ -0,0 +1,101 @@
+//! 認証情報の名前の索引 (暗号化しない)
+//!
+//! シェルの補完・プロンプトやステータスバーのウィジェット・`auth list --no-unlock` で
+//! 保管庫を開かずに名前を知るため、config.toml で有効にした場合だけ保管庫を読み書きするたびに
+//! 名前・発行者・タグを平文のファイルに書き出す。シークレットや PIN は含まない。
+//!
+//! ```toml
+//! [index]
+//! enabled = true
+//! path = "/path/to/names.toml"   # 省略時は設定ディレクトリの names.toml
+//! ```
+
+use crate::config::{self, Config};
+use crate::error::AppError;
+use crate::secrets::Credential;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct NameIndex {
+    #[serde(default)]
+    pub credentials: Vec<IndexEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct IndexEntry {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issuer: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl From<&Credential> for IndexEntry {
+    fn from(credential: &Credential) -> Self {
+        IndexEntry {
+            name: credential.name.clone(),
+            issuer: credential.issuer.clone(),
+            tags: credential.tags.clone(),
+        }
+    }
+}
+
+impl NameIndex {
+    /// 認証情報から索引を作る (名前順)
+    pub fn from_credentials<'a>(credentials: impl IntoIterator<Item = &'a Credential>) -> Self {
+        let mut credentials: Vec<IndexEntry> =
+            credentials.into_iter().map(IndexEntry::from).collect();
+        credentials.sort_by(|a, b| a.name.cmp(&b.name));
+        NameIndex { credentials }
+    }
+
+    /// 索引を読み込む (まだ書き出されていなければ `None`)
+    pub fn read(path: &Path) -> Result<Option<Self>, AppError> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(toml::from_str(&fs::read_to_string(path)?)?))
+    }
+
+    /// 内容が変わっている場合だけ書き出す
+    pub fn write(&self, path: &Path) -> Result<(), AppError> {
+        if NameIndex::read(path).ok().flatten().as_ref() == Some(self) {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, toml::to_string(self)?)?;
+        Ok(())
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.credentials.iter().map(|entry| entry.name.as_str())
+    }
+}
+
+/// 設定で有効にした索引の場所 (無効なら `None`)
+pub fn configured_path() -> Result<Option<PathBuf>, AppError> {
+    let index = Config::load()?.index;
+    if !index.enabled {
+        return Ok(None);
+    }
+    match index.path {
+        Some(path) => Ok(Some(path)),
+        None => Ok(Some(config::get_config_dir()?.join("names.toml"))),
+    }
+}
+
+/// 保管庫を開かずに索引を読む
+pub fn load() -> Result<NameIndex, AppError> {
+    let Some(path) = configured_path()? else {
+        return Err(AppError::IndexUnavailable(
+            "it is disabled in config.toml".into(),
+        ));
+    };
+    NameIndex::read(&path)?.ok_or_else(|| {
+        AppError::IndexUnavailable(format!("{} has not been written yet", path.display()))
+    })
+}
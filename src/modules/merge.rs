@@ -0,0 +1,118 @@
+//! 保管庫の内容の三方向マージ
+//!
+//! 最後に同期した時点の内容 (base) と、手元 (local)・同期先 (remote) の内容を
+//! 認証情報ごとに比較し、片方だけが変更したものはその変更を採用する。
+
+use crate::secrets::{Credential, Snapshot};
+use std::collections::BTreeSet;
+
+/// 両方で異なる変更が行われた認証情報
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeConflict {
+    pub name: String,
+    /// 手元の内容を退避した名前
+    pub kept_as: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MergeResult {
+    pub merged: Snapshot,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// `verify --record` による last_step の違いや、リカバリーコードの使用状況の違いだけなら
+/// 同じ認証情報とみなす
+fn same_except_usage(a: &Credential, b: &Credential) -> bool {
+    let codes = |credential: &Credential| {
+        credential
+            .recovery_codes
+            .iter()
+            .map(|code| code.code.clone())
+            .collect::<Vec<_>>()
+    };
+    codes(a) == codes(b)
+        && Credential {
+            last_step: b.last_step,
+            recovery_codes: b.recovery_codes.clone(),
+            ..a.clone()
+        } == *b
+}
+
+/// 両方の使用状況を合わせる (新しい方のステップ、どちらかで使用済みのコード)
+fn combine_usage(a: &Credential, b: &Credential) -> Credential {
+    let mut combined = Credential {
+        last_step: a.last_step.max(b.last_step),
+        ..a.clone()
+    };
+    for (code, other) in combined.recovery_codes.iter_mut().zip(&b.recovery_codes) {
+        code.used |= other.used;
+    }
+    combined
+}
+
+/// 衝突した手元の内容を退避する名前 ("name (conflict)", "name (conflict 2)", ...)
+fn conflict_name(name: &str, merged: &Snapshot) -> String {
+    let mut candidate = format!("{} (conflict)", name);
+    let mut index = 2;
+    while merged.credentials.contains_key(&candidate) {
+        candidate = format!("{} (conflict {})", name, index);
+        index += 1;
+    }
+    candidate
+}
+
+/// 認証情報ごとに三方向マージする
+///
+/// 両方で異なる変更があった場合は同期先の内容を元の名前で残し、
+/// 手元の内容を別名で退避する。変更と削除が衝突した場合は変更を優先する。
+pub fn merge(base: &Snapshot, local: &Snapshot, remote: &Snapshot) -> MergeResult {
+    let mut result = MergeResult::default();
+
+    let names: BTreeSet<&String> = base
+        .credentials
+        .keys()
+        .chain(local.credentials.keys())
+        .chain(remote.credentials.keys())
+        .collect();
+    let mut conflicted = Vec::new();
+
+    for name in names {
+        let b = base.credentials.get(name);
+        let l = local.credentials.get(name);
+        let r = remote.credentials.get(name);
+
+        let chosen = match (l, r) {
+            (None, None) => None,
+            (Some(l), Some(r)) if same_except_usage(l, r) => Some(combine_usage(l, r)),
+            // 片方だけが変更 (または削除) した場合はその変更を採用
+            (l, r) if l == b => r.cloned(),
+            (l, r) if r == b => l.cloned(),
+            // 削除と変更が衝突した場合は変更を残す
+            (Some(l), None) => Some(l.clone()),
+            (None, Some(r)) => Some(r.clone()),
+            (Some(l), Some(r)) => {
+                conflicted.push(l.clone());
+                Some(r.clone())
+            }
+        };
+        if let Some(credential) = chosen {
+            result.merged.credentials.insert(name.clone(), credential);
+        }
+    }
+
+    for credential in conflicted {
+        let kept_as = conflict_name(&credential.name, &result.merged);
+        result.conflicts.push(MergeConflict {
+            name: credential.name.clone(),
+            kept_as: kept_as.clone(),
+        });
+        result.merged.credentials.insert(
+            kept_as.clone(),
+            Credential {
+                name: kept_as,
+                ..credential
+            },
+        );
+    }
+    result
+}
@@ -0,0 +1,411 @@
+mod motp;
+mod yandex;
+pub use motp::Motp;
+pub use yandex::Yandex;
+
+use crate::error::AppError;
+use crate::secrets::Credential;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use totp_rs::{Algorithm, TOTP};
+
+/// 認証情報の種類
+#[derive(Serialize, Deserialize, ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CredentialKind {
+    /// 標準的な TOTP (SHA1 / 6桁 / 30秒)
+    #[default]
+    Totp,
+    /// Steam Guard (SHA1 / 30秒 / 独自アルファベット5文字)
+    Steam,
+    /// mOTP (MD5 / 10秒 / 16進6桁, PIN 必須)
+    Motp,
+    /// Yandex Key (HMAC-SHA256 / 30秒 / 英小文字8文字, PIN 必須)
+    Yandex,
+}
+
+/// TOTP のハッシュアルゴリズム
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum HashAlgorithm {
+    #[default]
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl HashAlgorithm {
+    /// "SHA1" / "sha256" などの表記から変換
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_uppercase().replace('-', "").as_str() {
+            "SHA1" => Some(HashAlgorithm::Sha1),
+            "SHA256" => Some(HashAlgorithm::Sha256),
+            "SHA512" => Some(HashAlgorithm::Sha512),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HashAlgorithm::Sha1 => f.write_str("SHA1"),
+            HashAlgorithm::Sha256 => f.write_str("SHA256"),
+            HashAlgorithm::Sha512 => f.write_str("SHA512"),
+        }
+    }
+}
+
+/// TOTP のパラメーター (totp 以外の種類では固定値が使われる)
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OtpParams {
+    pub algorithm: HashAlgorithm,
+    pub digits: usize,
+    pub period: u64,
+}
+
+impl Default for OtpParams {
+    fn default() -> Self {
+        OtpParams {
+            algorithm: HashAlgorithm::Sha1,
+            digits: 6,
+            period: 30,
+        }
+    }
+}
+
+impl std::fmt::Display for OtpParams {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}/{}s", self.algorithm, self.digits, self.period)
+    }
+}
+
+impl CredentialKind {
+    /// コード生成に PIN が必要かどうか
+    pub fn requires_pin(&self) -> bool {
+        matches!(self, CredentialKind::Motp | CredentialKind::Yandex)
+    }
+}
+
+/// ワンタイムコードの生成器
+pub trait OtpGenerator {
+    /// 指定した UNIX 時刻 (秒) におけるコード
+    fn generate(&self, timestamp: u64) -> String;
+    /// コードが切り替わる間隔 (秒)
+    fn period(&self) -> u64;
+}
+
+impl OtpGenerator for TOTP {
+    fn generate(&self, timestamp: u64) -> String {
+        TOTP::generate(self, timestamp)
+    }
+
+    fn period(&self) -> u64 {
+        self.step
+    }
+}
+
+/// URI から読み取った認証情報
+#[derive(Debug, Clone)]
+pub struct ParsedUri {
+    pub name: Option<String>,
+    pub issuer: Option<String>,
+    pub kind: CredentialKind,
+    pub params: OtpParams,
+    pub secret: Vec<u8>,
+}
+
+/// 種類に応じた生成器を作成
+pub fn generator(
+    kind: CredentialKind,
+    params: OtpParams,
+    secret: Vec<u8>,
+    pin: Option<String>,
+) -> Result<Box<dyn OtpGenerator>, AppError> {
+    let require_pin = || {
+        pin.clone()
+            .ok_or_else(|| AppError::InvalidInput("PIN is required for this credential".into()))
+    };
+    match kind {
+        CredentialKind::Totp => {
+            if secret.is_empty() {
+                return Err(AppError::InvalidKey);
+            }
+            if !(6..=8).contains(&params.digits) || params.period == 0 {
+                return Err(AppError::InvalidInput(format!(
+                    "Unsupported TOTP parameters: {}",
+                    params
+                )));
+            }
+            let algorithm = match params.algorithm {
+                HashAlgorithm::Sha1 => Algorithm::SHA1,
+                HashAlgorithm::Sha256 => Algorithm::SHA256,
+                HashAlgorithm::Sha512 => Algorithm::SHA512,
+            };
+            // 80bit のシークレットも広く使われているため、長さの検査は行わない
+            Ok(Box::new(TOTP::new_unchecked(
+                algorithm,
+                params.digits,
+                1,
+                params.period,
+                secret,
+                None,
+                String::new(),
+            )))
+        }
+        CredentialKind::Steam => Ok(Box::new(TOTP::new_steam(secret, String::new()))),
+        CredentialKind::Motp => Ok(Box::new(Motp::new(secret, require_pin()?)?)),
+        CredentialKind::Yandex => Ok(Box::new(Yandex::new(secret, require_pin()?)?)),
+    }
+}
+
+/// 保存済みの認証情報から生成器を作成
+pub fn generator_for(credential: &Credential) -> Result<Box<dyn OtpGenerator>, AppError> {
+    generator(
+        credential.kind,
+        credential.params,
+        credential.secret.clone(),
+        credential.pin.clone(),
+    )
+}
+
+/// 表示用にコードを整形 (6桁の数字のみ "123 456" 形式に区切る)
+pub fn format_code(code: &str) -> String {
+    if code.len() == 6 && code.chars().all(|c| c.is_ascii_digit()) {
+        format!("{} {}", &code[0..3], &code[3..6])
+    } else {
+        code.to_string()
+    }
+}
+
+/// 入力されたキーが URI かどうか
+pub fn is_uri(key: &str) -> bool {
+    key.starts_with("otpauth://") || key.starts_with("steam://")
+}
+
+/// 空白や小文字、パディングの有無を許容して Base32 をデコード
+pub fn decode_base32(key: &str) -> Result<Vec<u8>, AppError> {
+    let key: String = key
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '=')
+        .collect::<String>()
+        .to_uppercase();
+    base32::decode(base32::Alphabet::Rfc4648 { padding: false }, &key).ok_or(AppError::InvalidKey)
+}
+
+/// 入力されたキーで読み替えた文字
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyCorrection {
+    /// 入力での位置 (1 始まり)
+    pub position: usize,
+    pub from: char,
+    pub to: char,
+}
+
+/// 正規化してデコードしたキー
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NormalizedKey {
+    pub secret: Vec<u8>,
+    /// 0 を O、1 を I として読み替えた箇所 (利用者に確認を促す)
+    pub corrections: Vec<KeyCorrection>,
+}
+
+/// 手入力された Base32 のキーを正規化してデコード
+///
+/// 空白・ハイフン・末尾のパディングを無視し、小文字を受け付ける。Base32 にない
+/// 0 と 1 は見間違えやすい O と I として読み替える。それ以外の不正な文字は位置を示して拒否する。
+pub fn normalize_base32(key: &str) -> Result<NormalizedKey, AppError> {
+    let mut cleaned = String::new();
+    let mut corrections = Vec::new();
+    let mut padding = false;
+    for (index, original) in key.chars().enumerate() {
+        let position = index + 1;
+        if original.is_whitespace() || original == '-' {
+            continue;
+        }
+        if original == '=' {
+            padding = true;
+            continue;
+        }
+        let upper = original.to_ascii_uppercase();
+        let c = match upper {
+            '0' => 'O',
+            '1' => 'I',
+            other => other,
+        };
+        if padding || !matches!(c, 'A'..='Z' | '2'..='7') {
+            return Err(AppError::InvalidInput(format!(
+                "Invalid character '{}' at position {} of the key",
+                original, position
+            )));
+        }
+        if c != upper {
+            corrections.push(KeyCorrection {
+                position,
+                from: upper,
+                to: c,
+            });
+        }
+        cleaned.push(c);
+    }
+    // 8文字単位の末尾に 1, 3, 6 文字だけ余る長さは Base32 として作れない
+    if cleaned.is_empty() || matches!(cleaned.len() % 8, 1 | 3 | 6) {
+        return Err(AppError::InvalidInput(format!(
+            "The key has {} Base32 characters, which is not a valid length; check for missing or extra characters",
+            cleaned.len()
+        )));
+    }
+    let secret = base32::decode(base32::Alphabet::Rfc4648 { padding: false }, &cleaned)
+        .ok_or(AppError::InvalidKey)?;
+    Ok(NormalizedKey {
+        secret,
+        corrections,
+    })
+}
+
+/// 新しく登録するシークレットの最小の長さ (バイト)
+///
+/// SHA1 では広く使われている 80bit を、SHA256 / SHA512 では RFC 4226 の下限の 128bit を求める。
+/// mOTP のシークレットは16進文字列で、生成器側で検査する。
+pub fn min_secret_len(kind: CredentialKind, params: OtpParams) -> usize {
+    match (kind, params.algorithm) {
+        (CredentialKind::Motp, _) => 1,
+        (CredentialKind::Yandex, _) => 16,
+        (CredentialKind::Totp, HashAlgorithm::Sha256 | HashAlgorithm::Sha512) => 16,
+        _ => 10,
+    }
+}
+
+/// 短すぎるシークレットを拒否する
+pub fn check_secret_length(
+    kind: CredentialKind,
+    params: OtpParams,
+    secret: &[u8],
+) -> Result<(), AppError> {
+    let min = min_secret_len(kind, params);
+    if secret.len() < min {
+        return Err(AppError::InvalidInput(format!(
+            "The key is too short: {} bits, but at least {} bits are required for {}",
+            secret.len() * 8,
+            min * 8,
+            match kind {
+                CredentialKind::Totp => params.algorithm.to_string(),
+                other => format!("{:?}", other),
+            }
+        )));
+    }
+    Ok(())
+}
+
+/// otpauth:// または steam:// 形式の URI を解析
+pub fn parse_uri(uri: &str) -> Result<ParsedUri, AppError> {
+    // steam://<Base32シークレット> 形式 (一部のパスワードマネージャーが使用)
+    if let Some(secret) = uri.strip_prefix("steam://") {
+        return Ok(ParsedUri {
+            name: None,
+            issuer: Some("Steam".into()),
+            kind: CredentialKind::Steam,
+            params: OtpParams::default(),
+            secret: decode_base32(secret)?,
+        });
+    }
+
+    let totp = TOTP::from_url_unchecked(uri)
+        .map_err(|e| AppError::InvalidInput(format!("Invalid otpauth URI: {}", e)))?;
+    let (kind, algorithm) = match totp.algorithm {
+        Algorithm::Steam => (CredentialKind::Steam, HashAlgorithm::Sha1),
+        Algorithm::SHA1 => (CredentialKind::Totp, HashAlgorithm::Sha1),
+        Algorithm::SHA256 => (CredentialKind::Totp, HashAlgorithm::Sha256),
+        Algorithm::SHA512 => (CredentialKind::Totp, HashAlgorithm::Sha512),
+    };
+    let name = if totp.account_name.is_empty() {
+        None
+    } else {
+        Some(totp.account_name)
+    };
+
+    Ok(ParsedUri {
+        name,
+        issuer: totp.issuer,
+        kind,
+        params: OtpParams {
+            algorithm,
+            digits: totp.digits,
+            period: totp.step,
+        },
+        secret: totp.secret,
+    })
+}
+
+/// URI の要素をパーセントエンコード
+pub(crate) fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// 認証情報を otpauth:// URI に変換 (PIN は含めない)
+///
+/// Steam は issuer=Steam の TOTP として、mOTP と Yandex は Aegis と同じ
+/// otpauth://motp, otpauth://yandex として書き出す。
+pub fn to_uri(credential: &Credential) -> String {
+    let account = crate::export::account_name(credential);
+    let label = match &credential.issuer {
+        Some(issuer) => format!("{}:{}", percent_encode(issuer), percent_encode(account)),
+        None => percent_encode(account),
+    };
+    // mOTP のシークレットは16進文字列で保存しているため、バイト列に戻す
+    let secret = match credential.kind {
+        CredentialKind::Motp => {
+            hex::decode(&credential.secret).unwrap_or_else(|_| credential.secret.clone())
+        }
+        _ => credential.secret.clone(),
+    };
+    let mut query = vec![format!(
+        "secret={}",
+        base32::encode(base32::Alphabet::Rfc4648 { padding: false }, &secret)
+    )];
+    let issuer = match credential.kind {
+        CredentialKind::Steam => Some("Steam"),
+        _ => credential.issuer.as_deref(),
+    };
+    if let Some(issuer) = issuer {
+        query.push(format!("issuer={}", percent_encode(issuer)));
+    }
+    let host = match credential.kind {
+        CredentialKind::Totp => {
+            let params = credential.params;
+            query.push(format!("algorithm={}", params.algorithm));
+            query.push(format!("digits={}", params.digits));
+            query.push(format!("period={}", params.period));
+            "totp"
+        }
+        CredentialKind::Steam => "totp",
+        CredentialKind::Motp => "motp",
+        CredentialKind::Yandex => "yandex",
+    };
+    format!("otpauth://{}/{}?{}", host, label, query.join("&"))
+}
+
+/// 紙などに書き写す形式のシークレット (mOTP は16進、それ以外は Base32)
+pub fn secret_text(credential: &Credential) -> String {
+    match credential.kind {
+        CredentialKind::Motp => String::from_utf8_lossy(&credential.secret).into_owned(),
+        _ => base32::encode(
+            base32::Alphabet::Rfc4648 { padding: false },
+            &credential.secret,
+        ),
+    }
+}
+
+/// 書き写したシークレットの誤りを見つけるためのチェックサム (SHA-256 の先頭3バイト)
+pub fn secret_checksum(secret: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+    hex::encode_upper(&Sha256::digest(secret)[..3])
+}
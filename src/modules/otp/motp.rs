@@ -0,0 +1,37 @@
+use super::OtpGenerator;
+use crate::error::AppError;
+use md5::{Digest, Md5};
+
+/// mOTP (Mobile-OTP)
+///
+/// `md5(エポック秒 / 10 + シークレット + PIN)` の16進表記の先頭6文字をコードとする。
+/// シークレットは16進文字列のまま保存されている。
+pub struct Motp {
+    secret: String,
+    pin: String,
+}
+
+impl Motp {
+    pub fn new(secret: Vec<u8>, pin: String) -> Result<Self, AppError> {
+        let secret = String::from_utf8(secret).map_err(|_| AppError::InvalidKey)?;
+        if secret.is_empty() || !secret.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(AppError::InvalidKey);
+        }
+        Ok(Motp {
+            secret: secret.to_lowercase(),
+            pin,
+        })
+    }
+}
+
+impl OtpGenerator for Motp {
+    fn generate(&self, timestamp: u64) -> String {
+        let input = format!("{}{}{}", timestamp / self.period(), self.secret, self.pin);
+        let digest = Md5::digest(input.as_bytes());
+        digest[..3].iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn period(&self) -> u64 {
+        10
+    }
+}
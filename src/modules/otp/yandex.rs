@@ -0,0 +1,60 @@
+use super::OtpGenerator;
+use crate::error::AppError;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+/// Yandex Key のシークレット長 (Base32 文字列には末尾にチェックサムが付く)
+const SECRET_LENGTH: usize = 16;
+const DIGITS: u32 = 8;
+
+/// Yandex Key
+///
+/// `sha256(PIN + シークレット)` を鍵とした HMAC-SHA256 を動的切り捨てし、
+/// 26^8 の剰余を英小文字8文字で表現する。
+pub struct Yandex {
+    key: Vec<u8>,
+}
+
+impl Yandex {
+    pub fn new(secret: Vec<u8>, pin: String) -> Result<Self, AppError> {
+        if secret.len() < SECRET_LENGTH {
+            return Err(AppError::InvalidKey);
+        }
+        let mut hasher = Sha256::new();
+        hasher.update(pin.as_bytes());
+        hasher.update(&secret[..SECRET_LENGTH]);
+        let mut key = hasher.finalize().to_vec();
+        // 先頭が 0 の場合は取り除く (公式実装の挙動に合わせる)
+        if key[0] == 0 {
+            key.remove(0);
+        }
+        Ok(Yandex { key })
+    }
+}
+
+impl OtpGenerator for Yandex {
+    fn generate(&self, timestamp: u64) -> String {
+        let counter = timestamp / self.period();
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC can take key of any size");
+        mac.update(&counter.to_be_bytes());
+        let hash = mac.finalize().into_bytes();
+
+        let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+        let mut truncated = [0u8; 8];
+        truncated.copy_from_slice(&hash[offset..offset + 8]);
+        truncated[0] &= 0x7f;
+        let mut code = u64::from_be_bytes(truncated) % 26u64.pow(DIGITS);
+
+        let mut chars = vec!['a'; DIGITS as usize];
+        for c in chars.iter_mut().rev() {
+            *c = (b'a' + (code % 26) as u8) as char;
+            code /= 26;
+        }
+        chars.into_iter().collect()
+    }
+
+    fn period(&self) -> u64 {
+        30
+    }
+}
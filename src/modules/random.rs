@@ -0,0 +1,44 @@
+//! 暗号化に使う乱数の取得元
+//!
+//! 通常は OS の乱数を使い、テストではシード付きの ChaCha20 に差し替えて
+//! 保管庫の暗号文まで決定的にする。
+
+use aes_gcm::aead::{OsRng, rand_core::RngCore};
+use rand_chacha::ChaCha20Rng;
+use rand_chacha::rand_core::{RngCore as _, SeedableRng};
+use std::fmt;
+use std::sync::Mutex;
+
+pub trait Random: fmt::Debug + Send + Sync {
+    /// `dest` を乱数で埋める
+    fn fill(&self, dest: &mut [u8]);
+}
+
+/// OS の乱数
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OsRandom;
+
+impl Random for OsRandom {
+    fn fill(&self, dest: &mut [u8]) {
+        OsRng.fill_bytes(dest);
+    }
+}
+
+/// シードから決まる乱数 (テスト用。実際の保管庫には使わないこと)
+#[derive(Debug)]
+pub struct SeededRandom(Mutex<ChaCha20Rng>);
+
+impl SeededRandom {
+    pub fn new(seed: u64) -> Self {
+        SeededRandom(Mutex::new(ChaCha20Rng::seed_from_u64(seed)))
+    }
+}
+
+impl Random for SeededRandom {
+    fn fill(&self, dest: &mut [u8]) {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .fill_bytes(dest);
+    }
+}
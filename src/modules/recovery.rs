@@ -0,0 +1,209 @@
+//! 保管庫のデータ鍵の分散による復元 (gauth recovery)
+//!
+//! データ鍵を Shamir の秘密分散で印刷できる分散片に分け、キーリングのマスターパスワードを
+//! 失った場合に threshold 個の分散片から保管庫を開き直す。
+//!
+//! 分散片は `GAUTH-SHARE-<threshold>-<番号>-<鍵ID>-<Base32>-<チェック>` の形式で、
+//! 鍵 ID はデータ鍵のハッシュの先頭 4 バイト、チェックは分散片自身のハッシュの先頭 2 バイト。
+
+pub mod shamir;
+
+use crate::error::AppError;
+use crate::random::Random;
+use crate::secrets::{self, SecretManager, vault::OWNER};
+use crate::storage;
+use dialoguer::{Input, Password, theme::ColorfulTheme};
+use sha2::{Digest, Sha256};
+
+const SHARE_PREFIX: &str = "GAUTH-SHARE";
+const SHARE_ALPHABET: base32::Alphabet = base32::Alphabet::Rfc4648 { padding: false };
+
+/// 印刷用の分散片
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Share {
+    pub threshold: u8,
+    pub index: u8,
+    /// 分散元のデータ鍵の識別子 (16進)
+    pub key_id: String,
+    pub value: Vec<u8>,
+}
+
+/// データ鍵の識別子 (異なる鍵の分散片を混ぜていないかの確認に使う)
+pub fn key_id(data_key: &[u8; 32]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(b"gauth recovery key id");
+    hasher.update(data_key);
+    hex::encode_upper(&hasher.finalize()[..4])
+}
+
+fn checksum(body: &str) -> String {
+    hex::encode_upper(&Sha256::digest(body.as_bytes())[..2])
+}
+
+impl Share {
+    pub fn encode(&self) -> String {
+        let body = format!(
+            "{}-{}-{}-{}-{}",
+            SHARE_PREFIX,
+            self.threshold,
+            self.index,
+            self.key_id,
+            base32::encode(SHARE_ALPHABET, &self.value)
+        );
+        let check = checksum(&body);
+        format!("{}-{}", body, check)
+    }
+
+    /// 分散片を読み取る (空白と大文字小文字の違いは無視し、チェックで写し間違いを検出する)
+    pub fn parse(value: &str) -> Result<Self, AppError> {
+        let invalid = |reason: &str| AppError::RecoveryError(format!("Invalid share: {}", reason));
+        let normalized: String = value
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>()
+            .to_uppercase();
+        let (body, check) = normalized
+            .rsplit_once('-')
+            .ok_or_else(|| invalid("missing checksum"))?;
+        if checksum(body) != check {
+            return Err(invalid("checksum mismatch (check for typos)"));
+        }
+        let fields: Vec<&str> = body
+            .strip_prefix(SHARE_PREFIX)
+            .and_then(|rest| rest.strip_prefix('-'))
+            .ok_or_else(|| invalid("not a gauth share"))?
+            .split('-')
+            .collect();
+        let [threshold, index, key_id, data] = fields[..] else {
+            return Err(invalid("unexpected format"));
+        };
+        Ok(Share {
+            threshold: threshold.parse().map_err(|_| invalid("bad threshold"))?,
+            index: index.parse().map_err(|_| invalid("bad share number"))?,
+            key_id: key_id.to_string(),
+            value: base32::decode(SHARE_ALPHABET, data).ok_or_else(|| invalid("bad data"))?,
+        })
+    }
+}
+
+/// データ鍵を `shares` 個の分散片に分ける (`threshold` 個で復元できる)
+pub fn split_key(
+    data_key: &[u8; 32],
+    shares: u8,
+    threshold: u8,
+    random: &dyn Random,
+) -> Result<Vec<Share>, AppError> {
+    let key_id = key_id(data_key);
+    Ok(shamir::split(data_key, threshold, shares, random)?
+        .into_iter()
+        .map(|(index, value)| Share {
+            threshold,
+            index,
+            key_id: key_id.clone(),
+            value,
+        })
+        .collect())
+}
+
+/// 分散片からデータ鍵を復元し、鍵 ID と一致することを確かめる
+pub fn combine_shares(shares: &[Share]) -> Result<[u8; 32], AppError> {
+    let Some(first) = shares.first() else {
+        return Err(AppError::RecoveryError("No shares given".into()));
+    };
+    if let Some(other) = shares
+        .iter()
+        .find(|share| share.key_id != first.key_id || share.threshold != first.threshold)
+    {
+        return Err(AppError::RecoveryError(format!(
+            "Share {} belongs to a different split than share {}",
+            other.index, first.index
+        )));
+    }
+    if shares.len() < first.threshold as usize {
+        return Err(AppError::RecoveryError(format!(
+            "{} shares are required, but only {} were given",
+            first.threshold,
+            shares.len()
+        )));
+    }
+    let points: Vec<(u8, Vec<u8>)> = shares
+        .iter()
+        .map(|share| (share.index, share.value.clone()))
+        .collect();
+    let data_key: [u8; 32] = shamir::combine(&points)?
+        .try_into()
+        .map_err(|_| AppError::RecoveryError("Shares have an unexpected length".into()))?;
+    if key_id(&data_key) != first.key_id {
+        return Err(AppError::RecoveryError(
+            "The shares do not reconstruct the original key".into(),
+        ));
+    }
+    Ok(data_key)
+}
+
+pub fn split(shares: u8, threshold: u8) -> Result<(), AppError> {
+    let master_password = secrets::get_master_password()?;
+    let manager = SecretManager::load_secrets(&master_password)?;
+    let data_key = manager.recovery_key().ok_or_else(|| {
+        AppError::RecoveryError("The vault has not been saved yet; add a credential first".into())
+    })?;
+    let shares = split_key(&data_key, shares, threshold, &*manager.sources().random)?;
+
+    println!(
+        "Any {} of these {} shares restore access to the vault (key ID {}).",
+        threshold,
+        shares.len(),
+        shares[0].key_id
+    );
+    println!("Print them and keep each one in a separate place.\n");
+    for share in &shares {
+        println!("Share {}: {}", share.index, share.encode());
+    }
+    println!("\nRemoving a vault member replaces the key; split it again afterwards.");
+    Ok(())
+}
+
+/// 分散片から保管庫を開き、新しいマスターパスワードを設定する
+///
+/// 復元した鍵で保管庫を開けること、新しいパスワードで保存し直した保管庫を開けることを
+/// 確かめてから、最後にキーリングへ書き込む。
+pub fn combine(member: Option<String>) -> Result<(), AppError> {
+    let member = member.unwrap_or_else(|| OWNER.to_string());
+    let mut shares: Vec<Share> = Vec::new();
+    loop {
+        let needed = shares.first().map_or(1, |share| share.threshold as usize);
+        if shares.len() >= needed {
+            break;
+        }
+        let input: String = Input::with_theme(&ColorfulTheme::default())
+            .with_prompt(format!("Share {} of {}", shares.len() + 1, needed))
+            .interact_text()?;
+        match Share::parse(&input) {
+            Ok(share) if shares.iter().any(|other| other.index == share.index) => {
+                eprintln!("Share {} was already entered", share.index);
+            }
+            Ok(share) => shares.push(share),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+
+    let data_key = combine_shares(&shares)?;
+    let storage = storage::from_config()?;
+    let mut manager = SecretManager::recover_from(storage.clone(), data_key)?;
+    println!(
+        "The shares open the vault ({} credentials).",
+        manager.list_credentials().len()
+    );
+
+    let password = Password::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!("New master password for {}", member))
+        .with_confirmation("Confirm new master password", "Passwords do not match")
+        .interact()?;
+    manager.set_member_password(&member, &password)?;
+    manager.save_with_message(&password, &format!("Recover access for {}", member))?;
+    SecretManager::load_from(storage, &password)?;
+
+    secrets::master_password_entry()?.set_secret(password.as_bytes())?;
+    println!("Access restored; the new master password is stored in the keyring.");
+    Ok(())
+}
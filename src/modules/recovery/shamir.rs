@@ -0,0 +1,109 @@
+//! GF(2^8) 上の Shamir の秘密分散
+//!
+//! 秘密の各バイトを定数項とする (threshold - 1) 次の多項式をランダムに作り、
+//! x = 1..=shares での値を分散片とする。threshold 個の分散片から x = 0 での値を
+//! ラグランジュ補間で求めると元のバイトに戻る。
+
+use crate::error::AppError;
+use crate::random::Random;
+
+/// AES と同じ既約多項式 x^8 + x^4 + x^3 + x + 1 での乗算
+fn mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        let carry = a & 0x80;
+        a <<= 1;
+        if carry != 0 {
+            a ^= 0x1b;
+        }
+        b >>= 1;
+    }
+    product
+}
+
+/// 乗法逆元 (a^254)
+fn inv(a: u8) -> u8 {
+    let mut result = 1;
+    let mut base = a;
+    let mut exponent = 254u8;
+    while exponent != 0 {
+        if exponent & 1 != 0 {
+            result = mul(result, base);
+        }
+        base = mul(base, base);
+        exponent >>= 1;
+    }
+    result
+}
+
+/// 秘密を `shares` 個に分散し、(x 座標, 値) の組を返す
+pub fn split(
+    secret: &[u8],
+    threshold: u8,
+    shares: u8,
+    random: &dyn Random,
+) -> Result<Vec<(u8, Vec<u8>)>, AppError> {
+    if threshold == 0 || shares < threshold {
+        return Err(AppError::RecoveryError(format!(
+            "The threshold must be between 1 and the number of shares (got {} of {})",
+            threshold, shares
+        )));
+    }
+    let mut coefficients = vec![0u8; secret.len() * (threshold as usize - 1)];
+    random.fill(&mut coefficients);
+
+    Ok((1..=shares)
+        .map(|x| {
+            let values = secret
+                .iter()
+                .enumerate()
+                .map(|(i, &byte)| {
+                    let terms = &coefficients[i * (threshold as usize - 1)..];
+                    // ホーナー法で高次の係数から評価する
+                    let mut value = 0;
+                    for &coefficient in terms[..threshold as usize - 1].iter().rev() {
+                        value = mul(value, x) ^ coefficient;
+                    }
+                    mul(value, x) ^ byte
+                })
+                .collect();
+            (x, values)
+        })
+        .collect())
+}
+
+/// 分散片から秘密を復元する (分散時の threshold 個以上が必要)
+pub fn combine(shares: &[(u8, Vec<u8>)]) -> Result<Vec<u8>, AppError> {
+    let Some((_, first)) = shares.first() else {
+        return Err(AppError::RecoveryError("No shares given".into()));
+    };
+    for (position, (x, values)) in shares.iter().enumerate() {
+        if *x == 0 || values.len() != first.len() {
+            return Err(AppError::RecoveryError(format!("Share {} is invalid", x)));
+        }
+        if shares[..position].iter().any(|(other, _)| other == x) {
+            return Err(AppError::RecoveryError(format!(
+                "Share {} was given more than once",
+                x
+            )));
+        }
+    }
+
+    let mut secret = vec![0u8; first.len()];
+    for (i, &(xi, ref values)) in shares.iter().enumerate() {
+        // x = 0 でのラグランジュ基底多項式の値 (GF(2^8) では減算も XOR)
+        let mut basis = 1;
+        for (j, &(xj, _)) in shares.iter().enumerate() {
+            if i != j {
+                basis = mul(basis, mul(xj, inv(xj ^ xi)));
+            }
+        }
+        for (byte, &value) in secret.iter_mut().zip(values) {
+            *byte ^= mul(value, basis);
+        }
+    }
+    Ok(secret)
+}
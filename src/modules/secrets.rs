@@ -1,94 +1,749 @@
-use aes_gcm::{
-    Aes256Gcm, Key, Nonce,
-    aead::{Aead, KeyInit, OsRng, rand_core::RngCore},
-};
+mod legacy;
+pub mod vault;
+
+use crate::index::{self, NameIndex};
+use crate::merge;
+use crate::otp::{CredentialKind, OtpParams};
+use crate::random::{OsRandom, Random, SeededRandom};
+use crate::storage::{self, Storage, Version};
+use crate::time::{self, Clock, FixedClock, SystemClock};
 use dialoguer::{Password, theme::ColorfulTheme};
 use keyring::Entry;
 use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
-use std::collections::HashMap;
-use std::fs::{self, File};
-use std::io::{Read, Write};
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
+use std::sync::Arc;
+use vault::{Envelope, Member, VaultKey};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct Credential {
     pub name: String,
-    pub ciphertext: Vec<u8>,
-    pub nonce: Vec<u8>,
+    #[serde(default)]
+    pub kind: CredentialKind,
+    #[serde(default)]
+    pub issuer: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// totp 用のアルゴリズム・桁数・間隔
+    #[serde(default)]
+    pub params: OtpParams,
+    /// シークレット (保管庫内では Base32 文字列として保存)
+    #[serde(with = "base32_secret")]
+    pub secret: Vec<u8>,
+    /// mOTP / Yandex 用の PIN
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pin: Option<String>,
+    /// verify で最後に受理したタイムステップ (リプレイ検出用)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_step: Option<u64>,
+    /// サービスから発行されたリカバリーコード (保管庫の中身と一緒に暗号化される)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub recovery_codes: Vec<RecoveryCode>,
+}
+
+/// 残りがこの数以下になったら警告するリカバリーコードの数
+pub const LOW_RECOVERY_CODES: usize = 2;
+
+/// リカバリーコード (使用済みのものも記録として残す)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RecoveryCode {
+    pub code: String,
+    #[serde(default)]
+    pub used: bool,
+}
+
+/// 比較用にリカバリーコードの空白・ハイフン・大文字小文字の違いを取り除く
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+impl Credential {
+    pub fn new(name: String, kind: CredentialKind, secret: Vec<u8>) -> Self {
+        Credential {
+            name,
+            kind,
+            issuer: None,
+            tags: Vec::new(),
+            params: OtpParams::default(),
+            secret,
+            pin: None,
+            last_step: None,
+            recovery_codes: Vec::new(),
+        }
+    }
+
+    /// 未使用のリカバリーコードの数
+    pub fn remaining_recovery_codes(&self) -> usize {
+        self.recovery_codes.iter().filter(|code| !code.used).count()
+    }
+
+    /// リカバリーコードを追加し、追加した数を返す (登録済みのものは無視する)
+    pub fn add_recovery_codes<I, S>(&mut self, codes: I) -> usize
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut added = 0;
+        for code in codes {
+            let code = code.as_ref().trim();
+            let normalized = normalize_recovery_code(code);
+            if normalized.is_empty()
+                || self
+                    .recovery_codes
+                    .iter()
+                    .any(|existing| normalize_recovery_code(&existing.code) == normalized)
+            {
+                continue;
+            }
+            self.recovery_codes.push(RecoveryCode {
+                code: code.to_string(),
+                used: false,
+            });
+            added += 1;
+        }
+        added
+    }
+
+    /// リカバリーコードを使用済みにする
+    ///
+    /// `code` を省略した場合は最初の未使用のコードを使う。使ったコードを返す。
+    pub fn use_recovery_code(
+        &mut self,
+        code: Option<&str>,
+    ) -> Result<String, super::error::AppError> {
+        let entry = match code {
+            Some(code) => {
+                let normalized = normalize_recovery_code(code);
+                let entry = self
+                    .recovery_codes
+                    .iter_mut()
+                    .find(|entry| normalize_recovery_code(&entry.code) == normalized)
+                    .ok_or_else(|| {
+                        super::error::AppError::SecretManagerError(format!(
+                            "'{}' is not a recovery code of '{}'",
+                            code, self.name
+                        ))
+                    })?;
+                if entry.used {
+                    return Err(super::error::AppError::SecretManagerError(format!(
+                        "Recovery code '{}' has already been used",
+                        entry.code
+                    )));
+                }
+                entry
+            }
+            None => self
+                .recovery_codes
+                .iter_mut()
+                .find(|entry| !entry.used)
+                .ok_or_else(|| {
+                    super::error::AppError::SecretManagerError(format!(
+                        "No unused recovery codes left for '{}'",
+                        self.name
+                    ))
+                })?,
+        };
+        entry.used = true;
+        Ok(entry.code.clone())
+    }
+}
+
+mod base32_secret {
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    const ALPHABET: base32::Alphabet = base32::Alphabet::Rfc4648 { padding: false };
+
+    pub fn serialize<S: Serializer>(secret: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&base32::encode(ALPHABET, secret))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        base32::decode(ALPHABET, &value).ok_or_else(|| D::Error::custom("invalid Base32 secret"))
+    }
+}
+
+/// 保管庫の中身のうち同期・マージの対象になる部分
+///
+/// 時計のずれ (`time_offset`) は端末ごとの設定のため含めない。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Snapshot {
+    pub credentials: BTreeMap<String, Credential>,
+}
+
+impl Snapshot {
+    /// `after` までの変更を1行で説明する ("Add github, delete aws" など)
+    pub fn describe_changes(&self, after: &Snapshot) -> String {
+        sentence(self.changes(after))
+    }
+
+    /// `after` までの変更を "add github" のような句の並びにする
+    fn changes(&self, after: &Snapshot) -> Vec<String> {
+        fn list(verb: &str, names: &[&String]) -> Option<String> {
+            match names {
+                [] => None,
+                [a] => Some(format!("{} {}", verb, a)),
+                [a, b] => Some(format!("{} {} and {}", verb, a, b)),
+                [a, b, c] => Some(format!("{} {}, {} and {}", verb, a, b, c)),
+                [a, b, rest @ ..] => Some(format!("{} {}, {} and {} more", verb, a, b, rest.len())),
+            }
+        }
+
+        let added: Vec<&String> = after
+            .credentials
+            .keys()
+            .filter(|name| !self.credentials.contains_key(*name))
+            .collect();
+        let deleted: Vec<&String> = self
+            .credentials
+            .keys()
+            .filter(|name| !after.credentials.contains_key(*name))
+            .collect();
+        let updated: Vec<&String> = after
+            .credentials
+            .iter()
+            .filter(|(name, credential)| {
+                self.credentials
+                    .get(*name)
+                    .is_some_and(|before| before != *credential)
+            })
+            .map(|(name, _)| name)
+            .collect();
+
+        [
+            list("add", &added),
+            list("delete", &deleted),
+            list("update", &updated),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+}
+
+/// 変更の句をつないで先頭を大文字にする (変更がなければ "Update vault")
+fn sentence(parts: Vec<String>) -> String {
+    if parts.is_empty() {
+        return "Update vault".into();
+    }
+    let message = parts.join(", ");
+    let mut chars = message.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => message,
+    }
+}
+
+/// コード生成の時計と暗号化の乱数の取得元
+///
+/// 既定ではシステム時刻と OS の乱数を使う。テストでは [`Sources::fixed`] に差し替えると、
+/// 待たずに任意の時刻のコードを確かめられ、保管庫の暗号文も決定的になる。
+#[derive(Debug, Clone)]
+pub struct Sources {
+    pub clock: Arc<dyn Clock>,
+    pub random: Arc<dyn Random>,
+}
+
+impl Default for Sources {
+    fn default() -> Self {
+        Sources {
+            clock: Arc::new(SystemClock),
+            random: Arc::new(OsRandom),
+        }
+    }
+}
+
+impl Sources {
+    /// 指定した時刻で止まった時計とシード付きの乱数 (テスト用)
+    pub fn fixed(timestamp: u64, seed: u64) -> Self {
+        Sources {
+            clock: Arc::new(FixedClock::new(timestamp)),
+            random: Arc::new(SeededRandom::new(seed)),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct SecretManager {
+    /// コード生成時にシステム時刻へ加算する秒数
+    #[serde(default)]
+    time_offset: i64,
     credentials: HashMap<String, Credential>,
+    /// 読み込み時に取り出したデータ鍵 (保存時に再利用する)
+    #[serde(skip)]
+    key: Option<VaultKey>,
+    /// 読み込み元の保存先
+    #[serde(skip)]
+    storage: Option<Arc<dyn Storage>>,
+    /// 読み込んだ時点の版 (保存時に他のプロセスによる変更を検出する)
+    #[serde(skip)]
+    version: Option<Version>,
+    /// 読み込んだ時点の内容 (保存が衝突した場合のマージの基準)
+    #[serde(skip)]
+    base: Snapshot,
+    /// 時計と乱数の取得元
+    #[serde(skip)]
+    sources: Sources,
+    /// 保存のたびに書き出す名前の索引の場所 (無効なら None)
+    #[serde(skip)]
+    index: Option<PathBuf>,
+    /// 最後に名前の索引を書き出せなかった理由
+    #[serde(skip)]
+    index_warning: Option<String>,
+    /// 読み込み時 (または最後に保存した時) の鍵とスロット
+    #[serde(skip)]
+    base_key: Option<VaultKey>,
+    /// 読み込み時 (または最後に保存した時) の時計のずれ
+    #[serde(skip)]
+    base_time_offset: i64,
 }
 
+/// 旧形式の保管庫を現在の形式に移行したときの情報
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migration {
+    pub version: u32,
+    /// 元のファイルのバックアップ先 (保存先が対応していない場合は None)
+    pub backup: Option<String>,
+}
+
+impl std::fmt::Display for Migration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Migrated vault to format version {}", self.version)?;
+        if let Some(backup) = &self.backup {
+            write!(f, " (backup: {})", backup)?;
+        }
+        Ok(())
+    }
+}
+
+/// 保存が衝突したときにマージして再試行する回数
+const SAVE_ATTEMPTS: usize = 3;
+
 impl SecretManager {
     pub fn new() -> Self {
         SecretManager::default()
     }
 
-    pub fn load_secrets(master_password: &str) -> Result<Self, super::error::AppError> {
-        let path = get_config_file_path()?;
-        if !path.exists() {
-            return Ok(SecretManager::new());
+    /// 時計と乱数の取得元を指定した空の保管庫
+    pub fn with_sources(sources: Sources) -> Self {
+        SecretManager {
+            sources,
+            ..SecretManager::default()
         }
+    }
+
+    pub fn sources(&self) -> &Sources {
+        &self.sources
+    }
+
+    /// 時刻オフセットを適用した現在の UNIX 時刻
+    pub fn now(&self) -> Result<u64, super::error::AppError> {
+        time::now_with(&*self.sources.clock, self.time_offset)
+    }
 
-        let mut file = File::open(&path)?;
-        let mut contents = Vec::new();
-        file.read_to_end(&mut contents)?;
+    /// 時刻オフセットを適用した現在の UNIX 時刻 (ミリ秒)
+    pub fn now_millis(&self) -> Result<u64, super::error::AppError> {
+        time::now_millis_with(&*self.sources.clock, self.time_offset)
+    }
+
+    /// 設定ファイルで選択された保存先から読み込む
+    pub fn load_secrets(master_password: &str) -> Result<Self, super::error::AppError> {
+        let mut manager = SecretManager::load_from(storage::from_config()?, master_password)?;
+        manager.set_index_path(index::configured_path()?);
+        manager.report_index_warning();
+        Ok(manager)
+    }
+
+    /// 名前の索引の場所を設定し、内容が古ければ書き出す
+    pub fn set_index_path(&mut self, path: Option<PathBuf>) {
+        self.index = path;
+        self.write_index();
+    }
+
+    /// 最後に名前の索引を書き出せなかった場合はその理由
+    ///
+    /// 索引は補完などの補助のため、書き出せなくても保管庫の操作は失敗させない。
+    pub fn index_warning(&self) -> Option<&str> {
+        self.index_warning.as_deref()
+    }
 
-        // 暗号化されたTOMLデータを復号化
-        let key_bytes = master_password.as_bytes();
-        // nonceは12バイトと定義されているので、最後の12バイトをnonceとする
-        if contents.len() < 12 {
-            return Err(super::error::AppError::DecryptionError);
+    /// 名前の索引を書き出せなかった場合に知らせる
+    pub fn report_index_warning(&self) {
+        if let Some(warning) = self.index_warning() {
+            eprintln!("Warning: {}", warning);
         }
-        let (encrypted_toml, stored_nonce) = contents.split_at(contents.len() - 12);
+    }
 
-        let decrypted_toml_bytes = decrypt_data(key_bytes, encrypted_toml, stored_nonce)?;
-        let decrypted_toml_str = String::from_utf8(decrypted_toml_bytes)
-            .map_err(|_| super::error::AppError::DecryptionError)?;
+    fn write_index(&mut self) {
+        let Some(path) = &self.index else {
+            self.index_warning = None;
+            return;
+        };
+        let result = NameIndex::from_credentials(self.credentials.values()).write(path);
+        self.index_warning = result
+            .err()
+            .map(|e| format!("could not update the name index {}: {}", path.display(), e));
+    }
 
-        let manager: SecretManager = toml::from_str(&decrypted_toml_str)?;
+    /// 指定した保存先から読み込む (まだ何も保存されていなければ空の保管庫)
+    pub fn load_from(
+        storage: Arc<dyn Storage>,
+        master_password: &str,
+    ) -> Result<Self, super::error::AppError> {
+        let (manager, migration) = SecretManager::open(storage, master_password)?;
+        if let Some(migration) = migration {
+            eprintln!("{}", migration);
+        }
         Ok(manager)
     }
 
-    pub fn save_secrets(&self, master_password: &str) -> Result<(), super::error::AppError> {
-        let path = get_config_file_path()?;
-        let parent_dir = path
-            .parent()
-            .ok_or(super::error::AppError::ConfigDirNotFound)?;
-        fs::create_dir_all(parent_dir)?;
+    /// 何も表示せずに指定した保存先から読み込む
+    ///
+    /// 旧形式の保管庫はバックアップを残して現在の形式で保存し直し、その内容を返す。
+    pub fn open(
+        storage: Arc<dyn Storage>,
+        master_password: &str,
+    ) -> Result<(Self, Option<Migration>), super::error::AppError> {
+        SecretManager::open_with(storage, master_password, Sources::default())
+    }
+
+    /// 時計と乱数の取得元を指定して読み込む
+    pub fn open_with(
+        storage: Arc<dyn Storage>,
+        master_password: &str,
+        sources: Sources,
+    ) -> Result<(Self, Option<Migration>), super::error::AppError> {
+        let Some((contents, version)) = storage.read()? else {
+            return Ok((
+                SecretManager {
+                    storage: Some(storage),
+                    ..SecretManager::with_sources(sources)
+                },
+                None,
+            ));
+        };
 
-        let toml_string = toml::to_string(&self)?;
+        let mut manager = SecretManager::decode_with(&contents, master_password, sources)?;
+        manager.storage = Some(Arc::clone(&storage));
+        manager.version = Some(version);
+        manager.base = manager.snapshot();
+        manager.base_key = manager.key.clone();
+        manager.base_time_offset = manager.time_offset;
+        if Envelope::parse(&contents)?.is_some() {
+            return Ok((manager, None));
+        }
+        let backup = storage.backup(&contents, ".v1.bak")?;
+        let message = manager.describe_changes();
+        manager.commit(master_password, &message)?;
+        Ok((
+            manager,
+            Some(Migration {
+                version: vault::VERSION,
+                backup,
+            }),
+        ))
+    }
 
-        // TOMLデータを暗号化
-        let key_bytes = master_password.as_bytes();
-        let (ciphertext, nonce) = encrypt_data(key_bytes, toml_string.as_bytes())?;
+    /// 読み込み元の保存先に書き込む
+    ///
+    /// 読み込んだ後に他のプロセスが保管庫を変更していた場合は、読み込み時の内容を基準に
+    /// 認証情報ごとの三方向マージを行ってから書き込み直す。
+    pub fn save_secrets(&mut self, master_password: &str) -> Result<(), super::error::AppError> {
+        let message = self.describe_changes();
+        self.save_with_message(master_password, &message)
+    }
 
-        let mut file = File::create(&path)?;
-        file.write_all(&ciphertext)?;
-        file.write_all(&nonce)?; // nonceを暗号文の末尾に追加
+    /// 読み込み後の変更内容の説明 ("Add github" など)
+    pub fn describe_changes(&self) -> String {
+        let mut parts = self.base.changes(&self.snapshot());
+        if self.time_offset != self.base_time_offset {
+            parts.push(format!("set time offset to {:+}s", self.time_offset));
+        }
+        sentence(parts)
+    }
 
+    /// 変更内容の説明を指定して保存する (履歴を持つ保存先ではコミットメッセージになる)
+    pub fn save_with_message(
+        &mut self,
+        master_password: &str,
+        message: &str,
+    ) -> Result<(), super::error::AppError> {
+        for conflict in self.commit(master_password, message)? {
+            eprintln!(
+                "Conflicting changes to '{}'; kept the local version as '{}'",
+                conflict.name, conflict.kept_as
+            );
+        }
+        self.report_index_warning();
+        Ok(())
+    }
+
+    /// 何も表示せずに保存し、マージで衝突した認証情報を返す
+    pub fn commit(
+        &mut self,
+        master_password: &str,
+        message: &str,
+    ) -> Result<Vec<merge::MergeConflict>, super::error::AppError> {
+        let storage = match &self.storage {
+            Some(storage) => Arc::clone(storage),
+            None => storage::from_config()?,
+        };
+
+        let _lock = storage.lock()?;
+        let mut attempts = 0;
+        let mut conflicts = Vec::new();
+        let version = loop {
+            let contents = self.encode(master_password)?;
+            match storage.write_change(&contents, self.version.as_ref(), message) {
+                Err(super::error::AppError::StorageConflict) if attempts + 1 < SAVE_ATTEMPTS => {
+                    attempts += 1;
+                    let Some((remote, version)) = storage.read()? else {
+                        // 他のプロセスが削除した場合は手元の内容で作り直す
+                        self.version = None;
+                        continue;
+                    };
+                    let remote =
+                        SecretManager::decode_with(&remote, master_password, self.sources.clone())?;
+                    self.rebase_key(remote.key.as_ref())?;
+                    let result = merge::merge(&self.base, &self.snapshot(), &remote.snapshot());
+                    conflicts.extend(result.conflicts);
+                    // 時計のずれは認証情報とは別に、手元で変更していなければ他のプロセスの値を使う
+                    if self.time_offset == self.base_time_offset {
+                        self.time_offset = remote.time_offset;
+                    }
+                    self.base = remote.snapshot();
+                    self.base_key = remote.key;
+                    self.base_time_offset = remote.time_offset;
+                    self.apply(result.merged);
+                    self.version = Some(version);
+                }
+                result => break result?,
+            }
+        };
+        self.storage = Some(storage);
+        self.version = Some(version);
+        self.base = self.snapshot();
+        self.base_key = self.key.clone();
+        self.base_time_offset = self.time_offset;
+        self.write_index();
+        Ok(conflicts)
+    }
+
+    /// 他のプロセスが保存した保管庫の鍵とスロットを引き継ぐ
+    ///
+    /// 手元の鍵で暗号化し直すと、他のプロセスでのメンバーの追加や削除を取り消してしまう。
+    /// 他のプロセスが鍵を入れ替えていた場合は、読み込み直して変更をやり直すようエラーにする。
+    fn rebase_key(&mut self, remote: Option<&VaultKey>) -> Result<(), super::error::AppError> {
+        if let (Some(base), Some(remote)) = (&self.base_key, remote)
+            && !base.same_key(remote)
+        {
+            return Err(super::error::AppError::VaultKeyChanged);
+        }
+        self.key = VaultKey::merge(self.base_key.as_ref(), self.key.as_ref(), remote)?;
         Ok(())
     }
 
-    pub fn add_credential(&mut self, name: String, ciphertext: Vec<u8>, nonce: Vec<u8>) {
-        self.credentials.insert(
-            name.clone(),
-            Credential {
-                name,
-                ciphertext,
-                nonce,
-            },
-        );
+    /// 同期で基準・同期先と内容をマージする前に、暗号化に使う鍵とスロットを決める
+    ///
+    /// 同期先でのメンバーの削除 (鍵の入れ替え) やパスワードの変更は引き継ぎ、
+    /// 双方でメンバーを変更していた場合はエラーにする。
+    pub fn merge_key(
+        &mut self,
+        base: Option<&SecretManager>,
+        remote: &SecretManager,
+    ) -> Result<(), super::error::AppError> {
+        self.key = VaultKey::merge(
+            base.and_then(|base| base.key.as_ref()),
+            self.key.as_ref(),
+            remote.key.as_ref(),
+        )?;
+        Ok(())
+    }
+
+    /// 鍵とメンバー (スロット) が `other` と同じか
+    pub fn same_members(&self, other: &SecretManager) -> bool {
+        match (&self.key, &other.key) {
+            (Some(key), Some(other)) => key.same_members(other),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    /// 同期・マージの対象になる内容を取り出す
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            credentials: self
+                .credentials
+                .iter()
+                .map(|(name, credential)| (name.clone(), credential.clone()))
+                .collect(),
+        }
+    }
+
+    /// マージ結果などで内容を置き換える (時計のずれはそのまま)
+    pub fn apply(&mut self, snapshot: Snapshot) {
+        self.credentials = snapshot.credentials.into_iter().collect();
+    }
+
+    /// 保管庫ファイルの内容を復号する (旧形式も読み込める)
+    pub fn decode(contents: &[u8], master_password: &str) -> Result<Self, super::error::AppError> {
+        SecretManager::decode_with(contents, master_password, Sources::default())
+    }
+
+    /// 時計と乱数の取得元を指定して復号する
+    pub fn decode_with(
+        contents: &[u8],
+        master_password: &str,
+        sources: Sources,
+    ) -> Result<Self, super::error::AppError> {
+        let Some(envelope) = Envelope::parse(contents)? else {
+            let mut manager = legacy::load(contents, master_password)?;
+            manager.sources = sources;
+            return Ok(manager);
+        };
+        let key = VaultKey::unlock(&envelope, master_password, Arc::clone(&sources.random))?;
+        let mut manager = SecretManager::from_payload(&key.open(&envelope)?)?;
+        manager.key = Some(key);
+        manager.sources = sources;
+        Ok(manager)
+    }
+
+    /// 復号した中身を読み込む
+    fn from_payload(payload: &[u8]) -> Result<Self, super::error::AppError> {
+        let corrupted = |reason: String| super::error::AppError::VaultCorrupted(reason);
+        let payload = std::str::from_utf8(payload)
+            .map_err(|_| corrupted("the contents are not UTF-8".into()))?;
+        toml::from_str(payload).map_err(|e| corrupted(format!("invalid contents: {}", e.message())))
+    }
+
+    /// 現在の形式で保管庫ファイルの内容を作成する
+    ///
+    /// 新しい保管庫ではデータ鍵を生成し、読み込んだ保管庫では同じデータ鍵を使い回す。
+    pub fn encode(&mut self, master_password: &str) -> Result<Vec<u8>, super::error::AppError> {
+        let key = match &self.key {
+            Some(key) => key.clone(),
+            None => VaultKey::generate(master_password, Arc::clone(&self.sources.random))?,
+        };
+        let payload = toml::to_string(&self)?;
+        let envelope = key.seal(payload.as_bytes())?;
+        self.key = Some(key);
+        Ok(envelope.to_toml()?.into_bytes())
+    }
+
+    /// 開いている保管庫のデータ鍵 (新しい保管庫では生成する)
+    fn vault_key(
+        &mut self,
+        master_password: &str,
+    ) -> Result<&mut VaultKey, super::error::AppError> {
+        if self.key.is_none() {
+            self.key = Some(VaultKey::generate(
+                master_password,
+                Arc::clone(&self.sources.random),
+            )?);
+        }
+        Ok(self.key.as_mut().expect("vault key was just set"))
+    }
+
+    /// 復元用のデータ鍵 (まだ保存していない保管庫では `None`)
+    pub fn recovery_key(&self) -> Option<[u8; 32]> {
+        self.key.as_ref().map(VaultKey::data_key)
+    }
+
+    /// 復元したデータ鍵で保存先の保管庫を開く
+    ///
+    /// 保存する前に [`SecretManager::set_member_password`] で新しいパスワードを設定する。
+    pub fn recover_from(
+        storage: Arc<dyn Storage>,
+        data_key: [u8; 32],
+    ) -> Result<Self, super::error::AppError> {
+        let sources = Sources::default();
+        let (contents, version) = storage.read()?.ok_or_else(|| {
+            super::error::AppError::SecretManagerError(format!(
+                "No vault found in {}",
+                storage.describe()
+            ))
+        })?;
+        let envelope = Envelope::parse(&contents)?.ok_or_else(|| {
+            super::error::AppError::SecretManagerError(
+                "The vault uses the version 1 format, which cannot be recovered from shares".into(),
+            )
+        })?;
+        let key = VaultKey::recover(&envelope, data_key, Arc::clone(&sources.random))?;
+        let mut manager = SecretManager::from_payload(&key.open(&envelope)?)?;
+        manager.key = Some(key);
+        manager.sources = sources;
+        manager.storage = Some(storage);
+        manager.version = Some(version);
+        manager.base = manager.snapshot();
+        manager.base_key = manager.key.clone();
+        manager.base_time_offset = manager.time_offset;
+        Ok(manager)
+    }
+
+    /// メンバーのパスワードスロットを作り直す (なければ追加する)
+    pub fn set_member_password(
+        &mut self,
+        member: &str,
+        password: &str,
+    ) -> Result<(), super::error::AppError> {
+        let key = self.key.as_mut().ok_or_else(|| {
+            super::error::AppError::SecretManagerError("The vault has not been saved yet".into())
+        })?;
+        key.set_password(member, password)
+    }
+
+    /// 保管庫を開けるメンバーの一覧 (未保存の新しい保管庫では空)
+    pub fn members(&self) -> Vec<Member> {
+        self.key.as_ref().map(VaultKey::members).unwrap_or_default()
+    }
+
+    /// メンバー自身のパスワードで開けるようにする
+    pub fn add_member_password(
+        &mut self,
+        name: &str,
+        password: &str,
+        master_password: &str,
+    ) -> Result<(), super::error::AppError> {
+        self.vault_key(master_password)?
+            .add_password_member(name, password)
+    }
+
+    /// メンバーの公開鍵 (`gauth-pk-...`) で開けるようにする
+    pub fn add_member_public_key(
+        &mut self,
+        name: &str,
+        public_key: &str,
+        master_password: &str,
+    ) -> Result<(), super::error::AppError> {
+        self.vault_key(master_password)?
+            .add_public_key_member(name, public_key)
+    }
+
+    /// メンバーを削除し、残りのメンバー用にデータ鍵を入れ替える
+    pub fn remove_member(
+        &mut self,
+        name: &str,
+        master_password: &str,
+    ) -> Result<(), super::error::AppError> {
+        self.vault_key(master_password)?.remove_member(name)
+    }
+
+    /// 組み立て済みの認証情報を登録 (同名のものは置き換える)
+    pub fn insert_credential(&mut self, credential: Credential) {
+        self.credentials.insert(credential.name.clone(), credential);
     }
 
     pub fn get_credential(&self, name: &str) -> Option<&Credential> {
         self.credentials.get(name)
     }
 
+    pub fn get_credential_mut(&mut self, name: &str) -> Option<&mut Credential> {
+        self.credentials.get_mut(name)
+    }
+
     pub fn delete_credential(&mut self, name: &str) -> Option<Credential> {
         self.credentials.remove(name)
     }
@@ -96,18 +751,41 @@ impl SecretManager {
     pub fn list_credentials(&self) -> Vec<&String> {
         self.credentials.keys().collect()
     }
+
+    /// すべての認証情報を名前順に返す
+    pub fn credentials(&self) -> Vec<&Credential> {
+        let mut credentials: Vec<&Credential> = self.credentials.values().collect();
+        credentials.sort_by(|a, b| a.name.cmp(&b.name));
+        credentials
+    }
+
+    /// 指定した認証情報の復号済みシークレット
+    pub fn secret(&self, name: &str) -> Option<&[u8]> {
+        self.credentials
+            .get(name)
+            .map(|credential| credential.secret.as_slice())
+    }
+
+    pub fn time_offset(&self) -> i64 {
+        self.time_offset
+    }
+
+    pub fn set_time_offset(&mut self, offset: i64) {
+        self.time_offset = offset;
+    }
 }
 
-pub fn get_config_file_path() -> Result<PathBuf, super::error::AppError> {
-    let mut path = dirs::config_dir().ok_or(super::error::AppError::ConfigDirNotFound)?;
-    path.push("gauth");
-    path.push("credentials.toml");
-    Ok(path)
+/// マスターパスワード (または識別子) を保存するキーリングのエントリ
+pub fn master_password_entry() -> Result<Entry, super::error::AppError> {
+    const SERVICE_NAME: &str = "gauth_master_password";
+    Ok(Entry::new(SERVICE_NAME, "gauth_user")?)
 }
 
+/// キーリングにマスターパスワードがない状態で、既存の保管庫を開くときに尋ねる回数
+const UNLOCK_ATTEMPTS: usize = 3;
+
 pub fn get_master_password() -> Result<String, super::error::AppError> {
-    const SERVICE_NAME: &str = "gauth_master_password";
-    let entry = Entry::new(SERVICE_NAME, "gauth_user")?;
+    let entry = master_password_entry()?;
 
     match entry.get_secret() {
         Ok(password_bytes) => {
@@ -116,6 +794,35 @@ pub fn get_master_password() -> Result<String, super::error::AppError> {
             Ok(password)
         }
         Err(keyring::Error::NoEntry) => {
+            // OS の再インストールなどでキーリングだけが失われた場合は、既存の保管庫を開き直す
+            let storage = storage::from_config()?;
+            if let Some((contents, _)) = storage.read()? {
+                println!(
+                    "Found an existing vault in {}, but no master password in the keyring.",
+                    storage.describe()
+                );
+                let password = unlock_existing_vault(&contents, UNLOCK_ATTEMPTS, |attempt| {
+                    if attempt > 0 {
+                        eprintln!(
+                            "Wrong master password ({} attempts left)",
+                            UNLOCK_ATTEMPTS - attempt
+                        );
+                    }
+                    Ok(Password::with_theme(&ColorfulTheme::default())
+                        .with_prompt("Master password for the existing vault")
+                        .interact()?)
+                })
+                .inspect_err(|e| {
+                    if matches!(e, super::error::AppError::WrongMasterPassword) {
+                        eprintln!(
+                            "If the password is lost, restore access with `gauth recovery combine`."
+                        );
+                    }
+                })?;
+                entry.set_secret(password.as_bytes())?;
+                return Ok(password);
+            }
+
             println!("Master password not found. Please set one up.");
             let password = Password::with_theme(&ColorfulTheme::default())
                 .with_prompt("Master Password")
@@ -134,41 +841,22 @@ pub fn get_master_password() -> Result<String, super::error::AppError> {
     }
 }
 
-pub fn encrypt_data(
-    master_key_material: &[u8],
-    data: &[u8],
-) -> Result<(Vec<u8>, Vec<u8>), super::error::AppError> {
-    let mut hasher = Sha256::new();
-    hasher.update(master_key_material);
-    let key_bytes = hasher.finalize();
-
-    let key = Key::<Aes256Gcm>::from_slice(key_bytes.as_slice());
-    let cipher = Aes256Gcm::new(key);
-    let mut nonce_bytes = [0u8; 12];
-    OsRng.fill_bytes(&mut nonce_bytes);
-    let nonce = Nonce::from_slice(&nonce_bytes);
-
-    let ciphertext = cipher
-        .encrypt(nonce, data)
-        .map_err(|_| super::error::AppError::EncryptionError)?;
-    Ok((ciphertext, nonce_bytes.to_vec()))
-}
-
-pub fn decrypt_data(
-    master_key_material: &[u8],
-    ciphertext: &[u8],
-    nonce_bytes: &[u8],
-) -> Result<Vec<u8>, super::error::AppError> {
-    let mut hasher = Sha256::new();
-    hasher.update(master_key_material);
-    let key_bytes = hasher.finalize();
-
-    let key = Key::<Aes256Gcm>::from_slice(key_bytes.as_slice());
-    let cipher = Aes256Gcm::new(key);
-    let nonce = Nonce::from_slice(nonce_bytes);
-
-    let plaintext = cipher
-        .decrypt(nonce, ciphertext)
-        .map_err(|_| super::error::AppError::DecryptionError)?;
-    Ok(plaintext)
+/// 既存の保管庫を開けるパスワードを尋ねる
+///
+/// `prompt` には何回目の入力か (0 から) を渡す。パスワードが誤っている場合だけ
+/// `attempts` 回まで尋ね直し、ファイルが壊れている場合はすぐにエラーを返す。
+pub fn unlock_existing_vault(
+    contents: &[u8],
+    attempts: usize,
+    mut prompt: impl FnMut(usize) -> Result<String, super::error::AppError>,
+) -> Result<String, super::error::AppError> {
+    for attempt in 0..attempts {
+        let password = prompt(attempt)?;
+        match SecretManager::decode(contents, &password) {
+            Ok(_) => return Ok(password),
+            Err(super::error::AppError::WrongMasterPassword) => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Err(super::error::AppError::WrongMasterPassword)
 }
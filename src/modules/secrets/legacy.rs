@@ -0,0 +1,107 @@
+//! version 1 の保管庫 (TOML 全体と各シークレットをそれぞれマスターパスワードで暗号化した形式)
+//!
+//! 読み込みのみをサポートし、読み込んだ内容は version 2 で保存し直す。
+
+use super::{Credential, SecretManager};
+use crate::error::AppError;
+use crate::otp::{CredentialKind, OtpParams};
+use aes_gcm::{Aes256Gcm, Key, KeyInit, Nonce, aead::Aead};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+#[derive(Deserialize)]
+struct LegacySecretManager {
+    #[serde(default)]
+    time_offset: i64,
+    credentials: HashMap<String, LegacyCredential>,
+}
+
+#[derive(Deserialize)]
+struct LegacyCredential {
+    name: String,
+    #[serde(default)]
+    kind: CredentialKind,
+    #[serde(default)]
+    issuer: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    params: OtpParams,
+    ciphertext: Vec<u8>,
+    nonce: Vec<u8>,
+    #[serde(default)]
+    pin_ciphertext: Option<Vec<u8>>,
+    #[serde(default)]
+    pin_nonce: Option<Vec<u8>>,
+    #[serde(default)]
+    last_step: Option<u64>,
+}
+
+/// 旧形式の保管庫を復号する
+pub fn load(contents: &[u8], master_password: &str) -> Result<SecretManager, AppError> {
+    let key_bytes = master_password.as_bytes();
+    // nonceは12バイトと定義されているので、最後の12バイトをnonceとする
+    if contents.len() < 12 {
+        return Err(AppError::VaultCorrupted("the file is too short".into()));
+    }
+    let (encrypted_toml, stored_nonce) = contents.split_at(contents.len() - 12);
+
+    // 旧形式にはスロットがないため、全体の復号に失敗した場合はパスワードの誤りとみなす
+    let decrypted_toml_bytes = decrypt_data(key_bytes, encrypted_toml, stored_nonce)
+        .map_err(|_| AppError::WrongMasterPassword)?;
+    let corrupted = |reason: &str| AppError::VaultCorrupted(reason.into());
+    let decrypted_toml_str = String::from_utf8(decrypted_toml_bytes)
+        .map_err(|_| corrupted("the contents are not UTF-8"))?;
+    let legacy: LegacySecretManager = toml::from_str(&decrypted_toml_str)
+        .map_err(|_| corrupted("the contents are not valid TOML"))?;
+
+    let mut manager = SecretManager::new();
+    manager.set_time_offset(legacy.time_offset);
+    for (_, credential) in legacy.credentials {
+        let secret = decrypt_data(key_bytes, &credential.ciphertext, &credential.nonce)
+            .map_err(|_| corrupted("a secret failed authentication"))?;
+        let pin = match (&credential.pin_ciphertext, &credential.pin_nonce) {
+            (Some(ciphertext), Some(nonce)) => Some(
+                decrypt_data(key_bytes, ciphertext, nonce)
+                    .ok()
+                    .and_then(|pin| String::from_utf8(pin).ok())
+                    .ok_or_else(|| corrupted("a PIN failed authentication"))?,
+            ),
+            _ => None,
+        };
+        manager.insert_credential(Credential {
+            name: credential.name,
+            kind: credential.kind,
+            issuer: credential.issuer,
+            tags: credential.tags,
+            params: credential.params,
+            secret,
+            pin,
+            last_step: credential.last_step,
+            recovery_codes: Vec::new(),
+        });
+    }
+    Ok(manager)
+}
+
+fn decrypt_data(
+    master_key_material: &[u8],
+    ciphertext: &[u8],
+    nonce_bytes: &[u8],
+) -> Result<Vec<u8>, AppError> {
+    if nonce_bytes.len() != 12 {
+        return Err(AppError::DecryptionError);
+    }
+    let mut hasher = Sha256::new();
+    hasher.update(master_key_material);
+    let key_bytes = hasher.finalize();
+
+    let key = Key::<Aes256Gcm>::from_slice(key_bytes.as_slice());
+    let cipher = Aes256Gcm::new(key);
+    let nonce = Nonce::from_slice(nonce_bytes);
+
+    cipher
+        .decrypt(nonce, ciphertext)
+        .map_err(|_| AppError::DecryptionError)
+}
@@ -0,0 +1,761 @@
+//! 保管庫ファイル (`credentials.toml`) の形式
+//!
+//! version 2 の保管庫は平文の TOML で、認証付き暗号化された1つの封筒 (envelope) を表す。
+//!
+//! ```toml
+//! version = 2
+//!
+//! [[slots]]                 # データ鍵をラップしたもの (メンバーごとに1つ以上)
+//! type = "password"
+//! member = "owner"
+//! salt = "<Base64>"         # scrypt のソルト
+//! log_n = 15                # scrypt のパラメーター (N = 2^log_n)
+//! r = 8
+//! p = 1
+//! public_key = "<Base64>"   # scrypt の出力を秘密鍵とする X25519 公開鍵
+//! ephemeral_key = "<Base64>"
+//! nonce = "<Base64>"
+//! wrapped_key = "<Base64>"  # AES-256-GCM で暗号化した 32 バイトのデータ鍵
+//!
+//! [[slots]]
+//! type = "x25519"
+//! member = "carol"
+//! public_key = "<Base64>"   # メンバーの識別子 (GAUTH-IDENTITY-...) の公開鍵
+//! ephemeral_key = "<Base64>"
+//! nonce = "<Base64>"
+//! wrapped_key = "<Base64>"
+//!
+//! [payload]
+//! nonce = "<Base64>"
+//! ciphertext = "<Base64>"   # データ鍵で AES-256-GCM 暗号化した中身
+//! ```
+//!
+//! `payload` を復号すると次の TOML になる。シークレットは Base32 文字列として格納される。
+//!
+//! ```toml
+//! time_offset = 0
+//!
+//! [credentials.example]
+//! name = "example"
+//! kind = "totp"
+//! tags = []
+//! secret = "JBSWY3DPEHPK3PXP"
+//! params = { algorithm = "SHA1", digits = 6, period = 30 }
+//! ```
+//!
+//! データ鍵はランダムに生成され、パスワードを変更してもスロットを作り直すだけで済む。
+//!
+//! スロットはいずれもデータ鍵を受信者の X25519 公開鍵にラップする。一時鍵との共有秘密から
+//! SHA-256 でラップ鍵を導出するため、メンバーを削除した際はパスワードを知らなくても
+//! 新しいデータ鍵を残りのメンバーにラップし直せる。
+
+use crate::error::AppError;
+use crate::random::Random;
+use aes_gcm::{
+    Aes256Gcm, Key, Nonce,
+    aead::{Aead, KeyInit, Payload},
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::sync::Arc;
+use x25519_dalek::{PublicKey, StaticSecret};
+
+/// 現在の保管庫形式のバージョン
+pub const VERSION: u32 = 2;
+
+/// 新しいパスワードスロットに使う scrypt のパラメーター
+const SCRYPT_LOG_N: u8 = 15;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+/// 読み込むスロットで受け付ける scrypt のコストの上限
+///
+/// 改ざんされたヘッダーで解錠に膨大な時間やメモリを使わされないよう、
+/// gauth が書き込む値より大きいものは拒否する。
+const MAX_SCRYPT_LOG_N: u8 = 20;
+
+/// 暗号文をバージョンと用途に結び付けるための追加認証データ
+const PAYLOAD_AAD: &[u8] = b"gauth vault v2 payload";
+const SLOT_AAD: &[u8] = b"gauth vault v2 slot";
+/// X25519 の共有秘密からラップ鍵を導出する際の区別用の文字列
+const WRAP_DOMAIN: &[u8] = b"gauth vault v2 x25519";
+
+/// 初期のスロットのメンバー名
+pub const OWNER: &str = "owner";
+/// 識別子 (秘密鍵) と公開鍵の文字列表現の接頭辞
+pub const IDENTITY_PREFIX: &str = "GAUTH-IDENTITY-";
+pub const PUBLIC_KEY_PREFIX: &str = "gauth-pk-";
+const KEY_ALPHABET: base32::Alphabet = base32::Alphabet::Rfc4648 { padding: false };
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Envelope {
+    pub version: u32,
+    pub slots: Vec<Slot>,
+    pub payload: Sealed,
+}
+
+/// データ鍵のラップ方式
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Slot {
+    /// パスワードで開くスロット
+    Password(PasswordSlot),
+    /// X25519 の識別子 (秘密鍵) で開くスロット
+    X25519(X25519Slot),
+}
+
+fn owner() -> String {
+    OWNER.into()
+}
+
+/// パスワードから scrypt で X25519 の鍵ペアを導出し、その公開鍵にデータ鍵をラップする
+///
+/// 公開鍵を保存しておくことで、パスワードを知らなくてもデータ鍵を再ラップできる。
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PasswordSlot {
+    #[serde(default = "owner")]
+    pub member: String,
+    #[serde(with = "base64_bytes")]
+    pub salt: Vec<u8>,
+    pub log_n: u8,
+    pub r: u32,
+    pub p: u32,
+    #[serde(with = "base64_bytes")]
+    pub public_key: Vec<u8>,
+    #[serde(with = "base64_bytes")]
+    pub ephemeral_key: Vec<u8>,
+    #[serde(with = "base64_bytes")]
+    pub nonce: Vec<u8>,
+    #[serde(with = "base64_bytes")]
+    pub wrapped_key: Vec<u8>,
+}
+
+/// メンバーの X25519 公開鍵にデータ鍵をラップする
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct X25519Slot {
+    pub member: String,
+    #[serde(with = "base64_bytes")]
+    pub public_key: Vec<u8>,
+    #[serde(with = "base64_bytes")]
+    pub ephemeral_key: Vec<u8>,
+    #[serde(with = "base64_bytes")]
+    pub nonce: Vec<u8>,
+    #[serde(with = "base64_bytes")]
+    pub wrapped_key: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Sealed {
+    #[serde(with = "base64_bytes")]
+    pub nonce: Vec<u8>,
+    #[serde(with = "base64_bytes")]
+    pub ciphertext: Vec<u8>,
+}
+
+mod base64_bytes {
+    use base64::{Engine, engine::general_purpose::STANDARD};
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        STANDARD.decode(value).map_err(D::Error::custom)
+    }
+}
+
+impl Envelope {
+    /// ファイルの内容を version 2 以降の保管庫として解釈する
+    ///
+    /// 旧形式 (TOML 全体を暗号化したバイナリ) の場合は `None` を返す。
+    pub fn parse(contents: &[u8]) -> Result<Option<Self>, AppError> {
+        #[derive(Deserialize)]
+        struct Probe {
+            version: u32,
+        }
+
+        let Ok(text) = std::str::from_utf8(contents) else {
+            return Ok(None);
+        };
+        let Ok(probe) = toml::from_str::<Probe>(text) else {
+            // 旧形式は暗号文そのものなので、TOML らしいテキストが読めなければ壊れている
+            if text.trim_start().starts_with("version") {
+                return Err(AppError::VaultCorrupted(
+                    "the header is not valid TOML".into(),
+                ));
+            }
+            return Ok(None);
+        };
+        if probe.version != VERSION {
+            return Err(AppError::SecretManagerError(format!(
+                "Unsupported vault version {} (this build supports version {})",
+                probe.version, VERSION
+            )));
+        }
+        let envelope: Envelope = toml::from_str(text)
+            .map_err(|e| AppError::VaultCorrupted(format!("invalid header: {}", e.message())))?;
+        if envelope.slots.is_empty() {
+            return Err(AppError::VaultCorrupted(
+                "the vault has no key slots".into(),
+            ));
+        }
+        Ok(Some(envelope))
+    }
+
+    pub fn to_toml(&self) -> Result<String, AppError> {
+        Ok(toml::to_string(self)?)
+    }
+}
+
+/// X25519 の識別子 (秘密鍵)
+///
+/// 文字列表現 (`GAUTH-IDENTITY-...`) はマスターパスワードの代わりにキーリングへ保存して使う。
+#[derive(Clone)]
+pub struct Identity(StaticSecret);
+
+impl Identity {
+    pub fn generate(random: &dyn Random) -> Self {
+        let mut secret = [0u8; 32];
+        random.fill(&mut secret);
+        Identity(StaticSecret::from(secret))
+    }
+
+    /// 識別子の文字列でなければ `None`
+    pub fn parse(value: &str) -> Option<Self> {
+        let encoded = value.trim().strip_prefix(IDENTITY_PREFIX)?;
+        let bytes: [u8; 32] = base32::decode(KEY_ALPHABET, encoded)?.try_into().ok()?;
+        Some(Identity(StaticSecret::from(bytes)))
+    }
+
+    pub fn encode(&self) -> String {
+        format!(
+            "{}{}",
+            IDENTITY_PREFIX,
+            base32::encode(KEY_ALPHABET, self.0.as_bytes())
+        )
+    }
+
+    /// 他のメンバーに渡す公開鍵 (`gauth-pk-...`)
+    pub fn public_key(&self) -> String {
+        encode_public_key(&PublicKey::from(&self.0))
+    }
+}
+
+fn encode_public_key(public_key: &PublicKey) -> String {
+    format!(
+        "{}{}",
+        PUBLIC_KEY_PREFIX,
+        base32::encode(KEY_ALPHABET, public_key.as_bytes()).to_lowercase()
+    )
+}
+
+/// `gauth-pk-...` 形式の公開鍵を読み取る
+pub fn parse_public_key(value: &str) -> Result<PublicKey, AppError> {
+    let invalid = || AppError::SecretManagerError(format!("Invalid public key: {}", value));
+    let encoded = value
+        .trim()
+        .strip_prefix(PUBLIC_KEY_PREFIX)
+        .ok_or_else(invalid)?;
+    let bytes: [u8; 32] = base32::decode(KEY_ALPHABET, &encoded.to_uppercase())
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(invalid)?;
+    Ok(PublicKey::from(bytes))
+}
+
+/// 保管庫を開けるメンバー
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Member {
+    pub name: String,
+    pub kind: MemberKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MemberKind {
+    Password,
+    /// `gauth-pk-...` 形式の公開鍵
+    PublicKey(String),
+}
+
+impl Slot {
+    fn member(&self) -> &str {
+        match self {
+            Slot::Password(slot) => &slot.member,
+            Slot::X25519(slot) => &slot.member,
+        }
+    }
+
+    /// マスターパスワード (または識別子の文字列) でスロットを開く
+    fn unwrap_key(&self, secret: &str) -> Result<[u8; 32], AppError> {
+        match (self, Identity::parse(secret)) {
+            (Slot::X25519(slot), Some(identity)) => slot.unwrap_key(&identity),
+            (Slot::Password(slot), _) => slot.unwrap_key(secret),
+            _ => Err(AppError::DecryptionError),
+        }
+    }
+}
+
+/// 復号済みのデータ鍵と、それをラップしたスロット
+#[derive(Clone)]
+pub struct VaultKey {
+    data_key: [u8; 32],
+    slots: Vec<Slot>,
+    /// 自分が開いたスロットの位置
+    unlocked: usize,
+    /// スロットの作り直しと暗号化に使う乱数
+    random: Arc<dyn Random>,
+}
+
+impl fmt::Debug for VaultKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VaultKey")
+            .field("data_key", &"<redacted>")
+            .field("slots", &self.slots.len())
+            .finish()
+    }
+}
+
+impl VaultKey {
+    /// 新しいデータ鍵を生成し、マスターパスワード (または識別子) のスロットでラップする
+    pub fn generate(secret: &str, random: Arc<dyn Random>) -> Result<Self, AppError> {
+        let mut data_key = [0u8; 32];
+        random.fill(&mut data_key);
+        let slot = match Identity::parse(secret) {
+            Some(identity) => Slot::X25519(X25519Slot::new(
+                OWNER.into(),
+                &PublicKey::from(&identity.0),
+                &data_key,
+                &*random,
+            )?),
+            None => Slot::Password(PasswordSlot::new(
+                OWNER.into(),
+                &data_key,
+                secret,
+                &*random,
+            )?),
+        };
+        Ok(VaultKey {
+            data_key,
+            slots: vec![slot],
+            unlocked: 0,
+            random,
+        })
+    }
+
+    /// マスターパスワード (または識別子) でいずれかのスロットを開き、データ鍵を取り出す
+    pub fn unlock(
+        envelope: &Envelope,
+        secret: &str,
+        random: Arc<dyn Random>,
+    ) -> Result<Self, AppError> {
+        for (index, slot) in envelope.slots.iter().enumerate() {
+            match slot.unwrap_key(secret) {
+                Ok(data_key) => {
+                    return Ok(VaultKey {
+                        data_key,
+                        slots: envelope.slots.clone(),
+                        unlocked: index,
+                        random,
+                    });
+                }
+                // 改ざんされたスロットをパスワード違いとして扱うと、入力を繰り返させてしまう
+                Err(e @ AppError::VaultCorrupted(_)) => return Err(e),
+                Err(_) => {}
+            }
+        }
+        Err(AppError::WrongMasterPassword)
+    }
+
+    /// 中身を暗号化して封筒を作る
+    pub fn seal(&self, plaintext: &[u8]) -> Result<Envelope, AppError> {
+        Ok(Envelope {
+            version: VERSION,
+            slots: self.slots.clone(),
+            payload: encrypt(&self.data_key, plaintext, PAYLOAD_AAD, &*self.random)?,
+        })
+    }
+
+    /// 封筒の中身を復号する
+    ///
+    /// データ鍵は正しいのに認証に失敗した場合は、ファイルが壊れている。
+    pub fn open(&self, envelope: &Envelope) -> Result<Vec<u8>, AppError> {
+        decrypt(&self.data_key, &envelope.payload, PAYLOAD_AAD)
+            .map_err(|_| AppError::VaultCorrupted("the contents failed authentication".into()))
+    }
+
+    pub fn members(&self) -> Vec<Member> {
+        self.slots
+            .iter()
+            .map(|slot| Member {
+                name: slot.member().to_string(),
+                kind: match slot {
+                    Slot::Password(_) => MemberKind::Password,
+                    Slot::X25519(slot) => {
+                        let bytes: [u8; 32] = slot.public_key.clone().try_into().unwrap_or([0; 32]);
+                        MemberKind::PublicKey(encode_public_key(&PublicKey::from(bytes)))
+                    }
+                },
+            })
+            .collect()
+    }
+
+    fn ensure_new_member(&self, name: &str) -> Result<(), AppError> {
+        if self.slots.iter().any(|slot| slot.member() == name) {
+            return Err(AppError::SecretManagerError(format!(
+                "Member '{}' already exists",
+                name
+            )));
+        }
+        Ok(())
+    }
+
+    /// メンバー自身のパスワードで開けるスロットを追加
+    pub fn add_password_member(&mut self, name: &str, password: &str) -> Result<(), AppError> {
+        self.ensure_new_member(name)?;
+        let slot = PasswordSlot::new(name.into(), &self.data_key, password, &*self.random)?;
+        self.slots.push(Slot::Password(slot));
+        Ok(())
+    }
+
+    /// メンバーの X25519 公開鍵で開けるスロットを追加
+    pub fn add_public_key_member(&mut self, name: &str, public_key: &str) -> Result<(), AppError> {
+        self.ensure_new_member(name)?;
+        let public_key = parse_public_key(public_key)?;
+        let slot = X25519Slot::new(name.into(), &public_key, &self.data_key, &*self.random)?;
+        self.slots.push(Slot::X25519(slot));
+        Ok(())
+    }
+
+    /// メンバーを削除し、データ鍵を作り直して残りのメンバーにラップし直す
+    ///
+    /// 削除したメンバーは以前のデータ鍵を知っているため、鍵を入れ替えて以降の内容を読めなくする。
+    pub fn remove_member(&mut self, name: &str) -> Result<(), AppError> {
+        let index = self
+            .slots
+            .iter()
+            .position(|slot| slot.member() == name)
+            .ok_or_else(|| AppError::NotFound(format!("Member '{}' not found", name)))?;
+        if index == self.unlocked {
+            return Err(AppError::SecretManagerError(format!(
+                "Cannot remove '{}' because this vault is unlocked as that member",
+                name
+            )));
+        }
+
+        let random = &*self.random;
+        let mut data_key = [0u8; 32];
+        random.fill(&mut data_key);
+        let mut slots = Vec::with_capacity(self.slots.len() - 1);
+        for (position, slot) in self.slots.iter().enumerate() {
+            if position == index {
+                continue;
+            }
+            let rewrapped = match slot {
+                Slot::Password(slot) => Slot::Password(slot.rewrap(&data_key, random)?),
+                Slot::X25519(slot) => Slot::X25519(slot.rewrap(&data_key, random)?),
+            };
+            slots.push(rewrapped);
+        }
+        if index < self.unlocked {
+            self.unlocked -= 1;
+        }
+        self.slots = slots;
+        self.data_key = data_key;
+        Ok(())
+    }
+
+    /// 復元用に分割するデータ鍵
+    pub fn data_key(&self) -> [u8; 32] {
+        self.data_key
+    }
+
+    /// 同じデータ鍵か (メンバーの削除で鍵が入れ替わっていないか)
+    pub fn same_key(&self, other: &VaultKey) -> bool {
+        self.data_key == other.data_key
+    }
+
+    /// 同じデータ鍵で、メンバーのスロットも変わっていないか
+    pub fn same_members(&self, other: &VaultKey) -> bool {
+        self.same_key(other) && self.slots == other.slots
+    }
+
+    /// 基準・手元・相手の鍵から、マージした内容の暗号化に使う鍵を決める
+    ///
+    /// 相手だけがメンバーを変更していれば (鍵の入れ替えを含む) 相手の鍵、手元だけなら手元の鍵を使う。
+    /// 双方が別々に変更していた場合は、どちらかの変更を取り消してしまうためエラーにする。
+    /// 基準がない場合 (初回の同期や未保存の新しい保管庫) は、共有されている相手の鍵を使う。
+    pub fn merge(
+        base: Option<&VaultKey>,
+        local: Option<&VaultKey>,
+        remote: Option<&VaultKey>,
+    ) -> Result<Option<VaultKey>, AppError> {
+        match (base, local, remote) {
+            (_, local, None) => Ok(local.cloned()),
+            (Some(base), Some(local), Some(remote)) => {
+                if base.same_members(remote) {
+                    Ok(Some(local.clone()))
+                } else if base.same_members(local) || local.same_members(remote) {
+                    Ok(Some(remote.clone()))
+                } else {
+                    Err(AppError::VaultKeyChanged)
+                }
+            }
+            (_, _, Some(remote)) => Ok(Some(remote.clone())),
+        }
+    }
+
+    /// 復元したデータ鍵で保管庫を開く
+    ///
+    /// 鍵が正しいことは中身の復号 (AES-GCM の認証) で確かめる。どのスロットも開いていないため、
+    /// 保存する前に [`VaultKey::set_password`] でパスワードを設定する必要がある。
+    pub fn recover(
+        envelope: &Envelope,
+        data_key: [u8; 32],
+        random: Arc<dyn Random>,
+    ) -> Result<Self, AppError> {
+        decrypt(&data_key, &envelope.payload, PAYLOAD_AAD)?;
+        Ok(VaultKey {
+            data_key,
+            slots: envelope.slots.clone(),
+            unlocked: usize::MAX,
+            random,
+        })
+    }
+
+    /// メンバーのパスワードを設定し、そのスロットで開いたものとして扱う
+    ///
+    /// 既存のスロットは置き換え、なければ追加する。
+    pub fn set_password(&mut self, member: &str, password: &str) -> Result<(), AppError> {
+        let slot = Slot::Password(PasswordSlot::new(
+            member.into(),
+            &self.data_key,
+            password,
+            &*self.random,
+        )?);
+        match self.slots.iter().position(|slot| slot.member() == member) {
+            Some(index) => {
+                self.slots[index] = slot;
+                self.unlocked = index;
+            }
+            None => {
+                self.slots.push(slot);
+                self.unlocked = self.slots.len() - 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 一時的な X25519 鍵で受信者の公開鍵とのラップ鍵を作る
+fn wrapping_key(shared: &[u8; 32], ephemeral: &PublicKey, recipient: &PublicKey) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(WRAP_DOMAIN);
+    hasher.update(shared);
+    hasher.update(ephemeral.as_bytes());
+    hasher.update(recipient.as_bytes());
+    hasher.finalize().into()
+}
+
+/// データ鍵を公開鍵にラップし、一時公開鍵と暗号文を返す
+fn wrap_to(
+    recipient: &PublicKey,
+    data_key: &[u8; 32],
+    random: &dyn Random,
+) -> Result<(Vec<u8>, Sealed), AppError> {
+    let mut ephemeral = [0u8; 32];
+    random.fill(&mut ephemeral);
+    let ephemeral = StaticSecret::from(ephemeral);
+    let ephemeral_public = PublicKey::from(&ephemeral);
+    let shared = ephemeral.diffie_hellman(recipient);
+    if !shared.was_contributory() {
+        return Err(AppError::SecretManagerError("Invalid public key".into()));
+    }
+    let key = wrapping_key(shared.as_bytes(), &ephemeral_public, recipient);
+    let sealed = encrypt(&key, data_key, SLOT_AAD, random)?;
+    Ok((ephemeral_public.as_bytes().to_vec(), sealed))
+}
+
+fn unwrap_with(
+    secret: &StaticSecret,
+    ephemeral_key: &[u8],
+    nonce: &[u8],
+    wrapped_key: &[u8],
+) -> Result<[u8; 32], AppError> {
+    let ephemeral: [u8; 32] = ephemeral_key
+        .try_into()
+        .map_err(|_| AppError::DecryptionError)?;
+    let ephemeral = PublicKey::from(ephemeral);
+    let shared = secret.diffie_hellman(&ephemeral);
+    let key = wrapping_key(shared.as_bytes(), &ephemeral, &PublicKey::from(secret));
+    let sealed = Sealed {
+        nonce: nonce.to_vec(),
+        ciphertext: wrapped_key.to_vec(),
+    };
+    decrypt(&key, &sealed, SLOT_AAD)?
+        .try_into()
+        .map_err(|_| AppError::DecryptionError)
+}
+
+impl PasswordSlot {
+    fn new(
+        member: String,
+        data_key: &[u8; 32],
+        password: &str,
+        random: &dyn Random,
+    ) -> Result<Self, AppError> {
+        let mut salt = vec![0u8; 32];
+        random.fill(&mut salt);
+        let secret = StaticSecret::from(derive_key(
+            password,
+            &salt,
+            SCRYPT_LOG_N,
+            SCRYPT_R,
+            SCRYPT_P,
+        )?);
+        let public_key = PublicKey::from(&secret);
+        let (ephemeral_key, sealed) = wrap_to(&public_key, data_key, random)?;
+        Ok(PasswordSlot {
+            member,
+            salt,
+            log_n: SCRYPT_LOG_N,
+            r: SCRYPT_R,
+            p: SCRYPT_P,
+            public_key: public_key.as_bytes().to_vec(),
+            ephemeral_key,
+            nonce: sealed.nonce,
+            wrapped_key: sealed.ciphertext,
+        })
+    }
+
+    fn unwrap_key(&self, password: &str) -> Result<[u8; 32], AppError> {
+        let derived = derive_key(password, &self.salt, self.log_n, self.r, self.p)?;
+        unwrap_with(
+            &StaticSecret::from(derived),
+            &self.ephemeral_key,
+            &self.nonce,
+            &self.wrapped_key,
+        )
+    }
+
+    fn rewrap(&self, data_key: &[u8; 32], random: &dyn Random) -> Result<Self, AppError> {
+        let public_key: [u8; 32] = self
+            .public_key
+            .clone()
+            .try_into()
+            .map_err(|_| AppError::SecretManagerError("Invalid public key".into()))?;
+        let (ephemeral_key, sealed) = wrap_to(&PublicKey::from(public_key), data_key, random)?;
+        Ok(PasswordSlot {
+            ephemeral_key,
+            nonce: sealed.nonce,
+            wrapped_key: sealed.ciphertext,
+            ..self.clone()
+        })
+    }
+}
+
+impl X25519Slot {
+    fn new(
+        member: String,
+        public_key: &PublicKey,
+        data_key: &[u8; 32],
+        random: &dyn Random,
+    ) -> Result<Self, AppError> {
+        let (ephemeral_key, sealed) = wrap_to(public_key, data_key, random)?;
+        Ok(X25519Slot {
+            member,
+            public_key: public_key.as_bytes().to_vec(),
+            ephemeral_key,
+            nonce: sealed.nonce,
+            wrapped_key: sealed.ciphertext,
+        })
+    }
+
+    fn unwrap_key(&self, identity: &Identity) -> Result<[u8; 32], AppError> {
+        if PublicKey::from(&identity.0).as_bytes().as_slice() != self.public_key {
+            return Err(AppError::DecryptionError);
+        }
+        unwrap_with(
+            &identity.0,
+            &self.ephemeral_key,
+            &self.nonce,
+            &self.wrapped_key,
+        )
+    }
+
+    fn rewrap(&self, data_key: &[u8; 32], random: &dyn Random) -> Result<Self, AppError> {
+        let public_key: [u8; 32] = self
+            .public_key
+            .clone()
+            .try_into()
+            .map_err(|_| AppError::SecretManagerError("Invalid public key".into()))?;
+        X25519Slot::new(
+            self.member.clone(),
+            &PublicKey::from(public_key),
+            data_key,
+            random,
+        )
+    }
+}
+
+/// scrypt でパスワードから鍵を導出
+fn derive_key(
+    password: &str,
+    salt: &[u8],
+    log_n: u8,
+    r: u32,
+    p: u32,
+) -> Result<[u8; 32], AppError> {
+    if log_n > MAX_SCRYPT_LOG_N || r > SCRYPT_R || p > SCRYPT_P {
+        return Err(AppError::VaultCorrupted(format!(
+            "scrypt parameters log_n={}, r={}, p={} exceed the supported limits",
+            log_n, r, p
+        )));
+    }
+    let params = scrypt::Params::new(log_n, r, p, 32)
+        .map_err(|e| AppError::SecretManagerError(format!("Invalid scrypt parameters: {}", e)))?;
+    let mut key = [0u8; 32];
+    scrypt::scrypt(password.as_bytes(), salt, &params, &mut key)
+        .map_err(|e| AppError::SecretManagerError(format!("scrypt failed: {}", e)))?;
+    Ok(key)
+}
+
+fn encrypt(
+    key: &[u8; 32],
+    plaintext: &[u8],
+    aad: &[u8],
+    random: &dyn Random,
+) -> Result<Sealed, AppError> {
+    let mut nonce = [0u8; 12];
+    random.fill(&mut nonce);
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    let ciphertext = cipher
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
+        .map_err(|_| AppError::EncryptionError)?;
+    Ok(Sealed {
+        nonce: nonce.to_vec(),
+        ciphertext,
+    })
+}
+
+fn decrypt(key: &[u8; 32], sealed: &Sealed, aad: &[u8]) -> Result<Vec<u8>, AppError> {
+    if sealed.nonce.len() != 12 {
+        return Err(AppError::DecryptionError);
+    }
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    cipher
+        .decrypt(
+            Nonce::from_slice(&sealed.nonce),
+            Payload {
+                msg: &sealed.ciphertext,
+                aad,
+            },
+        )
+        .map_err(|_| AppError::DecryptionError)
+}
@@ -0,0 +1,123 @@
+//! 名前の引数から認証情報を選ぶ
+//!
+//! 次の順に解釈する (完全一致以外は大文字小文字を区別しない)。
+//!  - `tag:work`: タグが work の認証情報すべて
+//!  - `aws-*` / `git?ub`: glob (`*` は任意の文字列、`?` は任意の1文字)
+//!  - 名前と完全に一致するもの
+//!  - 大文字小文字を除いて一致するもの
+//!  - 名前の先頭部分
+
+use crate::error::AppError;
+use crate::secrets::Credential;
+use dialoguer::{FuzzySelect, theme::ColorfulTheme};
+use std::io::IsTerminal;
+
+/// タグで選ぶ場合の接頭辞
+pub const TAG_PREFIX: &str = "tag:";
+
+/// 選択に一致する認証情報の名前 (名前順)
+pub fn matches(credentials: &[&Credential], selector: &str) -> Vec<String> {
+    let lower = selector.to_lowercase();
+    let mut names: Vec<String> = if let Some(tag) = lower.strip_prefix(TAG_PREFIX) {
+        filter(credentials, |credential| {
+            credential.tags.iter().any(|t| t.to_lowercase() == tag)
+        })
+    } else if selector.contains(['*', '?']) {
+        filter(credentials, |credential| {
+            glob(&lower, &credential.name.to_lowercase())
+        })
+    } else if credentials.iter().any(|c| c.name == selector) {
+        vec![selector.to_string()]
+    } else {
+        let same = filter(credentials, |credential| {
+            credential.name.to_lowercase() == lower
+        });
+        if same.is_empty() {
+            filter(credentials, |credential| {
+                credential.name.to_lowercase().starts_with(&lower)
+            })
+        } else {
+            same
+        }
+    };
+    names.sort();
+    names
+}
+
+fn filter(credentials: &[&Credential], predicate: impl Fn(&Credential) -> bool) -> Vec<String> {
+    credentials
+        .iter()
+        .filter(|credential| predicate(credential))
+        .map(|credential| credential.name.clone())
+        .collect()
+}
+
+/// `*` と `?` だけの glob
+fn glob(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    // 直前の `*` の位置と、そこから照合し直す text の位置
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// 1つの認証情報を選ぶ
+///
+/// 複数に一致した場合は、端末ならあいまい検索の一覧から選ばせ、そうでなければエラーにする。
+pub fn one(credentials: &[&Credential], selector: &str) -> Result<String, AppError> {
+    let mut names = matches(credentials, selector);
+    match names.len() {
+        0 => Err(AppError::CredentialNotFound(selector.to_string())),
+        1 => Ok(names.remove(0)),
+        _ if std::io::stdin().is_terminal() => {
+            let index = FuzzySelect::with_theme(&ColorfulTheme::default())
+                .with_prompt(format!("'{}' matches several credentials", selector))
+                .items(&names)
+                .default(0)
+                .interact()?;
+            Ok(names.remove(index))
+        }
+        _ => Err(AppError::AmbiguousSelector(
+            selector.to_string(),
+            names.join(", "),
+        )),
+    }
+}
+
+/// 複数の選択に一致する認証情報をすべて選ぶ (重複は除き、指定した順に並べる)
+pub fn all(credentials: &[&Credential], selectors: &[String]) -> Result<Vec<String>, AppError> {
+    let mut selected: Vec<String> = Vec::new();
+    for selector in selectors {
+        let names = matches(credentials, selector);
+        if names.is_empty() {
+            return Err(AppError::CredentialNotFound(selector.clone()));
+        }
+        for name in names {
+            if !selected.contains(&name) {
+                selected.push(name);
+            }
+        }
+    }
+    Ok(selected)
+}
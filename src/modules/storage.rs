@@ -0,0 +1,181 @@
+//! 暗号化済み保管庫の保存先
+//!
+//! バックエンドは暗号化されたバイト列だけを扱い、中身の解釈は `SecretManager` が行う。
+
+mod file;
+mod git;
+mod keyring;
+mod memory;
+mod webdav;
+pub use file::FileStorage;
+pub use git::{GitStorage, Revision};
+pub use keyring::KeyringStorage;
+pub use memory::MemoryStorage;
+pub use webdav::WebDavStorage;
+
+use crate::config::{Config, StorageConfig};
+use crate::error::AppError;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// 保存されている内容の版 (内容のハッシュ、ETag など)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Version(pub String);
+
+impl Version {
+    /// 内容の SHA-256 を版として使う
+    pub fn of(data: &[u8]) -> Self {
+        Version(hex::encode(Sha256::digest(data)))
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+pub trait Storage: fmt::Debug + Send + Sync {
+    /// 保存されている内容と版を読み込む (まだ何も保存されていなければ `None`)
+    fn read(&self) -> Result<Option<(Vec<u8>, Version)>, AppError>;
+
+    /// 現在の版が `expected` と一致する場合だけ書き込み、新しい版を返す
+    ///
+    /// `expected` が `None` の場合はまだ何も保存されていないことを期待する。
+    /// 一致しなければ `AppError::StorageConflict` を返す。
+    fn write(&self, data: &[u8], expected: Option<&Version>) -> Result<Version, AppError>;
+
+    /// 他のプロセスによる書き込みを防ぐロックを取得 (ガードを破棄すると解放)
+    fn lock(&self) -> Result<LockGuard, AppError>;
+
+    /// 変更内容の説明付きで書き込む (履歴を持つ保存先はこれを記録する)
+    fn write_change(
+        &self,
+        data: &[u8],
+        expected: Option<&Version>,
+        _message: &str,
+    ) -> Result<Version, AppError> {
+        self.write(data, expected)
+    }
+
+    /// 表示用の保存先の説明
+    fn describe(&self) -> String;
+
+    /// 移行前の内容を退避する (対応していなければ何もしない)
+    fn backup(&self, _data: &[u8], _suffix: &str) -> Result<Option<String>, AppError> {
+        Ok(None)
+    }
+
+    /// git リポジトリの保存先であれば履歴・同期の操作を返す
+    fn as_git(&self) -> Option<&GitStorage> {
+        None
+    }
+}
+
+/// ロックを保持している間だけ生存するガード
+pub struct LockGuard {
+    release: Option<Box<dyn FnOnce() + Send>>,
+}
+
+impl LockGuard {
+    pub fn new(release: impl FnOnce() + Send + 'static) -> Self {
+        LockGuard {
+            release: Some(Box::new(release)),
+        }
+    }
+}
+
+impl Drop for LockGuard {
+    fn drop(&mut self) {
+        if let Some(release) = self.release.take() {
+            release();
+        }
+    }
+}
+
+/// ロックファイルの取得を諦めるまでの時間
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// ロックファイルを排他的に作成してロックを取得
+pub(crate) fn lock_file(path: PathBuf) -> Result<LockGuard, AppError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let started = Instant::now();
+    loop {
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                writeln!(file, "{}", std::process::id())?;
+                return Ok(LockGuard::new(move || {
+                    let _ = fs::remove_file(&path);
+                }));
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                if started.elapsed() >= LOCK_TIMEOUT {
+                    return Err(AppError::StorageLocked(path.display().to_string()));
+                }
+                thread::sleep(Duration::from_millis(50));
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// ファイルの内容と版を読み込む
+pub(crate) fn read_file(path: &Path) -> Result<Option<(Vec<u8>, Version)>, AppError> {
+    match fs::read(path) {
+        Ok(data) => {
+            let version = Version::of(&data);
+            Ok(Some((data, version)))
+        }
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// 現在の版と期待する版を比較
+pub(crate) fn check_version(
+    current: Option<&Version>,
+    expected: Option<&Version>,
+) -> Result<(), AppError> {
+    if current == expected {
+        Ok(())
+    } else {
+        Err(AppError::StorageConflict)
+    }
+}
+
+/// 設定ファイルで選択された保存先を開く
+pub fn from_config() -> Result<Arc<dyn Storage>, AppError> {
+    open(&Config::load()?.storage)
+}
+
+pub fn open(config: &StorageConfig) -> Result<Arc<dyn Storage>, AppError> {
+    match config {
+        StorageConfig::File { path: Some(path) } => Ok(Arc::new(FileStorage::new(path.clone()))),
+        StorageConfig::File { path: None } => Ok(Arc::new(FileStorage::default_path()?)),
+        StorageConfig::Keyring => Ok(Arc::new(KeyringStorage::new()?)),
+        StorageConfig::Git { path, remote } => {
+            let dir = match path {
+                Some(path) => path.clone(),
+                None => GitStorage::default_dir()?,
+            };
+            Ok(Arc::new(GitStorage::new(dir, remote.clone())))
+        }
+        StorageConfig::Webdav {
+            url,
+            username,
+            password,
+        } => Ok(Arc::new(WebDavStorage::new(
+            url.clone(),
+            username.clone(),
+            password.clone(),
+        ))),
+    }
+}
@@ -0,0 +1,68 @@
+use super::{LockGuard, Storage, Version, check_version, lock_file, read_file};
+use crate::config;
+use crate::error::AppError;
+use std::fs;
+use std::path::PathBuf;
+
+/// ローカルファイルに保存する
+#[derive(Debug, Clone)]
+pub struct FileStorage {
+    path: PathBuf,
+}
+
+impl FileStorage {
+    pub fn new(path: PathBuf) -> Self {
+        FileStorage { path }
+    }
+
+    /// 設定ディレクトリの credentials.toml
+    pub fn default_path() -> Result<Self, AppError> {
+        Ok(FileStorage::new(
+            config::get_config_dir()?.join("credentials.toml"),
+        ))
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    fn sibling(&self, suffix: &str) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(suffix);
+        PathBuf::from(path)
+    }
+}
+
+impl Storage for FileStorage {
+    fn read(&self) -> Result<Option<(Vec<u8>, Version)>, AppError> {
+        read_file(&self.path)
+    }
+
+    fn write(&self, data: &[u8], expected: Option<&Version>) -> Result<Version, AppError> {
+        let current = read_file(&self.path)?.map(|(_, version)| version);
+        check_version(current.as_ref(), expected)?;
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // 書き込み途中で中断しても元のファイルが壊れないよう、一時ファイルから置き換える
+        let temporary = self.sibling(".tmp");
+        fs::write(&temporary, data)?;
+        fs::rename(&temporary, &self.path)?;
+        Ok(Version::of(data))
+    }
+
+    fn lock(&self) -> Result<LockGuard, AppError> {
+        lock_file(self.sibling(".lock"))
+    }
+
+    fn describe(&self) -> String {
+        format!("file {}", self.path.display())
+    }
+
+    fn backup(&self, data: &[u8], suffix: &str) -> Result<Option<String>, AppError> {
+        let path = self.sibling(suffix);
+        fs::write(&path, data)?;
+        Ok(Some(path.display().to_string()))
+    }
+}
@@ -0,0 +1,306 @@
+use super::{LockGuard, Storage, Version, check_version, lock_file, read_file};
+use crate::config;
+use crate::error::AppError;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// リポジトリ内の保管庫ファイル名
+const VAULT_FILE: &str = "credentials.toml";
+/// 同期先として登録するリモート名
+const REMOTE_NAME: &str = "origin";
+
+/// git リポジトリで保管庫を管理する
+///
+/// 保存するたびに暗号化済みの保管庫をコミットし、`gauth sync` でリモートと同期する。
+/// git コマンドが必要。
+#[derive(Debug, Clone)]
+pub struct GitStorage {
+    dir: PathBuf,
+    remote: Option<String>,
+}
+
+/// 保管庫を変更したコミット
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Revision {
+    pub id: String,
+    pub date: String,
+    pub message: String,
+}
+
+impl GitStorage {
+    pub fn new(dir: PathBuf, remote: Option<String>) -> Self {
+        GitStorage { dir, remote }
+    }
+
+    /// 設定ディレクトリの vault リポジトリ
+    pub fn default_dir() -> Result<PathBuf, AppError> {
+        Ok(config::get_config_dir()?.join("vault"))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn vault_path(&self) -> PathBuf {
+        self.dir.join(VAULT_FILE)
+    }
+
+    /// git コマンドを実行して標準出力を返す
+    fn git(&self, args: &[&str]) -> Result<String, AppError> {
+        let output = self.output(args)?;
+        if !output.status.success() {
+            return Err(failed(args, &output));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .trim_end()
+            .to_string())
+    }
+
+    /// 失敗しても構わない git コマンドを実行し、成功したかを返す
+    fn git_succeeds(&self, args: &[&str]) -> Result<bool, AppError> {
+        Ok(self.output(args)?.status.success())
+    }
+
+    /// 終了ステータスを呼び出し側で判断する git コマンドを実行する
+    fn output(&self, args: &[&str]) -> Result<Output, AppError> {
+        self.command(args)
+            .output()
+            .map_err(|e| AppError::GitError(format!("failed to run git (is it installed?): {}", e)))
+    }
+
+    fn command(&self, args: &[&str]) -> Command {
+        let mut command = Command::new("git");
+        command.arg("-C").arg(&self.dir).args(args);
+        command
+    }
+
+    /// リポジトリがなければ作成し、リモートを設定する
+    fn ensure_repo(&self) -> Result<(), AppError> {
+        if !self.dir.join(".git").exists() {
+            fs::create_dir_all(&self.dir)?;
+            self.git(&["init", "--quiet", "--initial-branch", "main"])?;
+        }
+        // コミットに必要な名前とメールアドレスが未設定なら、このリポジトリだけに設定する
+        if !self.git_succeeds(&["config", "user.email"])? {
+            self.git(&["config", "user.email", "gauth@localhost"])?;
+        }
+        if !self.git_succeeds(&["config", "user.name"])? {
+            self.git(&["config", "user.name", "gauth"])?;
+        }
+        if let Some(remote) = &self.remote {
+            let current = self.git(&["remote", "get-url", REMOTE_NAME]).ok();
+            match current {
+                Some(current) if current == *remote => {}
+                Some(_) => {
+                    self.git(&["remote", "set-url", REMOTE_NAME, remote])?;
+                }
+                None => {
+                    self.git(&["remote", "add", REMOTE_NAME, remote])?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 保管庫ファイルを書き込んでコミットする (変更がなければコミットしない)
+    fn commit_file(&self, data: &[u8], message: &str) -> Result<(), AppError> {
+        let temporary = self.dir.join(format!("{}.tmp", VAULT_FILE));
+        fs::write(&temporary, data)?;
+        fs::rename(&temporary, self.vault_path())?;
+        self.git(&["add", VAULT_FILE])?;
+        if !self.git_succeeds(&["diff", "--cached", "--quiet"])? {
+            self.git(&["commit", "--quiet", "-m", message])?;
+        }
+        Ok(())
+    }
+
+    /// 指定したリビジョンの保管庫 (存在しなければ None)
+    pub fn show(&self, revision: &str) -> Result<Option<Vec<u8>>, AppError> {
+        let spec = format!("{}:{}", revision, VAULT_FILE);
+        if !self.git_succeeds(&["cat-file", "-e", &spec])? {
+            return Ok(None);
+        }
+        let output = self
+            .command(&["show", &spec])
+            .output()
+            .map_err(|e| AppError::GitError(e.to_string()))?;
+        Ok(Some(output.stdout))
+    }
+
+    /// 保管庫を変更したコミットを新しい順に返す
+    pub fn log(&self) -> Result<Vec<Revision>, AppError> {
+        self.ensure_repo()?;
+        if !self.git_succeeds(&["rev-parse", "--verify", "--quiet", "HEAD"])? {
+            return Ok(Vec::new());
+        }
+        let output = self.git(&[
+            "log",
+            "--format=%h%x09%ad%x09%s",
+            "--date=format:%Y-%m-%d %H:%M",
+            "--",
+            VAULT_FILE,
+        ])?;
+        Ok(output
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.splitn(3, '\t');
+                Some(Revision {
+                    id: fields.next()?.to_string(),
+                    date: fields.next()?.to_string(),
+                    message: fields.next()?.to_string(),
+                })
+            })
+            .collect())
+    }
+
+    fn branch(&self) -> Result<String, AppError> {
+        self.git(&["symbolic-ref", "--short", "HEAD"])
+    }
+
+    fn require_remote(&self) -> Result<(), AppError> {
+        if self.remote.is_none() {
+            return Err(AppError::GitError(
+                "No remote configured; set `remote` in the [storage] section of config.toml".into(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// リモートの同じブランチを取得する (リモートにまだブランチがなければ false)
+    pub fn fetch(&self) -> Result<bool, AppError> {
+        self.require_remote()?;
+        self.ensure_repo()?;
+        let branch = self.branch()?;
+        let refspec = format!("refs/heads/{}", branch);
+        let args = ["ls-remote", "--exit-code", "--heads", REMOTE_NAME, &refspec];
+        let output = self.output(&args)?;
+        match output.status.code() {
+            Some(0) => {}
+            // --exit-code はリモートに一致する参照がない場合だけ 2 で終了する
+            Some(2) => return Ok(false),
+            // 接続や認証の失敗をリモートが空だとみなすと、誤った前提で同期してしまう
+            _ => return Err(failed(&args, &output)),
+        }
+        self.git(&["fetch", "--quiet", REMOTE_NAME, &branch])?;
+        Ok(true)
+    }
+
+    /// 手元だけにあるコミット数とリモートだけにあるコミット数 (fetch の後に呼ぶ)
+    pub fn ahead_behind(&self) -> Result<(usize, usize), AppError> {
+        if !self.git_succeeds(&["rev-parse", "--verify", "--quiet", "HEAD"])? {
+            let behind = self.git(&["rev-list", "--count", "FETCH_HEAD"])?;
+            return Ok((0, behind.parse().unwrap_or(0)));
+        }
+        let counts = self.git(&["rev-list", "--left-right", "--count", "HEAD...FETCH_HEAD"])?;
+        let mut counts = counts
+            .split_whitespace()
+            .map(|count| count.parse().unwrap_or(0));
+        Ok((counts.next().unwrap_or(0), counts.next().unwrap_or(0)))
+    }
+
+    /// 手元と取得したリモートの共通の祖先
+    pub fn merge_base(&self) -> Result<Option<String>, AppError> {
+        Ok(self.git(&["merge-base", "HEAD", "FETCH_HEAD"]).ok())
+    }
+
+    /// 取得したリモートまで早送りする
+    pub fn fast_forward(&self) -> Result<(), AppError> {
+        if self.git_succeeds(&["rev-parse", "--verify", "--quiet", "HEAD"])? {
+            self.git(&["merge", "--quiet", "--ff-only", "FETCH_HEAD"])?;
+        } else {
+            self.git(&["reset", "--quiet", "--hard", "FETCH_HEAD"])?;
+        }
+        Ok(())
+    }
+
+    /// 取得したリモートとのマージコミットを、マージ済みの保管庫の内容で作成する
+    pub fn merge_commit(&self, data: &[u8], message: &str) -> Result<(), AppError> {
+        self.git(&[
+            "merge",
+            "--quiet",
+            "--no-commit",
+            "--allow-unrelated-histories",
+            "--strategy",
+            "ours",
+            "FETCH_HEAD",
+        ])?;
+        let temporary = self.dir.join(format!("{}.tmp", VAULT_FILE));
+        fs::write(&temporary, data)?;
+        fs::rename(&temporary, self.vault_path())?;
+        self.git(&["add", VAULT_FILE])?;
+        self.git(&["commit", "--quiet", "-m", message])?;
+        Ok(())
+    }
+
+    /// 現在のブランチをリモートへ送る
+    /// リモートに送信する
+    ///
+    /// 取得後にリモートが更新されていて早送りできない場合は [`AppError::StorageConflict`]、
+    /// 接続・認証の失敗やフックによる拒否などはそのまま [`AppError::GitError`] を返す。
+    pub fn push(&self) -> Result<(), AppError> {
+        self.require_remote()?;
+        let refspec = format!("HEAD:refs/heads/{}", self.branch()?);
+        let args = ["push", "--porcelain", REMOTE_NAME, &refspec];
+        let output = self.output(&args)?;
+        if output.status.success() {
+            return Ok(());
+        }
+        // --porcelain では拒否された参照が "!<TAB>参照<TAB>[rejected] (理由)" の行で出力される
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let outdated = stdout.lines().any(|line| {
+            line.starts_with('!')
+                && line.contains("[rejected]")
+                && (line.contains("non-fast-forward") || line.contains("fetch first"))
+        });
+        if outdated {
+            return Err(AppError::StorageConflict);
+        }
+        Err(failed(&args, &output))
+    }
+}
+
+/// 失敗した git コマンドのエラー
+fn failed(args: &[&str], output: &Output) -> AppError {
+    AppError::GitError(format!(
+        "git {} failed: {}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr).trim()
+    ))
+}
+
+impl Storage for GitStorage {
+    fn read(&self) -> Result<Option<(Vec<u8>, Version)>, AppError> {
+        read_file(&self.vault_path())
+    }
+
+    fn write(&self, data: &[u8], expected: Option<&Version>) -> Result<Version, AppError> {
+        self.write_change(data, expected, "Update vault")
+    }
+
+    fn write_change(
+        &self,
+        data: &[u8],
+        expected: Option<&Version>,
+        message: &str,
+    ) -> Result<Version, AppError> {
+        self.ensure_repo()?;
+        let current = read_file(&self.vault_path())?.map(|(_, version)| version);
+        check_version(current.as_ref(), expected)?;
+        self.commit_file(data, message)?;
+        Ok(Version::of(data))
+    }
+
+    fn lock(&self) -> Result<LockGuard, AppError> {
+        self.ensure_repo()?;
+        lock_file(self.dir.join(".git").join("gauth.lock"))
+    }
+
+    fn describe(&self) -> String {
+        format!("git repository {}", self.dir.display())
+    }
+
+    fn as_git(&self) -> Option<&GitStorage> {
+        Some(self)
+    }
+}
@@ -0,0 +1,50 @@
+use super::{LockGuard, Storage, Version, check_version, lock_file};
+use crate::config;
+use crate::error::AppError;
+use keyring::Entry;
+
+const SERVICE_NAME: &str = "gauth_vault";
+
+/// 暗号化した保管庫ごと OS のキーリングに保存する
+///
+/// キーリングにはロックの仕組みがないため、設定ディレクトリのロックファイルで排他制御する。
+#[derive(Debug)]
+pub struct KeyringStorage {
+    entry: Entry,
+}
+
+impl KeyringStorage {
+    pub fn new() -> Result<Self, AppError> {
+        Ok(KeyringStorage {
+            entry: Entry::new(SERVICE_NAME, "gauth_user")?,
+        })
+    }
+}
+
+impl Storage for KeyringStorage {
+    fn read(&self) -> Result<Option<(Vec<u8>, Version)>, AppError> {
+        match self.entry.get_secret() {
+            Ok(data) => {
+                let version = Version::of(&data);
+                Ok(Some((data, version)))
+            }
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn write(&self, data: &[u8], expected: Option<&Version>) -> Result<Version, AppError> {
+        let current = self.read()?.map(|(_, version)| version);
+        check_version(current.as_ref(), expected)?;
+        self.entry.set_secret(data)?;
+        Ok(Version::of(data))
+    }
+
+    fn lock(&self) -> Result<LockGuard, AppError> {
+        lock_file(config::get_config_dir()?.join("keyring-vault.lock"))
+    }
+
+    fn describe(&self) -> String {
+        format!("keyring service {}", SERVICE_NAME)
+    }
+}
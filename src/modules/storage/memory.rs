@@ -0,0 +1,63 @@
+use super::{LockGuard, Storage, Version, check_version};
+use crate::error::AppError;
+use std::sync::{Arc, Mutex};
+
+/// メモリ上に保存する (テスト用)
+///
+/// 複製したものは同じ内容を共有するため、複数のプロセスからの同時編集を再現できる。
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStorage {
+    state: Arc<Mutex<MemoryState>>,
+}
+
+#[derive(Debug, Default)]
+struct MemoryState {
+    data: Option<Vec<u8>>,
+    revision: u64,
+    locked: bool,
+}
+
+impl MemoryState {
+    fn version(&self) -> Option<Version> {
+        self.data
+            .as_ref()
+            .map(|_| Version(self.revision.to_string()))
+    }
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        MemoryStorage::default()
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn read(&self) -> Result<Option<(Vec<u8>, Version)>, AppError> {
+        let state = self.state.lock().unwrap();
+        Ok(state.data.clone().zip(state.version()))
+    }
+
+    fn write(&self, data: &[u8], expected: Option<&Version>) -> Result<Version, AppError> {
+        let mut state = self.state.lock().unwrap();
+        check_version(state.version().as_ref(), expected)?;
+        state.data = Some(data.to_vec());
+        state.revision += 1;
+        Ok(Version(state.revision.to_string()))
+    }
+
+    fn lock(&self) -> Result<LockGuard, AppError> {
+        let mut state = self.state.lock().unwrap();
+        if state.locked {
+            return Err(AppError::StorageLocked("memory".into()));
+        }
+        state.locked = true;
+        let shared = Arc::clone(&self.state);
+        Ok(LockGuard::new(move || {
+            shared.lock().unwrap().locked = false;
+        }))
+    }
+
+    fn describe(&self) -> String {
+        "memory".into()
+    }
+}
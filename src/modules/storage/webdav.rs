@@ -0,0 +1,124 @@
+use super::{LockGuard, Storage, Version};
+use crate::error::AppError;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use std::fmt;
+use std::time::Duration;
+
+/// WebDAV サーバー上の1ファイルに暗号化した保管庫を保存する
+///
+/// 版には ETag を使い、`If-Match` / `If-None-Match` 付きの PUT で同時編集を検出する。
+pub struct WebDavStorage {
+    url: String,
+    authorization: Option<String>,
+    agent: ureq::Agent,
+}
+
+impl fmt::Debug for WebDavStorage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebDavStorage")
+            .field("url", &self.url)
+            .finish_non_exhaustive()
+    }
+}
+
+fn remote_error(error: impl fmt::Display) -> AppError {
+    AppError::SyncError(format!("WebDAV request failed: {}", error))
+}
+
+fn etag(response: &ureq::http::Response<ureq::Body>) -> Option<Version> {
+    response
+        .headers()
+        .get("etag")
+        .and_then(|value| value.to_str().ok())
+        .map(|value| Version(value.to_string()))
+}
+
+impl WebDavStorage {
+    pub fn new(url: String, username: Option<String>, password: Option<String>) -> Self {
+        let authorization = username.map(|username| {
+            let credentials = format!("{}:{}", username, password.unwrap_or_default());
+            format!("Basic {}", STANDARD.encode(credentials))
+        });
+        let agent = ureq::Agent::config_builder()
+            .http_status_as_error(false)
+            .timeout_global(Some(Duration::from_secs(30)))
+            .build()
+            .into();
+        WebDavStorage {
+            url,
+            authorization,
+            agent,
+        }
+    }
+
+    fn unexpected(status: ureq::http::StatusCode) -> AppError {
+        AppError::SyncError(format!("WebDAV server responded with {}", status))
+    }
+
+    /// PUT の応答に ETag がなければ HEAD で取得する
+    fn head_etag(&self) -> Result<Version, AppError> {
+        let mut request = self.agent.head(&self.url);
+        if let Some(authorization) = &self.authorization {
+            request = request.header("Authorization", authorization);
+        }
+        let response = request.call().map_err(remote_error)?;
+        if !response.status().is_success() {
+            return Err(Self::unexpected(response.status()));
+        }
+        etag(&response)
+            .ok_or_else(|| AppError::SyncError("The WebDAV server did not return an ETag".into()))
+    }
+}
+
+impl Storage for WebDavStorage {
+    fn read(&self) -> Result<Option<(Vec<u8>, Version)>, AppError> {
+        let mut request = self.agent.get(&self.url);
+        if let Some(authorization) = &self.authorization {
+            request = request.header("Authorization", authorization);
+        }
+        let mut response = request.call().map_err(remote_error)?;
+        match response.status().as_u16() {
+            404 => Ok(None),
+            200 => {
+                let version = etag(&response).ok_or_else(|| {
+                    AppError::SyncError("The WebDAV server did not return an ETag".into())
+                })?;
+                let data = response.body_mut().read_to_vec().map_err(remote_error)?;
+                Ok(Some((data, version)))
+            }
+            _ => Err(Self::unexpected(response.status())),
+        }
+    }
+
+    fn write(&self, data: &[u8], expected: Option<&Version>) -> Result<Version, AppError> {
+        let mut request = self
+            .agent
+            .put(&self.url)
+            .header("Content-Type", "application/toml");
+        if let Some(authorization) = &self.authorization {
+            request = request.header("Authorization", authorization);
+        }
+        request = match expected {
+            Some(version) => request.header("If-Match", &version.0),
+            None => request.header("If-None-Match", "*"),
+        };
+        let response = request.send(data).map_err(remote_error)?;
+        match response.status().as_u16() {
+            412 => Err(AppError::StorageConflict),
+            200..=299 => match etag(&response) {
+                Some(version) => Ok(version),
+                None => self.head_etag(),
+            },
+            _ => Err(Self::unexpected(response.status())),
+        }
+    }
+
+    /// WebDAV の LOCK は使わず、ETag による条件付き PUT で同時編集を検出する
+    fn lock(&self) -> Result<LockGuard, AppError> {
+        Ok(LockGuard::new(|| {}))
+    }
+
+    fn describe(&self) -> String {
+        format!("webdav {}", self.url)
+    }
+}
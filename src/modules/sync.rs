@@ -0,0 +1,416 @@
+//! 同期先との同期 (gauth sync)
+//!
+//! 最後に同期した時点の保管庫を基準として手元に保存しておき、
+//! 次回の同期で手元と同期先の内容を認証情報ごとに三方向マージする。
+//! git の保存先ではコミットの共通の祖先を基準にする。
+//!
+//! 時計のずれ (`gauth time`) は端末ごとの設定のため同期しない。ただし git の保存先では
+//! 保管庫のファイル自体を共有するため、早送りで取り込んだ場合はリモートの値になる。
+
+use crate::config::{self, Config};
+use crate::error::AppError;
+use crate::index;
+use crate::merge::{self, MergeConflict};
+use crate::secrets::{self, SecretManager, Snapshot};
+use crate::storage::{self, FileStorage, GitStorage, Storage, Version};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde::{Deserialize, Serialize};
+
+/// 同期先の版が変わり続けた場合に諦めるまでの試行回数
+const SYNC_ATTEMPTS: usize = 3;
+
+/// 前回の同期の記録
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SyncState {
+    /// 同期先の版 (ETag など)
+    #[serde(default)]
+    pub remote_version: Option<String>,
+    /// 同期した時点の暗号化済み保管庫 (Base64)
+    #[serde(default)]
+    pub base: Option<String>,
+}
+
+impl SyncState {
+    fn load(store: &dyn Storage) -> Result<(Self, Option<Version>), AppError> {
+        match store.read()? {
+            Some((data, version)) => {
+                let text = String::from_utf8(data)
+                    .map_err(|_| AppError::SyncError("Sync state is not valid UTF-8".into()))?;
+                Ok((toml::from_str(&text)?, Some(version)))
+            }
+            None => Ok((SyncState::default(), None)),
+        }
+    }
+
+    fn base(&self) -> Result<Option<Vec<u8>>, AppError> {
+        self.base
+            .as_deref()
+            .map(|base| {
+                STANDARD
+                    .decode(base)
+                    .map_err(|_| AppError::SyncError("Sync state is corrupted".into()))
+            })
+            .transpose()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncStatus {
+    /// まだ一度も同期していない
+    NeverSynced,
+    UpToDate,
+    /// 手元に未送信の変更がある
+    LocalChanges,
+    /// 同期先に未取得の変更がある
+    RemoteChanges,
+    /// 両方に変更がある (次回の同期でマージされる)
+    Diverged,
+    /// 同期先の保管庫が見つからない
+    RemoteMissing,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SyncReport {
+    /// 同期先から取り込んだ認証情報の数 (追加・変更・削除)
+    pub pulled: usize,
+    /// 同期先へ書き込んだか
+    pub pushed: bool,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+fn changed_credentials(before: &Snapshot, after: &Snapshot) -> usize {
+    let mut changed = after
+        .credentials
+        .iter()
+        .filter(|(name, credential)| before.credentials.get(*name) != Some(credential))
+        .count();
+    changed += before
+        .credentials
+        .keys()
+        .filter(|name| !after.credentials.contains_key(*name))
+        .count();
+    changed
+}
+
+/// 手元の保管庫と同期先を同期する
+pub fn sync(
+    local: &dyn Storage,
+    remote: &dyn Storage,
+    state_store: &dyn Storage,
+    master_password: &str,
+) -> Result<SyncReport, AppError> {
+    let _lock = local.lock()?;
+    let (state, state_version) = SyncState::load(state_store)?;
+    // 基準が読めない場合に基準なしでマージすると、削除した認証情報が復活してしまう
+    let base = state
+        .base()?
+        .map(|base| {
+            SecretManager::decode(&base, master_password)
+                .map_err(|e| unreadable_base(e, &state_store.describe()))
+        })
+        .transpose()?;
+    let base_snapshot = base
+        .as_ref()
+        .map(SecretManager::snapshot)
+        .unwrap_or_default();
+
+    for _ in 0..SYNC_ATTEMPTS {
+        let local_blob = local.read()?;
+        let mut manager = match &local_blob {
+            Some((data, _)) => SecretManager::decode(data, master_password)?,
+            None => SecretManager::new(),
+        };
+        let local_snapshot = manager.snapshot();
+
+        let remote_blob = remote.read()?;
+        let mut report = SyncReport::default();
+        // 同期先がすでにマージ結果と同じなら、同期先には書き込まずにその版を基準にする
+        let (merged, remote_current) = match &remote_blob {
+            Some((data, version)) => {
+                let remote = SecretManager::decode(data, master_password)?;
+                let local_members = manager.same_members(&remote);
+                // 同期先でのメンバーの変更を手元の鍵で上書きしない
+                manager.merge_key(base.as_ref(), &remote)?;
+                let remote_snapshot = remote.snapshot();
+                let result = merge::merge(&base_snapshot, &local_snapshot, &remote_snapshot);
+                report.conflicts = result.conflicts;
+                // メンバーの変更は内容に含まれないため、鍵とスロットも同じ場合に限る
+                let current = remote_snapshot == result.merged && manager.same_members(&remote);
+                let current = current.then(|| {
+                    // 時計のずれは端末ごとの設定のため、同期先の値は取り込まない
+                    let data = if remote.time_offset() == manager.time_offset() {
+                        Some(data.clone())
+                    } else if local_members && local_snapshot == result.merged {
+                        local_blob.as_ref().map(|(data, _)| data.clone())
+                    } else {
+                        None
+                    };
+                    (data, version.clone())
+                });
+                (result.merged, current)
+            }
+            None => (local_snapshot.clone(), None),
+        };
+        report.pulled = changed_credentials(&local_snapshot, &merged);
+
+        let (data, remote_version) = match remote_current {
+            Some((Some(data), version)) => (data, version),
+            Some((None, version)) => {
+                manager.apply(merged);
+                (manager.encode(master_password)?, version)
+            }
+            None => {
+                manager.apply(merged);
+                let data = manager.encode(master_password)?;
+                let expected = remote_blob.as_ref().map(|(_, version)| version);
+                match remote.write(&data, expected) {
+                    Err(AppError::StorageConflict) => continue,
+                    result => {
+                        report.pushed = true;
+                        (data, result?)
+                    }
+                }
+            }
+        };
+
+        if local_blob.as_ref().map(|(data, _)| data) != Some(&data) {
+            local.write(&data, local_blob.as_ref().map(|(_, version)| version))?;
+        }
+        let state = SyncState {
+            remote_version: Some(remote_version.0),
+            base: Some(STANDARD.encode(&data)),
+        };
+        state_store.write(toml::to_string(&state)?.as_bytes(), state_version.as_ref())?;
+        return Ok(report);
+    }
+    Err(AppError::SyncError(
+        "The remote vault kept changing during sync; try again later".into(),
+    ))
+}
+
+/// 前回の同期からの変更の有無を調べる (復号は行わない)
+pub fn status(
+    local: &dyn Storage,
+    remote: &dyn Storage,
+    state_store: &dyn Storage,
+) -> Result<SyncStatus, AppError> {
+    let (state, _) = SyncState::load(state_store)?;
+    let Some(base) = state.base()? else {
+        return Ok(SyncStatus::NeverSynced);
+    };
+    let local_changed =
+        local.read()?.map(|(data, _)| Version::of(&data)) != Some(Version::of(&base));
+
+    let Some((_, remote_version)) = remote.read()? else {
+        return Ok(SyncStatus::RemoteMissing);
+    };
+    let remote_changed = state.remote_version.as_ref() != Some(&remote_version.0);
+
+    Ok(match (local_changed, remote_changed) {
+        (false, false) => SyncStatus::UpToDate,
+        (true, false) => SyncStatus::LocalChanges,
+        (false, true) => SyncStatus::RemoteChanges,
+        (true, true) => SyncStatus::Diverged,
+    })
+}
+
+/// 前回の同期時点の保管庫を復号できなかったときのエラー
+fn unreadable_base(error: AppError, location: &str) -> AppError {
+    AppError::SyncError(format!(
+        "The vault from the last sync cannot be decrypted ({}); remove {} to sync without it (credentials deleted since then may come back)",
+        error, location
+    ))
+}
+
+fn decode_snapshot(data: Option<Vec<u8>>, master_password: &str) -> Result<Snapshot, AppError> {
+    match data {
+        Some(data) => Ok(SecretManager::decode(&data, master_password)?.snapshot()),
+        None => Ok(Snapshot::default()),
+    }
+}
+
+/// git リポジトリの保管庫をリモートと同期する
+///
+/// 両方にコミットがある場合は共通の祖先を基準に三方向マージしたマージコミットを作成する。
+pub fn sync_git(repo: &GitStorage, master_password: &str) -> Result<SyncReport, AppError> {
+    let _lock = repo.lock()?;
+    let mut report = SyncReport::default();
+
+    for _ in 0..SYNC_ATTEMPTS {
+        let local_data = repo.read()?.map(|(data, _)| data);
+        if !repo.fetch()? {
+            if local_data.is_some() {
+                // 同時に別の手元からブランチが作られていれば取得からやり直す
+                match repo.push() {
+                    Err(AppError::StorageConflict) => continue,
+                    result => result?,
+                }
+                report.pushed = true;
+            }
+            return Ok(report);
+        }
+
+        let before = decode_snapshot(local_data.clone(), master_password)?;
+        match repo.ahead_behind()? {
+            (0, 0) => return Ok(report),
+            (_, 0) => {}
+            (0, _) => {
+                repo.fast_forward()?;
+                let after = decode_snapshot(repo.read()?.map(|(data, _)| data), master_password)?;
+                report.pulled = changed_credentials(&before, &after);
+                return Ok(report);
+            }
+            _ => {
+                let base = match repo.merge_base()? {
+                    Some(revision) => repo
+                        .show(&revision)?
+                        .map(|data| {
+                            SecretManager::decode(&data, master_password).map_err(|e| {
+                                AppError::SyncError(format!(
+                                    "The common ancestor {} of the local and remote vaults cannot be decrypted ({}); merge the repositories by hand",
+                                    revision, e
+                                ))
+                            })
+                        })
+                        .transpose()?,
+                    None => None,
+                };
+                let remote = match repo.show("FETCH_HEAD")? {
+                    Some(data) => SecretManager::decode(&data, master_password)?,
+                    None => SecretManager::new(),
+                };
+                let base_snapshot = base
+                    .as_ref()
+                    .map(SecretManager::snapshot)
+                    .unwrap_or_default();
+                let result = merge::merge(&base_snapshot, &before, &remote.snapshot());
+                report.pulled = changed_credentials(&before, &result.merged);
+                report.conflicts = result.conflicts;
+
+                let mut manager = match &local_data {
+                    Some(data) => SecretManager::decode(data, master_password)?,
+                    None => SecretManager::new(),
+                };
+                manager.merge_key(base.as_ref(), &remote)?;
+                let message = format!(
+                    "Merge remote changes: {}",
+                    before.describe_changes(&result.merged)
+                );
+                manager.apply(result.merged);
+                repo.merge_commit(&manager.encode(master_password)?, &message)?;
+            }
+        }
+        // 送信中にリモートが更新された場合だけ取得からやり直す
+        match repo.push() {
+            Err(AppError::StorageConflict) => continue,
+            result => {
+                result?;
+                report.pushed = true;
+                return Ok(report);
+            }
+        }
+    }
+    Err(AppError::SyncError(
+        "The remote repository kept changing during sync; try again later".into(),
+    ))
+}
+
+/// git リポジトリとリモートの差分を調べる (復号は行わない)
+pub fn status_git(repo: &GitStorage) -> Result<SyncStatus, AppError> {
+    if !repo.fetch()? {
+        return Ok(SyncStatus::RemoteMissing);
+    }
+    Ok(match repo.ahead_behind()? {
+        (0, 0) => SyncStatus::UpToDate,
+        (_, 0) => SyncStatus::LocalChanges,
+        (0, _) => SyncStatus::RemoteChanges,
+        _ => SyncStatus::Diverged,
+    })
+}
+
+fn state_store() -> Result<FileStorage, AppError> {
+    Ok(FileStorage::new(
+        config::get_config_dir()?.join("sync-state.toml"),
+    ))
+}
+
+fn remote_from_config(config: &Config) -> Result<std::sync::Arc<dyn Storage>, AppError> {
+    let remote = config.sync.as_ref().ok_or_else(|| {
+        AppError::SyncError("No sync target configured; add a [sync] section to config.toml".into())
+    })?;
+    storage::open(remote)
+}
+
+fn print_report(report: &SyncReport, remote: &str) {
+    for conflict in &report.conflicts {
+        println!(
+            "Conflict: '{}' was changed on both sides; kept the local version as '{}'",
+            conflict.name, conflict.kept_as
+        );
+    }
+    println!(
+        "Synced with {}: {} change(s) pulled, {}",
+        remote,
+        report.pulled,
+        if report.pushed {
+            "changes pushed"
+        } else {
+            "nothing to push"
+        }
+    );
+}
+
+pub fn run_sync() -> Result<(), AppError> {
+    let config = Config::load()?;
+    let local = storage::open(&config.storage)?;
+    // git の保存先は [sync] がなければリポジトリのリモートと同期する
+    if let (Some(repo), None) = (local.as_git(), &config.sync) {
+        let master_password = secrets::get_master_password()?;
+        let report = sync_git(repo, &master_password)?;
+        print_report(&report, "the git remote");
+        return refresh_index(&master_password);
+    }
+
+    let remote = remote_from_config(&config)?;
+    let master_password = secrets::get_master_password()?;
+    let report = sync(
+        local.as_ref(),
+        remote.as_ref(),
+        &state_store()?,
+        &master_password,
+    )?;
+    print_report(&report, &remote.describe());
+    refresh_index(&master_password)
+}
+
+/// 同期で取り込んだ変更を名前の索引に反映する
+fn refresh_index(master_password: &str) -> Result<(), AppError> {
+    if index::configured_path()?.is_some() {
+        SecretManager::load_secrets(master_password)?;
+    }
+    Ok(())
+}
+
+pub fn run_status() -> Result<(), AppError> {
+    let config = Config::load()?;
+    let local = storage::open(&config.storage)?;
+    let (status, remote) = match (local.as_git(), &config.sync) {
+        (Some(repo), None) => (status_git(repo)?, "the git remote".to_string()),
+        _ => {
+            let remote = remote_from_config(&config)?;
+            let status = status(local.as_ref(), remote.as_ref(), &state_store()?)?;
+            (status, remote.describe())
+        }
+    };
+
+    let message = match status {
+        SyncStatus::NeverSynced => "Never synced; run `gauth sync`",
+        SyncStatus::UpToDate => "Up to date",
+        SyncStatus::LocalChanges => "Local changes not yet pushed",
+        SyncStatus::RemoteChanges => "Remote has changes not yet pulled",
+        SyncStatus::Diverged => "Both local and remote changed; `gauth sync` will merge them",
+        SyncStatus::RemoteMissing => "Remote vault not found; `gauth sync` will upload it",
+    };
+    println!("{} ({})", message, remote);
+    Ok(())
+}
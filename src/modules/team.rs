@@ -0,0 +1,90 @@
+//! 共有保管庫のメンバー管理 (gauth vault)
+//!
+//! 保管庫のデータ鍵をメンバーごとのスロットでラップし、メンバーはそれぞれ自分の
+//! パスワードか X25519 の識別子で同じ保管庫を開く。
+
+use crate::error::AppError;
+use crate::import;
+use crate::random::OsRandom;
+use crate::secrets::{
+    self, SecretManager,
+    vault::{Identity, MemberKind},
+};
+use dialoguer::{Password, theme::ColorfulTheme};
+
+pub fn list_members() -> Result<(), AppError> {
+    let master_password = secrets::get_master_password()?;
+    let manager = SecretManager::load_secrets(&master_password)?;
+    let members = manager.members();
+    if members.is_empty() {
+        println!("The vault has not been saved yet.");
+        return Ok(());
+    }
+    let rows: Vec<Vec<String>> = members
+        .into_iter()
+        .map(|member| match member.kind {
+            MemberKind::Password => vec![member.name, "password".into(), "-".into()],
+            MemberKind::PublicKey(key) => vec![member.name, "public key".into(), key],
+        })
+        .collect();
+    import::print_table(&["Name", "Type", "Public key"], &rows);
+    Ok(())
+}
+
+/// メンバーを追加 (公開鍵を指定しない場合はメンバーのパスワードを尋ねる)
+pub fn add_member(name: String, public_key: Option<String>) -> Result<(), AppError> {
+    let master_password = secrets::get_master_password()?;
+    let mut manager = SecretManager::load_secrets(&master_password)?;
+    match public_key {
+        Some(public_key) => manager.add_member_public_key(&name, &public_key, &master_password)?,
+        None => {
+            let password = Password::with_theme(&ColorfulTheme::default())
+                .with_prompt(format!("Password for {}", name))
+                .with_confirmation("Confirm password", "Passwords do not match")
+                .interact()?;
+            manager.add_member_password(&name, &password, &master_password)?;
+        }
+    }
+    manager.save_with_message(&master_password, &format!("Add member {}", name))?;
+    println!("Added member '{}'", name);
+    Ok(())
+}
+
+/// メンバーを削除し、データ鍵を入れ替えて保存する
+pub fn remove_member(name: String) -> Result<(), AppError> {
+    let master_password = secrets::get_master_password()?;
+    let mut manager = SecretManager::load_secrets(&master_password)?;
+    manager.remove_member(&name, &master_password)?;
+    manager.save_with_message(&master_password, &format!("Remove member {}", name))?;
+    println!("Removed member '{}' and rotated the vault key", name);
+    Ok(())
+}
+
+/// この端末用の識別子を作成し、公開鍵を表示する
+///
+/// 識別子はマスターパスワードの代わりにキーリングへ保存する。
+pub fn identity() -> Result<(), AppError> {
+    let entry = secrets::master_password_entry()?;
+    let identity = match entry.get_secret() {
+        Ok(secret) => {
+            let secret = String::from_utf8(secret).map_err(|_| AppError::DecryptionError)?;
+            Identity::parse(&secret).ok_or_else(|| {
+                AppError::SecretManagerError(
+                    "A master password is already stored in the keyring; ask to be added with a password instead".into(),
+                )
+            })?
+        }
+        Err(keyring::Error::NoEntry) => {
+            let identity = Identity::generate(&OsRandom);
+            entry.set_secret(identity.encode().as_bytes())?;
+            println!("Created a new identity and stored it in the keyring.");
+            identity
+        }
+        Err(e) => return Err(e.into()),
+    };
+    println!("Public key: {}", identity.public_key());
+    println!(
+        "Share it with a vault member, who can run: gauth vault member add <name> --public-key <key>"
+    );
+    Ok(())
+}
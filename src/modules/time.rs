@@ -0,0 +1,175 @@
+use crate::error::AppError;
+use crate::otp;
+use crate::secrets::{self, SecretManager};
+use crate::select;
+use dialoguer::{Input, theme::ColorfulTheme};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 補正時に探索する最大の時刻ずれ (前後それぞれ 24 時間)
+const MAX_CALIBRATION_SECONDS: u64 = 24 * 60 * 60;
+
+/// コード生成に使う時計
+pub trait Clock: fmt::Debug + Send + Sync {
+    /// 現在の UNIX 時刻 (秒、時刻オフセットは含まない)
+    fn now(&self) -> Result<u64, AppError>;
+
+    /// 現在の UNIX 時刻 (ミリ秒)。秒の境界に合わせて表示を更新するために使う
+    fn now_millis(&self) -> Result<u64, AppError> {
+        Ok(self.now()?.saturating_mul(1000))
+    }
+}
+
+/// システム時刻
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl SystemClock {
+    fn elapsed() -> Result<std::time::Duration, AppError> {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| AppError::GeneralError(format!("SystemTime error: {}", e)))
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Result<u64, AppError> {
+        Ok(SystemClock::elapsed()?.as_secs())
+    }
+
+    fn now_millis(&self) -> Result<u64, AppError> {
+        Ok(SystemClock::elapsed()?.as_millis() as u64)
+    }
+}
+
+/// 指定した時刻で止まっている時計 (テスト用。`set` / `advance` で進める)
+#[derive(Debug, Default)]
+pub struct FixedClock(AtomicU64);
+
+impl FixedClock {
+    pub fn new(timestamp: u64) -> Self {
+        FixedClock(AtomicU64::new(timestamp))
+    }
+
+    pub fn set(&self, timestamp: u64) {
+        self.0.store(timestamp, Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, seconds: u64) {
+        self.0.fetch_add(seconds, Ordering::SeqCst);
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> Result<u64, AppError> {
+        Ok(self.0.load(Ordering::SeqCst))
+    }
+}
+
+/// 時刻オフセット (秒) を適用した現在の UNIX 時刻
+pub fn now(offset: i64) -> Result<u64, AppError> {
+    now_with(&SystemClock, offset)
+}
+
+/// 指定した時計で、時刻オフセット (秒) を適用した現在の UNIX 時刻
+pub fn now_with(clock: &dyn Clock, offset: i64) -> Result<u64, AppError> {
+    Ok(clock.now()?.saturating_add_signed(offset))
+}
+
+/// 指定した時計で、時刻オフセット (秒) を適用した現在の UNIX 時刻 (ミリ秒)
+pub fn now_millis_with(clock: &dyn Clock, offset: i64) -> Result<u64, AppError> {
+    Ok(clock
+        .now_millis()?
+        .saturating_add_signed(offset.saturating_mul(1000)))
+}
+
+pub fn calibrate(name: String, code: Option<String>) -> Result<(), AppError> {
+    let master_password = secrets::get_master_password()?;
+    let mut secret_manager = SecretManager::load_secrets(&master_password)?;
+    let name = select::one(&secret_manager.credentials(), &name)?;
+
+    let code = match code {
+        Some(code) => code,
+        None => Input::with_theme(&ColorfulTheme::default())
+            .with_prompt(format!("Current code for {} (from a trusted device)", name))
+            .interact_text()?,
+    };
+    let offset = calibrated_offset(&secret_manager, &name, &code)?;
+    secret_manager.set_time_offset(offset);
+    secret_manager.save_secrets(&master_password)?;
+
+    if offset == 0 {
+        println!("Clock is in sync. Time offset cleared.");
+    } else {
+        println!("Time offset set to {:+}s.", offset);
+    }
+    Ok(())
+}
+
+/// 信頼できる端末に表示された `code` から、システム時刻に加える時刻オフセットを求める
+///
+/// オフセットなしの時計を基準に、前後 24 時間までのステップを近い順に探索する。
+pub fn calibrated_offset(
+    secret_manager: &SecretManager,
+    name: &str,
+    code: &str,
+) -> Result<i64, AppError> {
+    let credential = secret_manager
+        .get_credential(name)
+        .ok_or_else(|| AppError::CredentialNotFound(name.to_string()))?;
+    let generator = otp::generator_for(credential)?;
+    let period = generator.period();
+    let code = code.trim().replace(" ", "").to_lowercase();
+
+    let system_timestamp = secret_manager.sources().clock.now()?;
+    let current_step = system_timestamp / period;
+    let matched_step = (0..=MAX_CALIBRATION_SECONDS / period)
+        .flat_map(|distance| {
+            [
+                current_step.checked_sub(distance),
+                current_step.checked_add(distance),
+            ]
+        })
+        .flatten()
+        .find(|step| {
+            step.checked_mul(period)
+                .is_some_and(|timestamp| generator.generate(timestamp).to_lowercase() == code)
+        })
+        .ok_or_else(|| {
+            AppError::GeneralError("The code did not match within ±24 hours.".to_string())
+        })?;
+
+    if matched_step == current_step {
+        return Ok(0);
+    }
+    // コードを入力した瞬間がステップ内のどこかは分からないため、ステップの中央に合わせる
+    // (ずれは ±24 時間に収まるため i64 に変換できる)
+    let target = matched_step as i128 * period as i128 + (period / 2) as i128;
+    Ok((target - system_timestamp as i128) as i64)
+}
+
+pub fn show() -> Result<(), AppError> {
+    let master_password = secrets::get_master_password()?;
+    let secret_manager = SecretManager::load_secrets(&master_password)?;
+
+    match secret_manager.time_offset() {
+        0 => println!("No time offset is active."),
+        offset => println!("Time offset: {:+}s", offset),
+    }
+    Ok(())
+}
+
+pub fn set(seconds: i64) -> Result<(), AppError> {
+    let master_password = secrets::get_master_password()?;
+    let mut secret_manager = SecretManager::load_secrets(&master_password)?;
+    secret_manager.set_time_offset(seconds);
+    secret_manager.save_secrets(&master_password)?;
+
+    println!("Time offset set to {:+}s.", seconds);
+    Ok(())
+}
+
+pub fn reset() -> Result<(), AppError> {
+    set(0)
+}
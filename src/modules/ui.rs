@@ -1,5 +1,7 @@
 use crate::error::AppError;
-use crate::secrets::{self, SecretManager};
+use crate::otp::{self, OtpGenerator};
+use crate::secrets::{self, LOW_RECOVERY_CODES, SecretManager};
+use crate::time::{self, Clock};
 use crossterm::{
     event::{self, Event as CrosstermEvent, KeyCode},
     execute,
@@ -11,48 +13,56 @@ use ratatui::{
     style::{Color, Modifier, Style},
     text::{Line, Span},
     widgets::{Block, BorderType, Borders, Gauge, ListState, Paragraph},
-    Terminal,
+    Frame, Terminal,
 };
 use std::{
     io,
     panic,
-    time::{Duration, SystemTime, UNIX_EPOCH},
+    sync::Arc,
+    time::Duration,
 };
-use totp_rs::{Algorithm, TOTP};
 
 /// 認証情報の表示用構造体
 struct CredentialDisplay {
     name: String,
     totp_code: Option<String>,
     time_until_next_code: u64,
-    decrypted_key_bytes: Vec<u8>,
+    generator: Box<dyn OtpGenerator>,
+    /// 未使用のリカバリーコードの数 (登録していなければ None)
+    recovery_codes_left: Option<usize>,
 }
 
+/// カード1枚分の高さ (枠線と隙間を含む)
+const ITEM_HEIGHT: u16 = 5;
+
+/// 残り秒数がこれ以下になったらコードを赤で表示する
+const EXPIRY_WARNING_SECONDS: u64 = 5;
+
 /// アプリケーションの状態管理
-struct App {
+pub struct App {
     credentials_display: Vec<CredentialDisplay>,
     list_state: ListState,
     scroll_offset: usize,
+    time_offset: i64,
+    /// コード生成に使う時計 (保管庫と同じもの)
+    clock: Arc<dyn Clock>,
+    last_update: u64,
 }
 
 impl App {
-    fn new(master_password: String, secret_manager: SecretManager) -> Result<Self, AppError> {
+    /// 保管庫の認証情報から表示用の状態を作る (保管庫の時計で最初のコードを生成する)
+    pub fn new(secret_manager: SecretManager) -> Result<Self, AppError> {
         let mut credentials_display = Vec::new();
-        let all_names = secret_manager.list_credentials();
-        for name in all_names {
-            if let Some(cred) = secret_manager.get_credential(name) {
-                let decrypted_key_bytes = secrets::decrypt_data(
-                    master_password.as_bytes(),
-                    &cred.ciphertext,
-                    &cred.nonce,
-                )?;
-                credentials_display.push(CredentialDisplay {
-                    name: name.clone(),
-                    totp_code: None,
-                    time_until_next_code: 0,
-                    decrypted_key_bytes,
-                });
-            }
+        for cred in secret_manager.credentials() {
+            let generator = otp::generator_for(cred)?;
+            credentials_display.push(CredentialDisplay {
+                name: cred.name.clone(),
+                totp_code: None,
+                time_until_next_code: 0,
+                generator,
+                recovery_codes_left: (!cred.recovery_codes.is_empty())
+                    .then(|| cred.remaining_recovery_codes()),
+            });
         }
 
         let mut list_state = ListState::default();
@@ -64,37 +74,41 @@ impl App {
             credentials_display,
             list_state,
             scroll_offset: 0,
+            time_offset: secret_manager.time_offset(),
+            clock: Arc::clone(&secret_manager.sources().clock),
+            last_update: 0,
         };
         app.update_all_totp_codes()?;
         Ok(app)
     }
 
+    /// 時刻オフセットを適用した現在の時刻
+    fn now(&self) -> Result<u64, AppError> {
+        time::now_with(&*self.clock, self.time_offset)
+    }
+
     /// TOTPコードと残り時間を一括更新
     fn update_all_totp_codes(&mut self) -> Result<(), AppError> {
-        let current_timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map_err(|e| AppError::GeneralError(format!("SystemTime error: {}", e)))?
-            .as_secs();
+        let current_timestamp = self.now()?;
 
         for cred_display in &mut self.credentials_display {
-            let totp_instance = TOTP::new(
-                Algorithm::SHA1,
-                6,
-                1,
-                30,
-                cred_display.decrypted_key_bytes.clone(),
-            )
-            .map_err(|e| AppError::GeneralError(format!("Failed to create TOTP: {}", e)))?;
-
-            cred_display.totp_code = Some(totp_instance.generate_current().map_err(|e| {
-                AppError::GeneralError(format!("Failed to generate TOTP code: {}", e))
-            })?);
-            cred_display.time_until_next_code = 30 - (current_timestamp % 30);
+            let period = cred_display.generator.period();
+            cred_display.totp_code = Some(cred_display.generator.generate(current_timestamp));
+            cred_display.time_until_next_code = period - (current_timestamp % period);
         }
+        self.last_update = current_timestamp;
         Ok(())
     }
 
-    fn next(&mut self) {
+    /// 時刻が進んでいればコードを更新
+    pub fn tick(&mut self) -> Result<(), AppError> {
+        if self.now()? != self.last_update {
+            self.update_all_totp_codes()?;
+        }
+        Ok(())
+    }
+
+    pub fn next(&mut self) {
         if self.credentials_display.is_empty() {
             return;
         }
@@ -105,7 +119,7 @@ impl App {
         self.list_state.select(Some(i));
     }
 
-    fn previous(&mut self) {
+    pub fn previous(&mut self) {
         if self.credentials_display.is_empty() {
             return;
         }
@@ -123,6 +137,160 @@ impl App {
     }
 }
 
+/// 画面全体を描画する (端末の操作や入力の処理は行わない)
+///
+/// 選択中のカードが見えるように `scroll_offset` を調整する。
+pub fn render(f: &mut Frame, app: &mut App) {
+    let full_area = f.area();
+
+    // レイアウト構成
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3), // ヘッダー
+            Constraint::Min(0),    // メインリスト
+        ])
+        .split(full_area);
+
+    // ヘッダー描画
+    let mut header_spans = vec![
+        Span::styled(" GAuth ", Style::default().fg(Color::Blue).add_modifier(Modifier::BOLD)),
+        Span::styled("Authenticator", Style::default().fg(Color::Gray)),
+    ];
+    // 時刻オフセットが有効な場合は警告を表示
+    if app.time_offset != 0 {
+        header_spans.push(Span::styled(
+            format!("  ⚠ Clock offset {:+}s", app.time_offset),
+            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+        ));
+    }
+    let header = Paragraph::new(Line::from(header_spans))
+    .block(Block::default().borders(Borders::BOTTOM).border_style(Style::default().fg(Color::DarkGray)))
+    .alignment(Alignment::Center);
+    f.render_widget(header, chunks[0]);
+
+    // コンテンツエリアの余白設定
+    let list_area = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage(5),
+            Constraint::Percentage(90),
+            Constraint::Percentage(5),
+        ])
+        .split(chunks[1])[1];
+
+    if app.credentials_display.is_empty() {
+        let hint = Paragraph::new(Line::from(vec![
+            Span::styled("No credentials yet. Run ", Style::default().fg(Color::Gray)),
+            Span::styled("gauth auth add", Style::default().fg(Color::Blue)),
+        ]))
+        .alignment(Alignment::Center);
+        f.render_widget(hint, Rect { height: list_area.height.min(1), ..list_area });
+        return;
+    }
+
+    let displayable_count = (list_area.height / ITEM_HEIGHT) as usize;
+    // カード1枚分の高さもない端末では何も描画しない
+    if displayable_count == 0 {
+        return;
+    }
+
+    // スクロール計算
+    if let Some(selected) = app.list_state.selected() {
+        if selected < app.scroll_offset {
+            app.scroll_offset = selected;
+        } else if selected >= app.scroll_offset + displayable_count {
+            app.scroll_offset = selected - displayable_count + 1;
+        }
+    }
+
+    let start = app.scroll_offset;
+    let end = (start + displayable_count).min(app.credentials_display.len());
+    let mut current_y = list_area.y;
+
+    for (idx, cred) in app.credentials_display[start..end].iter().enumerate() {
+        let is_selected = app.list_state.selected() == Some(start + idx);
+        
+        let card_rect = Rect {
+            x: list_area.x,
+            y: current_y,
+            width: list_area.width,
+            height: ITEM_HEIGHT - 1, // カード間に隙間を作る
+        };
+
+        // カードの枠線スタイル
+        let (bc, bt) = if is_selected {
+            (Color::Blue, BorderType::Thick)
+        } else {
+            (Color::DarkGray, BorderType::Rounded)
+        };
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(bc))
+            .border_type(bt);
+        
+        f.render_widget(block.clone(), card_rect);
+        let inner = block.inner(card_rect);
+
+        // カード内部レイアウト
+        let internal = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Min(20),   // 名前とコード
+                Constraint::Length(15), // ゲージ
+            ])
+            .split(inner);
+
+        // 左側: テキスト情報
+        let info_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Length(1)])
+            .split(internal[0]);
+
+        let mut name_spans = vec![Span::styled(
+            &cred.name,
+            Style::default().fg(if is_selected { Color::White } else { Color::Gray }),
+        )];
+        // リカバリーコードの残り (少ない場合は警告色)
+        if let Some(left) = cred.recovery_codes_left {
+            let color = if left <= LOW_RECOVERY_CODES { Color::Yellow } else { Color::DarkGray };
+            name_spans.push(Span::styled(
+                format!("  {} recovery code(s) left", left),
+                Style::default().fg(color),
+            ));
+        }
+        f.render_widget(Paragraph::new(Line::from(name_spans)), info_chunks[0]);
+
+        let code_disp = match &cred.totp_code {
+            Some(code) => otp::format_code(code),
+            None => "------".into(),
+        };
+
+        let color = if cred.time_until_next_code <= EXPIRY_WARNING_SECONDS { Color::Red } else { Color::Blue };
+        f.render_widget(
+            Paragraph::new(Span::styled(code_disp, Style::default().fg(color).add_modifier(Modifier::BOLD))),
+            info_chunks[1]
+        );
+
+        // 右側: タイムゲージ
+        let gauge = Gauge::default()
+            .gauge_style(Style::default().fg(color).bg(Color::Rgb(30, 30, 30)))
+            .ratio(cred.time_until_next_code as f64 / cred.generator.period() as f64)
+            .label(format!("{}s", cred.time_until_next_code))
+            .use_unicode(true);
+
+        let gauge_area = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Length(1)])
+            .split(internal[1])[1]; // 2行目に配置して中央寄せっぽくする
+
+        f.render_widget(gauge, gauge_area);
+
+        current_y += ITEM_HEIGHT;
+    }
+}
+
 /// UIモードのメインエントリポイント
 pub fn run_ui_mode() -> Result<(), AppError> {
     // 1. パニックハンドラの設定 (異常終了時にターミナルを復元する)
@@ -137,7 +305,7 @@ pub fn run_ui_mode() -> Result<(), AppError> {
     // 2. データのロード
     let master_password = secrets::get_master_password()?;
     let secret_manager = SecretManager::load_secrets(&master_password)?;
-    let mut app = App::new(master_password, secret_manager)?;
+    let mut app = App::new(secret_manager)?;
 
     // 3. ターミナルの準備
     enable_raw_mode()
@@ -149,134 +317,13 @@ pub fn run_ui_mode() -> Result<(), AppError> {
     let mut terminal = Terminal::new(backend)
         .map_err(|e| AppError::GeneralError(format!("Failed to create terminal: {}", e)))?;
 
-    const ITEM_HEIGHT: u16 = 5;
-
     // 4. メインループ
     loop {
-        terminal.draw(|f| {
-            let full_area = f.area();
-
-            // レイアウト構成
-            let chunks = Layout::default()
-                .direction(Direction::Vertical)
-                .constraints([
-                    Constraint::Length(3), // ヘッダー
-                    Constraint::Min(0),    // メインリスト
-                ])
-                .split(full_area);
-
-            // ヘッダー描画
-            let header = Paragraph::new(Line::from(vec![
-                Span::styled(" GAuth ", Style::default().fg(Color::Blue).add_modifier(Modifier::BOLD)),
-                Span::styled("Authenticator", Style::default().fg(Color::Gray)),
-            ]))
-            .block(Block::default().borders(Borders::BOTTOM).border_style(Style::default().fg(Color::DarkGray)))
-            .alignment(Alignment::Center);
-            f.render_widget(header, chunks[0]);
-
-            // コンテンツエリアの余白設定
-            let list_area = Layout::default()
-                .direction(Direction::Horizontal)
-                .constraints([
-                    Constraint::Percentage(5),
-                    Constraint::Percentage(90),
-                    Constraint::Percentage(5),
-                ])
-                .split(chunks[1])[1];
-
-            let displayable_count = (list_area.height / ITEM_HEIGHT) as usize;
-            
-            // スクロール計算
-            if let Some(selected) = app.list_state.selected() {
-                if selected < app.scroll_offset {
-                    app.scroll_offset = selected;
-                } else if selected >= app.scroll_offset + displayable_count {
-                    app.scroll_offset = selected - displayable_count + 1;
-                }
-            }
-
-            let start = app.scroll_offset;
-            let end = (start + displayable_count).min(app.credentials_display.len());
-            let mut current_y = list_area.y;
-
-            for (idx, cred) in app.credentials_display[start..end].iter().enumerate() {
-                let is_selected = app.list_state.selected() == Some(start + idx);
-                
-                let card_rect = Rect {
-                    x: list_area.x,
-                    y: current_y,
-                    width: list_area.width,
-                    height: ITEM_HEIGHT - 1, // カード間に隙間を作る
-                };
-
-                // カードの枠線スタイル
-                let (bc, bt) = if is_selected {
-                    (Color::Blue, BorderType::Thick)
-                } else {
-                    (Color::DarkGray, BorderType::Rounded)
-                };
-
-                let block = Block::default()
-                    .borders(Borders::ALL)
-                    .border_style(Style::default().fg(bc))
-                    .border_type(bt);
-                
-                f.render_widget(block.clone(), card_rect);
-                let inner = block.inner(card_rect);
-
-                // カード内部レイアウト
-                let internal = Layout::default()
-                    .direction(Direction::Horizontal)
-                    .constraints([
-                        Constraint::Min(20),   // 名前とコード
-                        Constraint::Length(15), // ゲージ
-                    ])
-                    .split(inner);
-
-                // 左側: テキスト情報
-                let info_chunks = Layout::default()
-                    .direction(Direction::Vertical)
-                    .constraints([Constraint::Length(1), Constraint::Length(1)])
-                    .split(internal[0]);
-
-                f.render_widget(
-                    Paragraph::new(Span::styled(&cred.name, Style::default().fg(if is_selected { Color::White } else { Color::Gray }))),
-                    info_chunks[0]
-                );
-
-                let code_raw = cred.totp_code.clone().unwrap_or_else(|| "------".into());
-                let code_disp = if code_raw.len() == 6 {
-                    format!("{} {}", &code_raw[0..3], &code_raw[3..6])
-                } else {
-                    code_raw
-                };
-
-                let color = if cred.time_until_next_code <= 5 { Color::Red } else { Color::Blue };
-                f.render_widget(
-                    Paragraph::new(Span::styled(code_disp, Style::default().fg(color).add_modifier(Modifier::BOLD))),
-                    info_chunks[1]
-                );
-
-                // 右側: タイムゲージ
-                let gauge = Gauge::default()
-                    .gauge_style(Style::default().fg(color).bg(Color::Rgb(30, 30, 30)))
-                    .ratio(cred.time_until_next_code as f64 / 30.0)
-                    .label(format!("{}s", cred.time_until_next_code))
-                    .use_unicode(true);
-
-                let gauge_area = Layout::default()
-                    .direction(Direction::Vertical)
-                    .constraints([Constraint::Length(1), Constraint::Length(1)])
-                    .split(internal[1])[1]; // 2行目に配置して中央寄せっぽくする
-
-                f.render_widget(gauge, gauge_area);
-
-                current_y += ITEM_HEIGHT;
-            }
-        })
+        terminal.draw(|f| render(f, &mut app))
         .map_err(|e| AppError::GeneralError(format!("Draw error: {}", e)))?;
 
         // 入力イベント
+        #[allow(clippy::collapsible_if)]
         if event::poll(Duration::from_millis(100))? {
             if let CrosstermEvent::Key(key) = event::read()? {
                 match key.code {
@@ -289,12 +336,7 @@ pub fn run_ui_mode() -> Result<(), AppError> {
         }
 
         // 時間による自動更新
-        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
-        if let Some(first) = app.credentials_display.first() {
-            if (30 - (now % 30)) != first.time_until_next_code {
-                app.update_all_totp_codes()?;
-            }
-        }
+        app.tick()?;
     }
 
     // 5. 正常終了時の復元
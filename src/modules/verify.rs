@@ -0,0 +1,156 @@
+use crate::error::AppError;
+use crate::otp;
+use crate::secrets::{self, SecretManager};
+use crate::select;
+
+/// 検証結果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyOutcome {
+    /// 有効なコード (一致したタイムステップと現在からのずれ)
+    Valid { step: u64, drift: i64 },
+    /// 一致するコードなし
+    Invalid,
+    /// 既に受理済みのステップ以前のコード
+    Replayed { step: u64, drift: i64 },
+}
+
+impl VerifyOutcome {
+    /// プロセスの終了ステータス (エラー時の 1 とは重ならないようにする)
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            VerifyOutcome::Valid { .. } => 0,
+            VerifyOutcome::Invalid => 2,
+            VerifyOutcome::Replayed { .. } => 3,
+        }
+    }
+}
+
+/// `--window` に指定できる最大のステップ数
+pub const MAX_WINDOW: u64 = 20;
+
+pub fn verify(
+    name: String,
+    code: String,
+    window: u64,
+    record: bool,
+) -> Result<VerifyOutcome, AppError> {
+    let master_password = secrets::get_master_password()?;
+    let mut secret_manager = SecretManager::load_secrets(&master_password)?;
+    let name = select::one(&secret_manager.credentials(), &name)?;
+    let last_step = secret_manager
+        .get_credential(&name)
+        .and_then(|credential| credential.last_step);
+
+    let outcome = check(&secret_manager, &name, &code, window)?;
+    match outcome {
+        VerifyOutcome::Valid { step, drift } => {
+            println!("Valid code for {} (step {}, drift {:+})", name, step, drift);
+            if record {
+                record_step(&mut secret_manager, &name, step)?;
+                secret_manager.save_secrets(&master_password)?;
+            }
+        }
+        VerifyOutcome::Invalid => {
+            println!("Invalid code for {} (window ±{})", name, window);
+        }
+        VerifyOutcome::Replayed { step, drift } => {
+            println!(
+                "Replayed code for {} (step {}, drift {:+}, already accepted up to step {})",
+                name,
+                step,
+                drift,
+                last_step.unwrap_or_default()
+            );
+        }
+    }
+
+    Ok(outcome)
+}
+
+/// 保管庫の時計で `code` を検証する (表示も保存も行わない)
+///
+/// 現在のステップから前後 `window` ステップまでを探索する。
+pub fn check(
+    secret_manager: &SecretManager,
+    name: &str,
+    code: &str,
+    window: u64,
+) -> Result<VerifyOutcome, AppError> {
+    if window > MAX_WINDOW {
+        return Err(AppError::InvalidInput(format!(
+            "The window must be at most {} steps",
+            MAX_WINDOW
+        )));
+    }
+    let credential = secret_manager
+        .get_credential(name)
+        .ok_or_else(|| AppError::CredentialNotFound(name.to_string()))?;
+    let generator = otp::generator_for(credential)?;
+    let period = generator.period();
+
+    let current_step = secret_manager.now()? / period;
+    let code = code.trim().replace(" ", "").to_lowercase();
+
+    // 現在のステップに近い順に探索し、最もずれの小さい一致を採用する
+    let mut matched = None;
+    for distance in 0..=window {
+        let candidates = if distance == 0 {
+            vec![Some(current_step)]
+        } else {
+            vec![
+                current_step.checked_sub(distance),
+                current_step.checked_add(distance),
+            ]
+        };
+        // 時刻が表せる範囲の外になるステップは探索しない
+        let timestamps = candidates
+            .into_iter()
+            .flatten()
+            .filter_map(|step| Some((step, step.checked_mul(period)?)));
+        for (step, timestamp) in timestamps {
+            let expected = generator.generate(timestamp).to_lowercase();
+            if constant_time_eq(expected.as_bytes(), code.as_bytes()) {
+                matched = Some(step);
+                break;
+            }
+        }
+        if matched.is_some() {
+            break;
+        }
+    }
+
+    let Some(step) = matched else {
+        return Ok(VerifyOutcome::Invalid);
+    };
+    // ずれは window 以下のため i64 に収まる
+    let drift = if step >= current_step {
+        (step - current_step) as i64
+    } else {
+        -((current_step - step) as i64)
+    };
+    Ok(match credential.last_step {
+        Some(last_step) if step <= last_step => VerifyOutcome::Replayed { step, drift },
+        _ => VerifyOutcome::Valid { step, drift },
+    })
+}
+
+/// 受理したステップを記録し、以降はそのステップ以前のコードをリプレイとして拒否する
+pub fn record_step(
+    secret_manager: &mut SecretManager,
+    name: &str,
+    step: u64,
+) -> Result<(), AppError> {
+    let credential = secret_manager
+        .get_credential_mut(name)
+        .ok_or_else(|| AppError::CredentialNotFound(name.to_string()))?;
+    credential.last_step = Some(step);
+    Ok(())
+}
+
+/// タイミング差で一致位置が漏れないように比較する
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
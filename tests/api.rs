@@ -0,0 +1,132 @@
+use aes_gcm::{
+    Aes256Gcm, Key, KeyInit, Nonce,
+    aead::{Aead, OsRng, rand_core::RngCore},
+};
+use gauth::error::AppError;
+use gauth::otp::CredentialKind;
+use gauth::secrets::Credential;
+use gauth::storage::{MemoryStorage, Storage};
+use gauth::{Code, Vault};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+
+const SECRET: &[u8] = b"12345678901234567890";
+
+fn example() -> Credential {
+    Credential::new("example".into(), CredentialKind::Totp, SECRET.to_vec())
+}
+
+#[test]
+fn codes_at_arbitrary_timestamps() {
+    let mut vault = Vault::open_with(Arc::new(MemoryStorage::new()), "hunter2").unwrap();
+    assert!(vault.credentials().is_empty());
+    vault.add(example()).unwrap();
+
+    // RFC 6238 の SHA1 のテストベクタ (8桁の下6桁)
+    assert_eq!(
+        vault.code_at("example", 59).unwrap(),
+        Code {
+            code: "287082".into(),
+            timestamp: 59,
+            period: 30,
+            remaining: 1,
+        }
+    );
+    assert_eq!(vault.code_at("example", 1111111109).unwrap().code, "081804");
+    assert!(matches!(
+        vault.code_at("missing", 59),
+        Err(AppError::CredentialNotFound(_))
+    ));
+}
+
+#[test]
+fn debug_output_hides_the_password_and_secrets() {
+    let mut vault = Vault::open_with(Arc::new(MemoryStorage::new()), "hunter2").unwrap();
+    vault.add(example()).unwrap();
+    let debug = format!("{:?}", vault);
+    assert!(debug.contains("<redacted>"));
+    assert!(!debug.contains("hunter2"));
+    assert!(!debug.contains(&format!("{:?}", SECRET)));
+    assert!(!debug.contains("secret"));
+}
+
+#[test]
+fn add_remove_and_reopen() {
+    let storage = MemoryStorage::new();
+    let mut vault = Vault::open_with(Arc::new(storage.clone()), "hunter2").unwrap();
+    vault.add(example()).unwrap();
+    assert!(matches!(
+        vault.add(example()),
+        Err(AppError::CredentialExists(name)) if name == "example"
+    ));
+    let mut other = Credential::new("other".into(), CredentialKind::Totp, SECRET.to_vec());
+    other.issuer = Some("Other".into());
+    assert!(vault.insert(other).unwrap().is_none());
+    assert!(vault.save().unwrap().is_empty());
+
+    let mut reopened = Vault::open_with(Arc::new(storage.clone()), "hunter2").unwrap();
+    assert!(reopened.migration().is_none());
+    let names: Vec<_> = reopened
+        .credentials()
+        .iter()
+        .map(|c| c.name.clone())
+        .collect();
+    assert_eq!(names, ["example", "other"]);
+
+    assert_eq!(
+        reopened.remove("other").unwrap().issuer.as_deref(),
+        Some("Other")
+    );
+    assert!(matches!(
+        reopened.remove("other"),
+        Err(AppError::CredentialNotFound(_))
+    ));
+    reopened.save().unwrap();
+
+    let reopened = Vault::open_with(Arc::new(storage.clone()), "hunter2").unwrap();
+    assert_eq!(reopened.credentials().len(), 1);
+    assert!(matches!(
+        Vault::open_with(Arc::new(storage), "wrong"),
+        Err(AppError::WrongMasterPassword)
+    ));
+}
+
+#[test]
+fn invalid_credentials_are_rejected_before_saving() {
+    let mut vault = Vault::open_with(Arc::new(MemoryStorage::new()), "hunter2").unwrap();
+    // mOTP には PIN が必要
+    let motp = Credential::new(
+        "vpn".into(),
+        CredentialKind::Motp,
+        b"0123456789abcdef".to_vec(),
+    );
+    assert!(vault.add(motp).is_err());
+    assert!(vault.get("vpn").is_none());
+}
+
+#[test]
+fn reports_migration_instead_of_printing() {
+    let password = "hunter2";
+    let key = Sha256::digest(password.as_bytes());
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+    let mut nonce = [0u8; 12];
+    OsRng.fill_bytes(&mut nonce);
+    let mut contents = cipher
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            b"time_offset = 3\n\n[credentials]\n".as_slice(),
+        )
+        .unwrap();
+    contents.extend_from_slice(&nonce);
+
+    let storage = MemoryStorage::new();
+    storage.write(&contents, None).unwrap();
+    let vault = Vault::open_with(Arc::new(storage.clone()), password).unwrap();
+    assert_eq!(vault.migration().unwrap().version, 2);
+    assert_eq!(vault.time_offset(), 3);
+
+    let (migrated, _) = storage.read().unwrap().unwrap();
+    assert!(String::from_utf8_lossy(&migrated).starts_with("version = 2"));
+    let reopened = Vault::open_with(Arc::new(storage), password).unwrap();
+    assert!(reopened.migration().is_none());
+}
@@ -0,0 +1,219 @@
+use gauth::Vault;
+use gauth::error::AppError;
+use gauth::otp::{CredentialKind, HashAlgorithm, OtpParams};
+use gauth::random::SeededRandom;
+use gauth::secrets::{Credential, SecretManager, Sources};
+use gauth::storage::MemoryStorage;
+use gauth::time::{self, FixedClock};
+use std::sync::Arc;
+
+/// RFC 6238 Appendix B の時刻と SHA1 / SHA256 / SHA512 の8桁のコード
+const RFC6238_VECTORS: [(u64, [&str; 3]); 6] = [
+    (59, ["94287082", "46119246", "90693936"]),
+    (1111111109, ["07081804", "68084774", "25091201"]),
+    (1111111111, ["14050471", "67062674", "99943326"]),
+    (1234567890, ["89005924", "91819424", "93441116"]),
+    (2000000000, ["69279037", "90698825", "38618901"]),
+    (20000000000, ["65353130", "77737706", "47863826"]),
+];
+
+/// RFC 6238 の各アルゴリズム用のシークレット
+fn rfc6238_credentials() -> Vec<Credential> {
+    [
+        (HashAlgorithm::Sha1, &b"12345678901234567890"[..]),
+        (HashAlgorithm::Sha256, b"12345678901234567890123456789012"),
+        (
+            HashAlgorithm::Sha512,
+            b"1234567890123456789012345678901234567890123456789012345678901234",
+        ),
+    ]
+    .into_iter()
+    .map(|(algorithm, secret)| {
+        let mut credential = Credential::new(
+            format!("{:?}", algorithm).to_lowercase(),
+            CredentialKind::Totp,
+            secret.to_vec(),
+        );
+        credential.params = OtpParams {
+            algorithm,
+            digits: 8,
+            period: 30,
+        };
+        credential
+    })
+    .collect()
+}
+
+/// 指定した時計と乱数で開いた空の保管庫
+fn vault_with(clock: Arc<FixedClock>, seed: u64) -> Vault {
+    let sources = Sources {
+        clock,
+        random: Arc::new(SeededRandom::new(seed)),
+    };
+    Vault::open_with_sources(Arc::new(MemoryStorage::new()), "hunter2", sources).unwrap()
+}
+
+#[test]
+fn fixed_clock_produces_rfc6238_vectors() {
+    let clock = Arc::new(FixedClock::new(0));
+    let mut vault = vault_with(Arc::clone(&clock), 1);
+    for credential in rfc6238_credentials() {
+        vault.add(credential).unwrap();
+    }
+
+    for (timestamp, expected) in RFC6238_VECTORS {
+        clock.set(timestamp);
+        for (name, expected) in ["sha1", "sha256", "sha512"].into_iter().zip(expected) {
+            let code = vault.code(name).unwrap();
+            assert_eq!(code.code, expected, "{} at {}", name, timestamp);
+            assert_eq!(code.timestamp, timestamp);
+            assert_eq!(code, vault.code_at(name, timestamp).unwrap());
+        }
+    }
+}
+
+#[test]
+fn codes_follow_the_clock_without_sleeping() {
+    let clock = Arc::new(FixedClock::new(59));
+    let mut vault = vault_with(Arc::clone(&clock), 1);
+    vault.add(rfc6238_credentials().remove(0)).unwrap();
+
+    let code = vault.code("sha1").unwrap();
+    assert_eq!((code.code.as_str(), code.remaining), ("94287082", 1));
+
+    clock.advance(1);
+    let code = vault.code("sha1").unwrap();
+    assert_eq!((code.timestamp, code.remaining), (60, 30));
+    assert_ne!(code.code, "94287082");
+}
+
+#[test]
+fn time_offset_is_applied_to_the_clock() {
+    let mut manager = SecretManager::with_sources(Sources::fixed(1111111100, 1));
+    manager.set_time_offset(9);
+    assert_eq!(manager.now().unwrap(), 1111111109);
+    manager.set_time_offset(-1111111200);
+    assert_eq!(manager.now().unwrap(), 0);
+}
+
+#[test]
+fn seeded_random_makes_vaults_reproducible() {
+    let encode = |seed| {
+        let mut manager = SecretManager::with_sources(Sources::fixed(0, seed));
+        manager.insert_credential(rfc6238_credentials().remove(0));
+        manager.encode("hunter2").unwrap()
+    };
+
+    let first = encode(7);
+    assert_eq!(first, encode(7));
+    assert_ne!(first, encode(8));
+
+    // 決定的に作った保管庫も通常どおり開ける
+    let manager = SecretManager::decode(&first, "hunter2").unwrap();
+    assert!(manager.get_credential("sha1").is_some());
+}
+
+/// RFC 6238 の SHA1 のテストベクタ (6桁) の時刻と、そのステップのコード
+const TRUSTED_TIME: u64 = 1111111109;
+const TRUSTED_CODE: &str = "081804";
+
+/// システム時刻が `timestamp` の端末の保管庫
+fn manager_at(timestamp: u64) -> SecretManager {
+    let mut manager = SecretManager::with_sources(Sources::fixed(timestamp, 1));
+    manager.insert_credential(Credential::new(
+        "example".into(),
+        CredentialKind::Totp,
+        b"12345678901234567890".to_vec(),
+    ));
+    manager
+}
+
+#[test]
+fn calibration_finds_a_known_skew() {
+    // 同じステップ内なら補正しない
+    let manager = manager_at(TRUSTED_TIME - 20);
+    assert_eq!(
+        time::calibrated_offset(&manager, "example", TRUSTED_CODE).unwrap(),
+        0
+    );
+
+    // 5分遅れている端末と 3時間進んでいる端末
+    for skew in [-300i64, 3 * 60 * 60] {
+        let mut manager = manager_at(TRUSTED_TIME.saturating_add_signed(skew));
+        let offset = time::calibrated_offset(&manager, "example", " 081 804 ").unwrap();
+        // ステップの中央 (1111111080 + 15) に合わせる
+        assert_eq!(offset, 1111111095 - (TRUSTED_TIME as i64 + skew));
+        manager.set_time_offset(offset);
+        assert_eq!(manager.now().unwrap(), 1111111095);
+        assert_eq!(manager.now().unwrap() % 30, 15);
+    }
+}
+
+#[test]
+fn calibration_gives_up_past_a_day() {
+    let day = 24 * 60 * 60;
+    assert!(
+        time::calibrated_offset(
+            &manager_at(TRUSTED_TIME + day - 60),
+            "example",
+            TRUSTED_CODE
+        )
+        .is_ok()
+    );
+    assert!(
+        time::calibrated_offset(
+            &manager_at(TRUSTED_TIME - day + 60),
+            "example",
+            TRUSTED_CODE
+        )
+        .is_ok()
+    );
+    for timestamp in [TRUSTED_TIME + day + 60, TRUSTED_TIME - day - 60] {
+        assert!(matches!(
+            time::calibrated_offset(&manager_at(timestamp), "example", TRUSTED_CODE),
+            Err(AppError::GeneralError(message)) if message.contains("24 hours")
+        ));
+    }
+    // 時刻の範囲の端でも溢れない
+    for timestamp in [0, u64::MAX] {
+        assert!(time::calibrated_offset(&manager_at(timestamp), "example", "000000").is_err());
+    }
+}
+
+#[test]
+fn negative_offsets_do_not_underflow() {
+    let clock = FixedClock::new(10);
+    assert_eq!(time::now_with(&clock, -3).unwrap(), 7);
+    assert_eq!(time::now_with(&clock, -100).unwrap(), 0);
+    assert_eq!(time::now_with(&clock, i64::MIN).unwrap(), 0);
+    assert_eq!(time::now_millis_with(&clock, -3).unwrap(), 7000);
+    assert_eq!(time::now_millis_with(&clock, i64::MIN).unwrap(), 0);
+    assert_eq!(
+        time::now_with(&FixedClock::new(u64::MAX), i64::MAX).unwrap(),
+        u64::MAX
+    );
+}
+
+#[test]
+fn set_and_reset_are_saved_with_the_vault() {
+    let storage = MemoryStorage::new();
+    let (mut manager, _) =
+        SecretManager::open_with(Arc::new(storage.clone()), "hunter2", Sources::fixed(100, 1))
+            .unwrap();
+    manager.set_time_offset(-30);
+    assert_eq!(manager.describe_changes(), "Set time offset to -30s");
+    manager.save_secrets("hunter2").unwrap();
+    let (mut manager, _) =
+        SecretManager::open_with(Arc::new(storage.clone()), "hunter2", Sources::fixed(100, 1))
+            .unwrap();
+    assert_eq!(manager.time_offset(), -30);
+    assert_eq!(manager.now().unwrap(), 70);
+
+    manager.set_time_offset(0);
+    assert_eq!(manager.describe_changes(), "Set time offset to +0s");
+    manager.save_secrets("hunter2").unwrap();
+    let (manager, _) =
+        SecretManager::open_with(Arc::new(storage), "hunter2", Sources::fixed(100, 1)).unwrap();
+    assert_eq!(manager.time_offset(), 0);
+    assert_eq!(manager.now().unwrap(), 100);
+}
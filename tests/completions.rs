@@ -0,0 +1,139 @@
+use clap_complete::Shell;
+use gauth::completions;
+use gauth::config::Config;
+use gauth::index::{IndexEntry, NameIndex};
+use gauth::otp::CredentialKind;
+use gauth::secrets::{Credential, SecretManager};
+use gauth::storage::MemoryStorage;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const PASSWORD: &str = "hunter2";
+
+fn temp_path(label: &str) -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir()
+        .join(format!(
+            "gauth-index-{}-{}-{}",
+            label,
+            std::process::id(),
+            nanos
+        ))
+        .join("names.toml")
+}
+
+fn credential(name: &str) -> Credential {
+    Credential::new(
+        name.into(),
+        CredentialKind::Totp,
+        b"12345678901234567890".to_vec(),
+    )
+}
+
+#[test]
+fn index_follows_saves() {
+    let path = temp_path("save");
+    let storage = Arc::new(MemoryStorage::new());
+    let mut manager = SecretManager::load_from(storage.clone(), PASSWORD).unwrap();
+    manager.set_index_path(Some(path.clone()));
+    // 空の保管庫でも索引は作られる
+    assert_eq!(NameIndex::read(&path).unwrap(), Some(NameIndex::default()));
+    assert_eq!(manager.index_warning(), None);
+
+    manager.insert_credential(credential("gitlab"));
+    manager.insert_credential(credential("github"));
+    manager.save_secrets(PASSWORD).unwrap();
+    let index = NameIndex::read(&path).unwrap().unwrap();
+    assert_eq!(index.names().collect::<Vec<_>>(), ["github", "gitlab"]);
+
+    manager.delete_credential("gitlab");
+    manager.save_secrets(PASSWORD).unwrap();
+    let index = NameIndex::read(&path).unwrap().unwrap();
+    assert_eq!(index.names().collect::<Vec<_>>(), ["github"]);
+
+    manager.get_credential_mut("github").unwrap().issuer = Some("GitHub".into());
+    manager.get_credential_mut("github").unwrap().tags = vec!["work".into(), "dev".into()];
+    manager.save_secrets(PASSWORD).unwrap();
+    assert_eq!(
+        NameIndex::read(&path).unwrap().unwrap().credentials,
+        [IndexEntry {
+            name: "github".into(),
+            issuer: Some("GitHub".into()),
+            tags: vec!["work".into(), "dev".into()],
+        }]
+    );
+
+    // シークレットや PIN は書き出さない
+    let contents = std::fs::read_to_string(&path).unwrap();
+    assert!(!contents.contains("secret"));
+    assert!(!contents.contains("GEZDGNBVGY3TQOJQ"));
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn index_is_off_by_default() {
+    assert!(!Config::default().index.enabled);
+
+    // 一度書き出した場所でも、無効にした後の保存では書き出さない
+    let path = temp_path("off");
+    let storage = Arc::new(MemoryStorage::new());
+    let mut manager = SecretManager::load_from(storage, PASSWORD).unwrap();
+    manager.set_index_path(Some(path.clone()));
+    assert!(path.exists());
+    std::fs::remove_file(&path).unwrap();
+
+    manager.set_index_path(None);
+    manager.insert_credential(credential("github"));
+    manager.save_secrets(PASSWORD).unwrap();
+    assert!(!path.exists());
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn unwritable_index_does_not_fail_saves() {
+    // 索引の親ディレクトリがファイルのため書き出せない
+    let blocker = temp_path("blocked");
+    std::fs::create_dir_all(blocker.parent().unwrap()).unwrap();
+    std::fs::write(&blocker, "").unwrap();
+    let path = blocker.join("names.toml");
+
+    let storage = Arc::new(MemoryStorage::new());
+    let mut manager = SecretManager::load_from(storage.clone(), PASSWORD).unwrap();
+    manager.set_index_path(Some(path.clone()));
+    assert!(manager.index_warning().is_some());
+    manager.insert_credential(credential("github"));
+    manager.commit(PASSWORD, "Add github").unwrap();
+    assert!(!path.exists());
+    // 書き出せない理由は表示せずに呼び出し側へ返す
+    let warning = manager.index_warning().unwrap();
+    assert!(warning.contains("name index"), "{}", warning);
+
+    // 書き出せるようになれば警告は消える
+    manager.set_index_path(None);
+    assert_eq!(manager.index_warning(), None);
+
+    let reopened = SecretManager::load_from(storage, PASSWORD).unwrap();
+    assert!(reopened.get_credential("github").is_some());
+    std::fs::remove_dir_all(blocker.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn registration_scripts_call_back_into_gauth() {
+    for shell in [
+        Shell::Bash,
+        Shell::Zsh,
+        Shell::Fish,
+        Shell::Elvish,
+        Shell::PowerShell,
+    ] {
+        let mut script = Vec::new();
+        completions::write_registration(shell, &mut script).unwrap();
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.contains(completions::ENV_VAR), "{}", shell);
+        assert!(script.contains("gauth"), "{}", shell);
+    }
+}
@@ -0,0 +1,84 @@
+use gauth::error::{AppError, exit_code};
+use gauth::otp::{self, CredentialKind, OtpParams};
+use gauth::storage::MemoryStorage;
+use gauth::{Vault, secrets::Credential};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+#[test]
+fn error_kinds_have_distinct_exit_codes() {
+    let cases = [
+        (
+            AppError::CredentialNotFound("x".into()),
+            exit_code::NOT_FOUND,
+        ),
+        (AppError::WrongMasterPassword, exit_code::WRONG_PASSWORD),
+        (AppError::VaultCorrupted("x".into()), exit_code::CORRUPTED),
+        (
+            AppError::KeyringError(keyring::Error::NoStorageAccess("locked".into())),
+            exit_code::KEYRING,
+        ),
+        (AppError::InvalidKey, exit_code::INVALID_INPUT),
+        (
+            AppError::IoError(std::io::Error::other("disk full")),
+            exit_code::IO,
+        ),
+        (AppError::GeneralError("x".into()), exit_code::GENERAL),
+    ];
+    for (error, code) in &cases {
+        assert_eq!(error.exit_code(), *code, "{:?}", error);
+    }
+    let codes: HashSet<_> = cases.iter().map(|(_, code)| *code).collect();
+    assert_eq!(codes.len(), cases.len());
+    // verify の結果 (2: 無効, 3: リプレイ) と重ならない
+    assert!(!codes.contains(&2) && !codes.contains(&3) && !codes.contains(&0));
+}
+
+#[test]
+fn common_mistakes_come_with_hints() {
+    let error = AppError::CredentialNotFound("github".into());
+    assert_eq!(error.to_string(), "Auth 'github' not found");
+    assert!(error.hint().unwrap().contains("gauth auth list"));
+    assert!(
+        AppError::WrongMasterPassword
+            .hint()
+            .unwrap()
+            .contains("gauth recovery combine")
+    );
+    assert!(AppError::InvalidKey.hint().is_some());
+    assert!(
+        AppError::IndexUnavailable("x".into())
+            .hint()
+            .unwrap()
+            .contains("[index]")
+    );
+    assert!(AppError::StorageConflict.hint().is_none());
+}
+
+#[test]
+fn library_errors_are_typed() {
+    let mut vault = Vault::open_with(Arc::new(MemoryStorage::new()), "hunter2").unwrap();
+    assert_eq!(
+        vault.remove("missing").unwrap_err().exit_code(),
+        exit_code::NOT_FOUND
+    );
+
+    let motp = Credential::new(
+        "vpn".into(),
+        CredentialKind::Motp,
+        b"0123456789abcdef".to_vec(),
+    );
+    assert!(matches!(vault.add(motp), Err(AppError::InvalidInput(_))));
+    assert!(matches!(
+        otp::parse_uri("otpauth://totp/?secret=***"),
+        Err(AppError::InvalidInput(_))
+    ));
+    let params = OtpParams {
+        digits: 12,
+        ..OtpParams::default()
+    };
+    assert!(matches!(
+        otp::generator(CredentialKind::Totp, params, b"secret".to_vec(), None),
+        Err(AppError::InvalidInput(_))
+    ));
+}
@@ -0,0 +1,182 @@
+use gauth::export::{self, ExportFormat};
+use gauth::import::{self, ImportFormat};
+use gauth::otp::{CredentialKind, HashAlgorithm, OtpParams};
+use gauth::random::SeededRandom;
+use gauth::secrets::Credential;
+
+fn credential(name: &str, issuer: Option<&str>, kind: CredentialKind, secret: &[u8]) -> Credential {
+    Credential {
+        name: name.into(),
+        issuer: issuer.map(str::to_string),
+        kind,
+        params: OtpParams::default(),
+        secret: secret.to_vec(),
+        pin: None,
+        tags: Vec::new(),
+        last_step: None,
+        recovery_codes: Vec::new(),
+    }
+}
+
+fn sample() -> Vec<Credential> {
+    let mut totp = credential(
+        "Example:alice@example.com",
+        Some("Example"),
+        CredentialKind::Totp,
+        b"12345678901234567890",
+    );
+    totp.params = OtpParams {
+        algorithm: HashAlgorithm::Sha256,
+        digits: 8,
+        period: 60,
+    };
+    totp.tags = vec!["work".into()];
+    let mut motp = credential("legacy", None, CredentialKind::Motp, b"e3152afee62599c8");
+    motp.pin = Some("1234".into());
+    vec![
+        totp,
+        credential(
+            "gamer",
+            Some("Steam"),
+            CredentialKind::Steam,
+            b"12345678901234567890",
+        ),
+        motp,
+    ]
+}
+
+fn round_trip(password: Option<&str>) {
+    let credentials = sample();
+    let data = export::exporter(ExportFormat::Aegis)
+        .export(&credentials, password, &SeededRandom::new(1))
+        .unwrap();
+    let report = import::importer(ImportFormat::Aegis)
+        .parse(&data, &mut || Ok(password.unwrap_or_default().to_string()))
+        .unwrap();
+
+    assert!(report.skipped.is_empty());
+    let names: Vec<&str> = report.credentials.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(
+        names,
+        ["Example:alice@example.com", "Steam:gamer", "legacy"]
+    );
+    for (imported, original) in report.credentials.iter().zip(&credentials) {
+        assert_eq!(imported.kind, original.kind);
+        assert_eq!(imported.secret, original.secret);
+        assert_eq!(imported.pin, original.pin);
+        assert_eq!(imported.tags, original.tags);
+    }
+    assert_eq!(report.credentials[0].params, credentials[0].params);
+}
+
+#[test]
+fn aegis_plain_round_trip() {
+    round_trip(None);
+}
+
+#[test]
+fn aegis_encrypted_round_trip() {
+    round_trip(Some("export-password"));
+}
+
+#[test]
+fn aegis_export_is_deterministic_with_a_seed() {
+    let credentials = sample();
+    let export = |seed| {
+        export::exporter(ExportFormat::Aegis)
+            .export(&credentials, Some("export-password"), &SeededRandom::new(seed))
+            .unwrap()
+    };
+    assert_eq!(export(1), export(1));
+    assert_ne!(export(1), export(2));
+}
+
+#[test]
+fn otpauth_uri_round_trips() {
+    let credentials = sample();
+    let uri = gauth::otp::to_uri(&credentials[0]);
+    assert_eq!(
+        uri,
+        "otpauth://totp/Example:alice%40example.com?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ\
+         &issuer=Example&algorithm=SHA256&digits=8&period=60"
+    );
+    let parsed = gauth::otp::parse_uri(&uri).unwrap();
+    assert_eq!(parsed.name.as_deref(), Some("alice@example.com"));
+    assert_eq!(parsed.params, credentials[0].params);
+    assert_eq!(parsed.secret, credentials[0].secret);
+
+    let steam = gauth::otp::parse_uri(&gauth::otp::to_uri(&credentials[1])).unwrap();
+    assert_eq!(steam.kind, CredentialKind::Steam);
+}
+
+#[test]
+fn paper_backup_lists_every_credential() {
+    use export::paper::{self, PaperFormat};
+
+    let mut credentials = sample();
+    credentials[0].name = "<script>".into();
+    let html = String::from_utf8(paper::render(PaperFormat::Html, &credentials).unwrap()).unwrap();
+    assert!(html.contains("&lt;script&gt;"));
+    assert!(!html.contains("<script>"));
+    assert!(html.contains("GEZD GNBV GY3T QOJQ GEZD GNBV GY3T QOJQ"));
+    assert!(html.contains("e315 2afe e625 99c8"));
+    assert!(html.contains(&gauth::otp::secret_checksum(b"12345678901234567890")));
+    assert!(html.contains("PIN required (not printed)"));
+    assert!(!html.contains("1234</"));
+    assert_eq!(html.matches("<svg").count(), credentials.len());
+}
+
+#[test]
+fn paper_pdf_is_well_formed() {
+    use export::paper::{self, PaperFormat};
+
+    // 1ページに収まらない件数で改ページを確認する
+    let credentials: Vec<Credential> = (0..6)
+        .map(|i| {
+            credential(
+                &format!("account{}", i),
+                None,
+                CredentialKind::Totp,
+                b"12345678901234567890",
+            )
+        })
+        .collect();
+    let pdf = paper::render(PaperFormat::Pdf, &credentials).unwrap();
+    let text = String::from_utf8(pdf).unwrap();
+    assert!(text.starts_with("%PDF-1.4\n"));
+    assert!(text.ends_with("%%EOF\n"));
+    assert!(text.contains("/Count 2"));
+    assert!(text.contains("(account5)"));
+
+    // startxref が xref 表の位置を指している
+    let start = text.rsplit("startxref\n").next().unwrap();
+    let offset: usize = start.lines().next().unwrap().parse().unwrap();
+    assert!(text[offset..].starts_with("xref\n"));
+    let first_object = text[offset..].lines().nth(3).unwrap();
+    let position: usize = first_object[..10].parse().unwrap();
+    assert!(text[position..].starts_with("1 0 obj"));
+
+    // 標準フォントで表示できない名前は URI のラベルと同じ形で印刷し、注記を添える
+    let unicode = [credential(
+        "日本",
+        Some("Café"),
+        CredentialKind::Totp,
+        b"12345678901234567890",
+    )];
+    let pdf = paper::render(PaperFormat::Pdf, &unicode).unwrap();
+    let text = String::from_utf8(pdf).unwrap();
+    assert!(text.contains("(%E6%97%A5%E6%9C%AC)"));
+    assert!(text.contains("(Issuer: Caf%C3%A9)"));
+    assert!(text.contains("percent-encoded"));
+    assert!(!text.contains('?'));
+    let html = paper::render(PaperFormat::Html, &unicode).unwrap();
+    assert!(String::from_utf8(html).unwrap().contains("日本"));
+    let ascii = paper::render(PaperFormat::Pdf, &credentials).unwrap();
+    assert!(!String::from_utf8(ascii).unwrap().contains("percent-encoded"));
+
+    assert!(PaperFormat::from_path(std::path::Path::new("backup.txt")).is_err());
+    assert_eq!(
+        PaperFormat::from_path(std::path::Path::new("Backup.PDF")).unwrap(),
+        PaperFormat::Pdf
+    );
+}
@@ -0,0 +1,302 @@
+use gauth::error::AppError;
+use gauth::history;
+use gauth::otp::CredentialKind;
+use gauth::secrets::{Credential, SecretManager};
+use gauth::storage::{GitStorage, MemoryStorage, Storage};
+use gauth::sync::{self, SyncStatus};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const PASSWORD: &str = "hunter2";
+
+fn temp_dir(label: &str) -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let dir = std::env::temp_dir().join(format!(
+        "gauth-git-{}-{}-{}",
+        label,
+        std::process::id(),
+        nanos
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn credential(name: &str) -> Credential {
+    Credential::new(
+        name.into(),
+        CredentialKind::Totp,
+        b"12345678901234567890".to_vec(),
+    )
+}
+
+fn edit(repo: &GitStorage, change: impl FnOnce(&mut SecretManager)) {
+    let mut manager = SecretManager::load_from(Arc::new(repo.clone()), PASSWORD).unwrap();
+    change(&mut manager);
+    manager.save_secrets(PASSWORD).unwrap();
+}
+
+fn names(repo: &GitStorage) -> Vec<String> {
+    SecretManager::load_from(Arc::new(repo.clone()), PASSWORD)
+        .unwrap()
+        .credentials()
+        .iter()
+        .map(|credential| credential.name.clone())
+        .collect()
+}
+
+fn messages(repo: &GitStorage) -> Vec<String> {
+    repo.log()
+        .unwrap()
+        .into_iter()
+        .map(|revision| revision.message)
+        .collect()
+}
+
+fn bare_repo(root: &Path) -> String {
+    let bare = root.join("remote.git");
+    let status = Command::new("git")
+        .args(["init", "--quiet", "--bare"])
+        .arg(&bare)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    bare.display().to_string()
+}
+
+#[test]
+fn each_mutation_is_committed() {
+    let root = temp_dir("commits");
+    let repo = GitStorage::new(root.join("vault"), None);
+
+    edit(&repo, |m| m.insert_credential(credential("github")));
+    edit(&repo, |m| {
+        m.insert_credential(credential("aws"));
+        m.insert_credential(credential("gitlab"));
+    });
+    edit(&repo, |m| {
+        m.delete_credential("github");
+    });
+    edit(&repo, |m| m.set_time_offset(-3));
+
+    assert_eq!(
+        messages(&repo),
+        [
+            "Set time offset to -3s",
+            "Delete github",
+            "Add aws and gitlab",
+            "Add github",
+        ]
+    );
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn restores_a_deleted_credential() {
+    let root = temp_dir("restore");
+    let repo = GitStorage::new(root.join("vault"), None);
+    edit(&repo, |m| m.insert_credential(credential("github")));
+    edit(&repo, |m| m.insert_credential(credential("aws")));
+    edit(&repo, |m| {
+        m.delete_credential("github");
+    });
+
+    let mut manager = SecretManager::load_from(Arc::new(repo.clone()), PASSWORD).unwrap();
+    assert!(
+        history::restore_credential(&mut manager, &repo, "aws", None, PASSWORD).is_err(),
+        "existing credentials must not be overwritten"
+    );
+    let revision =
+        history::restore_credential(&mut manager, &repo, "github", None, PASSWORD).unwrap();
+    assert_eq!(revision.message, "Add aws");
+
+    assert_eq!(names(&repo), ["aws", "github"]);
+    assert_eq!(
+        messages(&repo)[0],
+        format!("Restore github from {}", revision.id)
+    );
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn revision_prefixes_must_be_unique() {
+    let root = temp_dir("prefix");
+    let repo = GitStorage::new(root.join("vault"), None);
+    // 17 個のリビジョンがあれば、先頭の1文字が同じものが必ずある
+    let mut shared = None;
+    for index in 0..17 {
+        edit(&repo, |m| {
+            m.insert_credential(credential(&format!("site{}", index)))
+        });
+        let ids: Vec<String> = repo.log().unwrap().into_iter().map(|r| r.id).collect();
+        shared = ids
+            .iter()
+            .find(|id| ids.iter().filter(|other| other[..1] == id[..1]).count() > 1)
+            .map(|id| id[..1].to_string());
+        if shared.is_some() {
+            break;
+        }
+    }
+    let shared = shared.unwrap();
+    assert!(matches!(
+        history::find_credential(&repo, "site0", Some(&shared), PASSWORD),
+        Err(AppError::AmbiguousRevision(revision, _)) if revision == shared
+    ));
+    assert!(matches!(
+        history::find_credential(&repo, "site0", Some("zzzzzzz"), PASSWORD),
+        Err(AppError::NotFound(_))
+    ));
+
+    let first = repo.log().unwrap().pop().unwrap();
+    let (revision, _) = history::find_credential(&repo, "site0", Some(&first.id), PASSWORD)
+        .unwrap()
+        .unwrap();
+    assert_eq!(revision, first);
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn undecodable_revisions_are_skipped() {
+    let root = temp_dir("undecodable");
+    let repo = GitStorage::new(root.join("vault"), None);
+    edit(&repo, |m| m.insert_credential(credential("github")));
+
+    // 別のパスワードで暗号化した保管庫をコミットする
+    let other = MemoryStorage::new();
+    let mut manager = SecretManager::load_from(Arc::new(other.clone()), "other-pw").unwrap();
+    manager.save_secrets("other-pw").unwrap();
+    let (data, _) = other.read().unwrap().unwrap();
+    let (_, version) = repo.read().unwrap().unwrap();
+    repo.write_change(&data, Some(&version), "Re-encrypt")
+        .unwrap();
+
+    let (revision, credential) = history::find_credential(&repo, "github", None, PASSWORD)
+        .unwrap()
+        .unwrap();
+    assert_eq!(revision.message, "Add github");
+    assert_eq!(credential.name, "github");
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn syncs_through_a_bare_remote() {
+    let root = temp_dir("sync");
+    let remote = bare_repo(&root);
+    let laptop = GitStorage::new(root.join("laptop"), Some(remote.clone()));
+    let desktop = GitStorage::new(root.join("desktop"), Some(remote));
+
+    edit(&laptop, |m| m.insert_credential(credential("github")));
+    assert_eq!(
+        sync::status_git(&laptop).unwrap(),
+        SyncStatus::RemoteMissing
+    );
+    assert!(sync::sync_git(&laptop, PASSWORD).unwrap().pushed);
+
+    assert_eq!(
+        sync::status_git(&desktop).unwrap(),
+        SyncStatus::RemoteChanges
+    );
+    assert_eq!(sync::sync_git(&desktop, PASSWORD).unwrap().pulled, 1);
+    assert_eq!(names(&desktop), ["github"]);
+
+    // 両方で別々に変更した後の同期はマージコミットになる
+    edit(&desktop, |m| m.insert_credential(credential("aws")));
+    sync::sync_git(&desktop, PASSWORD).unwrap();
+    edit(&laptop, |m| {
+        m.insert_credential(credential("gitlab"));
+        m.delete_credential("github");
+    });
+    assert_eq!(sync::status_git(&laptop).unwrap(), SyncStatus::Diverged);
+    let report = sync::sync_git(&laptop, PASSWORD).unwrap();
+    assert!(report.conflicts.is_empty());
+    assert_eq!(report.pulled, 1);
+    assert!(messages(&laptop)[0].starts_with("Merge remote changes"));
+
+    sync::sync_git(&desktop, PASSWORD).unwrap();
+    for repo in [&laptop, &desktop] {
+        assert_eq!(names(repo), ["aws", "gitlab"]);
+        assert_eq!(sync::status_git(repo).unwrap(), SyncStatus::UpToDate);
+    }
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn merges_unrelated_histories() {
+    let root = temp_dir("unrelated");
+    let remote = bare_repo(&root);
+    let laptop = GitStorage::new(root.join("laptop"), Some(remote.clone()));
+    let desktop = GitStorage::new(root.join("desktop"), Some(remote));
+
+    edit(&laptop, |m| m.insert_credential(credential("github")));
+    sync::sync_git(&laptop, PASSWORD).unwrap();
+    edit(&desktop, |m| m.insert_credential(credential("aws")));
+    sync::sync_git(&desktop, PASSWORD).unwrap();
+
+    assert_eq!(names(&desktop), ["aws", "github"]);
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn unreachable_remotes_are_not_empty() {
+    let root = temp_dir("unreachable");
+    let missing = GitStorage::new(
+        root.join("local"),
+        Some(root.join("missing.git").display().to_string()),
+    );
+    edit(&missing, |m| m.insert_credential(credential("github")));
+    assert!(matches!(missing.fetch(), Err(AppError::GitError(_))));
+    assert!(matches!(
+        sync::sync_git(&missing, PASSWORD),
+        Err(AppError::GitError(_))
+    ));
+
+    // 空のリモートは同期する内容がないだけ
+    let empty = GitStorage::new(root.join("empty"), Some(bare_repo(&root)));
+    edit(&empty, |m| m.insert_credential(credential("github")));
+    assert!(!empty.fetch().unwrap());
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn rejected_pushes_are_retried_after_merging() {
+    let root = temp_dir("rejected");
+    let remote = bare_repo(&root);
+    let laptop = GitStorage::new(root.join("laptop"), Some(remote.clone()));
+    let desktop = GitStorage::new(root.join("desktop"), Some(remote));
+
+    edit(&laptop, |m| m.insert_credential(credential("github")));
+    sync::sync_git(&laptop, PASSWORD).unwrap();
+    sync::sync_git(&desktop, PASSWORD).unwrap();
+    edit(&desktop, |m| m.insert_credential(credential("aws")));
+    sync::sync_git(&desktop, PASSWORD).unwrap();
+
+    edit(&laptop, |m| m.insert_credential(credential("gitlab")));
+    assert!(matches!(laptop.push(), Err(AppError::StorageConflict)));
+    assert!(sync::sync_git(&laptop, PASSWORD).unwrap().pushed);
+    assert_eq!(names(&laptop), ["aws", "github", "gitlab"]);
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn declined_pushes_are_reported() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let root = temp_dir("declined");
+    let remote = bare_repo(&root);
+    let hook = Path::new(&remote).join("hooks").join("pre-receive");
+    std::fs::write(&hook, "#!/bin/sh\nexit 1\n").unwrap();
+    std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let laptop = GitStorage::new(root.join("laptop"), Some(remote));
+    edit(&laptop, |m| m.insert_credential(credential("github")));
+    assert!(matches!(
+        sync::sync_git(&laptop, PASSWORD),
+        Err(AppError::GitError(_))
+    ));
+    std::fs::remove_dir_all(root).unwrap();
+}
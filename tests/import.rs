@@ -0,0 +1,135 @@
+use gauth::error::AppError;
+use gauth::import::{self, ImportFormat, ImportReport};
+use gauth::otp::{self, CredentialKind, HashAlgorithm};
+
+const PASSWORD: &str = "test";
+
+fn parse(format: ImportFormat, fixture: &str, password: &str) -> Result<ImportReport, AppError> {
+    let data = std::fs::read(format!("tests/fixtures/{}", fixture)).unwrap();
+    import::importer(format).parse(&data, &mut || Ok(password.to_string()))
+}
+
+fn names(report: &ImportReport) -> Vec<&str> {
+    report.credentials.iter().map(|c| c.name.as_str()).collect()
+}
+
+fn code_at(report: &ImportReport, name: &str, timestamp: u64) -> String {
+    let credential = report.credentials.iter().find(|c| c.name == name).unwrap();
+    otp::generator(
+        credential.kind,
+        credential.params,
+        credential.secret.clone(),
+        credential.pin.clone(),
+    )
+    .unwrap()
+    .generate(timestamp)
+}
+
+fn assert_aegis(report: &ImportReport) {
+    assert_eq!(
+        names(report),
+        [
+            "Example:alice@example.com",
+            "Sha256Corp:bob",
+            "Steam:gamer",
+            "Yandex:ya",
+            "mOTP:legacy"
+        ]
+    );
+    assert_eq!(report.skipped.len(), 1);
+    assert_eq!(report.skipped[0].name, "Old:counter");
+
+    let example = &report.credentials[0];
+    assert_eq!(example.issuer.as_deref(), Some("Example"));
+    assert_eq!(example.tags, ["work"]);
+    assert_eq!(code_at(report, "Example:alice@example.com", 59), "287082");
+
+    let sha256 = &report.credentials[1];
+    assert_eq!(sha256.params.algorithm, HashAlgorithm::Sha256);
+    assert_eq!((sha256.params.digits, sha256.params.period), (8, 60));
+
+    assert_eq!(report.credentials[2].kind, CredentialKind::Steam);
+    assert_eq!(code_at(report, "Steam:gamer", 59), "PV9M4");
+    assert_eq!(code_at(report, "Yandex:ya", 1581064020), "oactmacq");
+    assert_eq!(code_at(report, "mOTP:legacy", 1165000000), "c408e1");
+}
+
+#[test]
+fn aegis_plain() {
+    assert_aegis(&parse(ImportFormat::Aegis, "aegis_plain.json", "unused").unwrap());
+}
+
+#[test]
+fn aegis_encrypted() {
+    assert_aegis(&parse(ImportFormat::Aegis, "aegis_encrypted.json", PASSWORD).unwrap());
+    assert!(matches!(
+        parse(ImportFormat::Aegis, "aegis_encrypted.json", "wrong"),
+        Err(AppError::DecryptionError)
+    ));
+}
+
+#[test]
+fn andotp_plain_and_encrypted() {
+    for fixture in ["andotp_plain.json", "andotp_encrypted.json.aes"] {
+        let report = parse(ImportFormat::Andotp, fixture, PASSWORD).unwrap();
+        assert_eq!(names(&report), ["Example:alice@example.com", "Steam:gamer"]);
+        assert_eq!(report.credentials[0].tags, ["work"]);
+        assert_eq!(code_at(&report, "Steam:gamer", 59), "PV9M4");
+        assert_eq!(report.skipped.len(), 1);
+    }
+}
+
+#[test]
+fn twofas_encrypted() {
+    let report = parse(ImportFormat::TwoFas, "twofas_encrypted.2fas", PASSWORD).unwrap();
+    assert_eq!(names(&report), ["Example:alice@example.com"]);
+    assert_eq!(report.credentials[0].tags, ["work"]);
+    assert_eq!(code_at(&report, "Example:alice@example.com", 59), "287082");
+    assert_eq!(report.skipped.len(), 1);
+}
+
+#[test]
+fn freeotp_plus() {
+    let report = parse(ImportFormat::FreeOtpPlus, "freeotp_plus.json", PASSWORD).unwrap();
+    assert_eq!(names(&report), ["Example:alice@example.com"]);
+    assert_eq!(report.credentials[0].secret.last(), Some(&200));
+    assert_eq!(report.skipped.len(), 1);
+}
+
+#[test]
+fn bitwarden() {
+    let report = parse(ImportFormat::Bitwarden, "bitwarden.json", PASSWORD).unwrap();
+    assert_eq!(
+        names(&report),
+        ["Example:alice@example.com", "Steam:gamer", "Plain"]
+    );
+    assert_eq!(report.credentials[0].tags, ["work"]);
+    assert_eq!(report.credentials[1].kind, CredentialKind::Steam);
+    let skipped: Vec<&str> = report.skipped.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(skipped, ["NoOtp:bob", "Secure note"]);
+}
+
+#[test]
+fn keepass() {
+    let report = parse(ImportFormat::Keepass, "keepass.xml", PASSWORD).unwrap();
+    assert_eq!(
+        names(&report),
+        ["Example:alice@example.com", "Legacy:ops", "Steam", "KeeOTP"]
+    );
+    assert_eq!(report.credentials[0].tags, Vec::<String>::new());
+    assert_eq!(report.credentials[1].tags, ["Work/Cloud"]);
+    assert_eq!(report.credentials[1].params.digits, 8);
+    assert_eq!(report.credentials[2].kind, CredentialKind::Steam);
+    assert_eq!(report.credentials[3].params.period, 60);
+    assert_eq!(report.skipped.len(), 1);
+    assert_eq!(report.skipped[0].name, "Bank:me");
+}
+
+#[test]
+fn onepassword() {
+    let report = parse(ImportFormat::OnePassword, "1password.csv", PASSWORD).unwrap();
+    assert_eq!(names(&report), ["Example:alice@example.com"]);
+    assert_eq!(report.credentials[0].tags, ["work", "cloud"]);
+    let skipped: Vec<&str> = report.skipped.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(skipped, ["Bank:me", "Broken:x"]);
+}
@@ -0,0 +1,222 @@
+use gauth::error::AppError;
+use gauth::otp::{self, CredentialKind, HashAlgorithm, OtpParams};
+
+const RFC6238_SECRET: &[u8] = b"12345678901234567890";
+
+fn code(kind: CredentialKind, secret: &[u8], pin: Option<&str>, timestamp: u64) -> String {
+    otp::generator(
+        kind,
+        OtpParams::default(),
+        secret.to_vec(),
+        pin.map(str::to_string),
+    )
+    .unwrap()
+    .generate(timestamp)
+}
+
+/// RFC 6238 Appendix B (SHA1) の下位6桁
+#[test]
+fn totp_rfc6238_vectors() {
+    let cases = [
+        (59, "287082"),
+        (1111111109, "081804"),
+        (1111111111, "050471"),
+        (1234567890, "005924"),
+        (2000000000, "279037"),
+        (20000000000, "353130"),
+    ];
+    for (timestamp, expected) in cases {
+        assert_eq!(
+            code(CredentialKind::Totp, RFC6238_SECRET, None, timestamp),
+            expected
+        );
+    }
+}
+
+#[test]
+fn steam_vectors() {
+    let cases = [(59, "PV9M4"), (1111111109, "PY4YB"), (2000000000, "9N776")];
+    for (timestamp, expected) in cases {
+        assert_eq!(
+            code(CredentialKind::Steam, RFC6238_SECRET, None, timestamp),
+            expected
+        );
+    }
+}
+
+#[test]
+fn steam_uris_select_the_steam_kind() {
+    for uri in [
+        "steam://GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ",
+        "steam://gezdgnbvgy3tqojqgezdgnbvgy3tqojq",
+        "otpauth://steam/Steam:gamer?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&issuer=Steam",
+    ] {
+        assert!(otp::is_uri(uri), "{}", uri);
+        let parsed = otp::parse_uri(uri).unwrap();
+        assert_eq!(parsed.kind, CredentialKind::Steam, "{}", uri);
+        assert_eq!(parsed.secret, RFC6238_SECRET, "{}", uri);
+        assert_eq!(parsed.issuer.as_deref(), Some("Steam"), "{}", uri);
+        assert_eq!(code(parsed.kind, &parsed.secret, None, 59), "PV9M4");
+    }
+    assert!(!otp::is_uri("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ"));
+    assert!(matches!(
+        otp::parse_uri("steam://not-base32!"),
+        Err(AppError::InvalidKey)
+    ));
+}
+
+#[test]
+fn plain_otpauth_uris_stay_totp() {
+    let parsed =
+        otp::parse_uri("otpauth://totp/Example:alice?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ")
+            .unwrap();
+    assert_eq!(parsed.kind, CredentialKind::Totp);
+    assert_eq!(parsed.name.as_deref(), Some("alice"));
+    assert_eq!(CredentialKind::default(), CredentialKind::Totp);
+}
+
+#[test]
+fn only_six_digit_codes_are_grouped() {
+    assert_eq!(otp::format_code("287082"), "287 082");
+    assert_eq!(otp::format_code("PV9M4"), "PV9M4");
+    assert_eq!(otp::format_code("94287082"), "94287082");
+    assert_eq!(otp::format_code("c408e1"), "c408e1");
+}
+
+#[test]
+fn motp_vectors() {
+    let cases = [
+        ("e3152afee62599c8", "1234", 1165000000, "c408e1"),
+        ("e3152afee62599c8", "1234", 1600000005, "a8fcc0"),
+        ("0123456789abcdef", "9876", 1700000000, "af5895"),
+    ];
+    for (secret, pin, timestamp, expected) in cases {
+        assert_eq!(
+            code(
+                CredentialKind::Motp,
+                secret.as_bytes(),
+                Some(pin),
+                timestamp
+            ),
+            expected
+        );
+    }
+}
+
+#[test]
+fn yandex_vectors() {
+    let cases = [
+        (
+            "5239",
+            "6SB2IKNM6OBZPAVBVTOHDKS4FAAAAAAADFUTQMBTRY",
+            1641559648,
+            "umozdicq",
+        ),
+        (
+            "7586",
+            "LA2V6KMCGYMWWVEW64RNP3JA3IAAAAAAHTSG4HRZPI",
+            1581064020,
+            "oactmacq",
+        ),
+        (
+            "7586",
+            "LA2V6KMCGYMWWVEW64RNP3JA3IAAAAAAHTSG4HRZPI",
+            1581090810,
+            "wemdwrix",
+        ),
+        (
+            "5210481216086702",
+            "JBGSAU4G7IEZG6OY4UAXX62JU4AAAAAAHTSG4HXU3M",
+            1581091469,
+            "dfrpywob",
+        ),
+        (
+            "5210481216086702",
+            "JBGSAU4G7IEZG6OY4UAXX62JU4AAAAAAHTSG4HXU3M",
+            1581093059,
+            "vunyprpd",
+        ),
+    ];
+    for (pin, secret, timestamp, expected) in cases {
+        let secret = base32::decode(base32::Alphabet::Rfc4648 { padding: false }, secret).unwrap();
+        assert_eq!(
+            code(CredentialKind::Yandex, &secret, Some(pin), timestamp),
+            expected
+        );
+    }
+}
+
+#[test]
+fn pin_is_required() {
+    let params = OtpParams::default();
+    assert!(
+        otp::generator(
+            CredentialKind::Motp,
+            params,
+            b"e3152afee62599c8".to_vec(),
+            None
+        )
+        .is_err()
+    );
+    assert!(otp::generator(CredentialKind::Yandex, params, vec![0; 16], None).is_err());
+}
+
+#[test]
+fn keys_are_normalized_before_decoding() {
+    // "12345678901234567890" の Base32 表記
+    let canonical = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+    for key in [
+        canonical,
+        "gezd gnbv gy3t qojq gezd gnbv gy3t qojq",
+        "GEZD-GNBV-GY3T-QOJQ-GEZD-GNBV-GY3T-QOJQ",
+        "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ====",
+    ] {
+        let normalized = otp::normalize_base32(key).unwrap();
+        assert_eq!(normalized.secret, RFC6238_SECRET, "{}", key);
+        assert!(normalized.corrections.is_empty());
+    }
+
+    // 0 と 1 は O と I として読み替え、位置を報告する
+    let normalized = otp::normalize_base32("GEZDGNBVGY3TQ0JQGEZDGNBVGY3TQOJQ").unwrap();
+    assert_eq!(normalized.secret, RFC6238_SECRET);
+    assert_eq!(
+        normalized.corrections,
+        [otp::KeyCorrection {
+            position: 14,
+            from: '0',
+            to: 'O'
+        }]
+    );
+    let normalized = otp::normalize_base32("1NBU").unwrap();
+    assert_eq!(normalized.corrections[0].to, 'I');
+}
+
+#[test]
+fn invalid_keys_report_the_position() {
+    let error = otp::normalize_base32("GEZD GNB8").unwrap_err();
+    assert!(matches!(&error, AppError::InvalidInput(_)));
+    assert!(error.to_string().contains("'8' at position 9"));
+    assert!(
+        otp::normalize_base32("GEZD=GNB")
+            .unwrap_err()
+            .to_string()
+            .contains("position 6")
+    );
+    // 1文字足りない
+    assert!(otp::normalize_base32("GEZDGNBVG").is_err());
+    assert!(otp::normalize_base32(" - ").is_err());
+}
+
+#[test]
+fn short_seeds_are_rejected_per_algorithm() {
+    let sha1 = OtpParams::default();
+    let sha256 = OtpParams {
+        algorithm: HashAlgorithm::Sha256,
+        ..sha1
+    };
+    assert!(otp::check_secret_length(CredentialKind::Totp, sha1, &[0; 10]).is_ok());
+    assert!(otp::check_secret_length(CredentialKind::Totp, sha1, &[0; 5]).is_err());
+    assert!(otp::check_secret_length(CredentialKind::Totp, sha256, &[0; 10]).is_err());
+    assert!(otp::check_secret_length(CredentialKind::Totp, sha256, &[0; 32]).is_ok());
+    assert!(otp::check_secret_length(CredentialKind::Yandex, sha1, &[0; 10]).is_err());
+}
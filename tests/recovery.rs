@@ -0,0 +1,115 @@
+use gauth::error::AppError;
+use gauth::otp::CredentialKind;
+use gauth::random::SeededRandom;
+use gauth::recovery::{Share, combine_shares, shamir, split_key};
+use gauth::secrets::vault::OWNER;
+use gauth::secrets::{Credential, SecretManager};
+use gauth::storage::{MemoryStorage, Storage};
+use std::sync::Arc;
+
+fn saved_vault(password: &str) -> (MemoryStorage, SecretManager) {
+    let storage = MemoryStorage::new();
+    let mut manager = SecretManager::load_from(Arc::new(storage.clone()), password).unwrap();
+    manager.insert_credential(Credential::new(
+        "example".into(),
+        CredentialKind::Totp,
+        b"12345678901234567890".to_vec(),
+    ));
+    manager.save_secrets(password).unwrap();
+    (storage, manager)
+}
+
+#[test]
+fn any_threshold_subset_restores_the_secret() {
+    let secret: Vec<u8> = (0..32).collect();
+    let shares = shamir::split(&secret, 3, 5, &SeededRandom::new(1)).unwrap();
+    assert_eq!(shares.len(), 5);
+
+    for subset in [[0, 1, 2], [0, 2, 4], [4, 3, 1], [1, 2, 3]] {
+        let picked: Vec<_> = subset.iter().map(|&i| shares[i].clone()).collect();
+        assert_eq!(shamir::combine(&picked).unwrap(), secret);
+    }
+    // threshold に満たない分散片では元に戻らない
+    assert_ne!(shamir::combine(&shares[..2]).unwrap(), secret);
+}
+
+#[test]
+fn invalid_parameters_are_rejected() {
+    assert!(shamir::split(b"secret", 0, 3, &SeededRandom::new(1)).is_err());
+    assert!(shamir::split(b"secret", 4, 3, &SeededRandom::new(1)).is_err());
+    let shares = shamir::split(b"secret", 2, 3, &SeededRandom::new(1)).unwrap();
+    let duplicated = [shares[0].clone(), shares[0].clone()];
+    assert!(shamir::combine(&duplicated).is_err());
+}
+
+#[test]
+fn shares_round_trip_and_detect_typos() {
+    let shares = split_key(&[7u8; 32], 3, 2, &SeededRandom::new(2)).unwrap();
+    let encoded = shares[1].encode();
+    assert!(encoded.starts_with("GAUTH-SHARE-2-2-"));
+    assert_eq!(Share::parse(&encoded).unwrap(), shares[1]);
+    assert_eq!(
+        Share::parse(&format!("  {}  ", encoded.to_lowercase())).unwrap(),
+        shares[1]
+    );
+
+    // 1文字だけ写し間違えた分散片
+    let position = "GAUTH-SHARE-2-2-".len() + 10;
+    let mut typo = encoded.into_bytes();
+    typo[position] = if typo[position] == b'A' { b'B' } else { b'A' };
+    assert!(matches!(
+        Share::parse(&String::from_utf8(typo).unwrap()),
+        Err(AppError::RecoveryError(_))
+    ));
+}
+
+#[test]
+fn shares_from_different_splits_are_not_mixed() {
+    let first = split_key(&[1u8; 32], 3, 2, &SeededRandom::new(2)).unwrap();
+    let second = split_key(&[2u8; 32], 3, 2, &SeededRandom::new(3)).unwrap();
+    assert!(combine_shares(&[first[0].clone(), second[1].clone()]).is_err());
+    assert!(combine_shares(&first[..1]).is_err());
+    assert_eq!(
+        combine_shares(&[first[2].clone(), first[0].clone()]).unwrap(),
+        [1u8; 32]
+    );
+}
+
+#[test]
+fn combined_key_restores_access_with_a_new_password() {
+    let (storage, manager) = saved_vault("lost-password");
+    let shares = split_key(
+        &manager.recovery_key().unwrap(),
+        5,
+        3,
+        &*manager.sources().random,
+    )
+    .unwrap();
+    let data_key =
+        combine_shares(&[shares[4].clone(), shares[1].clone(), shares[2].clone()]).unwrap();
+
+    let mut recovered = SecretManager::recover_from(Arc::new(storage.clone()), data_key).unwrap();
+    assert!(recovered.get_credential("example").is_some());
+    recovered
+        .set_member_password(OWNER, "new-password")
+        .unwrap();
+    recovered
+        .save_with_message("new-password", "Recover access for owner")
+        .unwrap();
+
+    let reopened = SecretManager::load_from(Arc::new(storage.clone()), "new-password").unwrap();
+    assert!(reopened.get_credential("example").is_some());
+    assert_eq!(reopened.members().len(), 1);
+    assert!(SecretManager::load_from(Arc::new(storage), "lost-password").is_err());
+}
+
+#[test]
+fn wrong_key_does_not_touch_the_vault() {
+    let (storage, _) = saved_vault("hunter2");
+    let before = storage.read().unwrap().unwrap();
+    assert!(matches!(
+        SecretManager::recover_from(Arc::new(storage.clone()), [9u8; 32]),
+        Err(AppError::DecryptionError)
+    ));
+    assert_eq!(storage.read().unwrap().unwrap(), before);
+}
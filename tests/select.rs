@@ -0,0 +1,85 @@
+use gauth::error::AppError;
+use gauth::otp::CredentialKind;
+use gauth::secrets::Credential;
+use gauth::select;
+
+fn credential(name: &str, tags: &[&str]) -> Credential {
+    let mut credential = Credential::new(
+        name.into(),
+        CredentialKind::Totp,
+        b"12345678901234567890".to_vec(),
+    );
+    credential.tags = tags.iter().map(|tag| tag.to_string()).collect();
+    credential
+}
+
+fn fixtures() -> Vec<Credential> {
+    vec![
+        credential("GitHub", &["work", "dev"]),
+        credential("gitlab", &["Dev"]),
+        credential("aws-prod", &["work"]),
+        credential("aws-staging", &[]),
+        credential("google", &[]),
+    ]
+}
+
+#[test]
+fn exact_case_insensitive_and_prefix() {
+    let credentials = fixtures();
+    let credentials: Vec<&Credential> = credentials.iter().collect();
+    assert_eq!(select::matches(&credentials, "GitHub"), ["GitHub"]);
+    assert_eq!(select::matches(&credentials, "github"), ["GitHub"]);
+    assert_eq!(select::matches(&credentials, "GOO"), ["google"]);
+    assert_eq!(select::matches(&credentials, "git"), ["GitHub", "gitlab"]);
+    assert!(select::matches(&credentials, "bitbucket").is_empty());
+}
+
+#[test]
+fn globs_and_tags() {
+    let credentials = fixtures();
+    let credentials: Vec<&Credential> = credentials.iter().collect();
+    assert_eq!(
+        select::matches(&credentials, "aws-*"),
+        ["aws-prod", "aws-staging"]
+    );
+    assert_eq!(select::matches(&credentials, "*-prod"), ["aws-prod"]);
+    assert_eq!(select::matches(&credentials, "git?ab"), ["gitlab"]);
+    assert_eq!(select::matches(&credentials, "*o*g*"), ["google"]);
+    assert_eq!(select::matches(&credentials, "*ing"), ["aws-staging"]);
+    assert_eq!(
+        select::matches(&credentials, "aws-"),
+        ["aws-prod", "aws-staging"]
+    );
+    assert!(select::matches(&credentials, "aws?").is_empty());
+
+    assert_eq!(
+        select::matches(&credentials, "tag:work"),
+        ["GitHub", "aws-prod"]
+    );
+    assert_eq!(
+        select::matches(&credentials, "TAG:dev"),
+        ["GitHub", "gitlab"]
+    );
+    assert!(select::matches(&credentials, "tag:home").is_empty());
+}
+
+#[test]
+fn several_selectors_keep_order_without_duplicates() {
+    let credentials = fixtures();
+    let credentials: Vec<&Credential> = credentials.iter().collect();
+    let selectors = ["google".to_string(), "tag:work".into(), "aws-*".into()];
+    assert_eq!(
+        select::all(&credentials, &selectors).unwrap(),
+        ["google", "GitHub", "aws-prod", "aws-staging"]
+    );
+    assert!(matches!(
+        select::all(&credentials, &["google".to_string(), "missing".into()]),
+        Err(AppError::CredentialNotFound(name)) if name == "missing"
+    ));
+
+    assert_eq!(select::one(&credentials, "goo").unwrap(), "google");
+    assert!(matches!(
+        select::one(&credentials, "missing"),
+        Err(AppError::CredentialNotFound(_))
+    ));
+}
@@ -0,0 +1,157 @@
+use gauth::Vault;
+use gauth::auth::{self, ShowEnd, ShowLimit, Wait};
+use gauth::otp::CredentialKind;
+use gauth::random::SeededRandom;
+use gauth::secrets::{Credential, Sources};
+use gauth::storage::MemoryStorage;
+use gauth::time::FixedClock;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 固定の時計で開き、`names` の認証情報を登録した保管庫
+fn vault_at(clock: &Arc<FixedClock>, names: &[&str]) -> Vault {
+    let sources = Sources {
+        clock: Arc::clone(clock) as _,
+        random: Arc::new(SeededRandom::new(1)),
+    };
+    let mut vault =
+        Vault::open_with_sources(Arc::new(MemoryStorage::new()), "hunter2", sources).unwrap();
+    for name in names {
+        vault
+            .add(Credential::new(
+                name.to_string(),
+                CredentialKind::Totp,
+                b"12345678901234567890".to_vec(),
+            ))
+            .unwrap();
+    }
+    vault
+}
+
+/// 待機のたびに時計を進めながらバーなしで表示し、終えた理由・出力した行・待機の回数を返す
+///
+/// `interrupt_after` 回目の待機で Ctrl-C を受け取ったことにする。
+fn watch(
+    names: &[&str],
+    limit: ShowLimit,
+    interrupt_after: Option<usize>,
+) -> (ShowEnd, Vec<String>, usize) {
+    let clock = Arc::new(FixedClock::new(1111111109));
+    let vault = vault_at(&clock, names);
+    let names = names.iter().map(|name| name.to_string()).collect();
+    let mut out = Vec::new();
+    let mut waits = 0;
+    let mut wait = |duration: Duration| {
+        waits += 1;
+        if Some(waits) == interrupt_after {
+            return Wait::Interrupted;
+        }
+        clock.advance(duration.as_secs());
+        Wait::Elapsed
+    };
+    let end = auth::watch(&vault, names, limit, None, &mut out, &mut wait).unwrap();
+    let lines = String::from_utf8(out)
+        .unwrap()
+        .lines()
+        .map(str::to_string)
+        .collect();
+    (end, lines, waits)
+}
+
+#[test]
+fn limits_from_flags() {
+    assert_eq!(ShowLimit::from_args(false, None, false), ShowLimit::Forever);
+    assert_eq!(ShowLimit::from_args(true, None, false), ShowLimit::Codes(1));
+    assert_eq!(
+        ShowLimit::from_args(false, Some(3), false),
+        ShowLimit::Codes(3)
+    );
+    assert_eq!(ShowLimit::from_args(false, None, true), ShowLimit::Codes(2));
+
+    assert!(!ShowLimit::Forever.reached(u64::MAX));
+    assert!(!ShowLimit::Codes(2).reached(1));
+    assert!(ShowLimit::Codes(2).reached(2));
+}
+
+#[test]
+fn waits_until_the_next_second_boundary() {
+    assert_eq!(
+        auth::until_next_second(1_700_000_000_250),
+        Duration::from_millis(750)
+    );
+    assert_eq!(
+        auth::until_next_second(1_700_000_000_999),
+        Duration::from_millis(1)
+    );
+    // ちょうど境界にいる場合は次の秒まで待つ
+    assert_eq!(
+        auth::until_next_second(1_700_000_001_000),
+        Duration::from_secs(1)
+    );
+}
+
+#[test]
+fn plain_output_previews_the_next_code() {
+    let storage = Arc::new(MemoryStorage::new());
+    let mut vault =
+        Vault::open_with_sources(storage, "hunter2", Sources::fixed(1111111109, 0)).unwrap();
+    vault
+        .add(Credential::new(
+            "example".into(),
+            CredentialKind::Totp,
+            b"12345678901234567890".to_vec(),
+        ))
+        .unwrap();
+    // オフセットなしの固定の時計はミリ秒でも秒の境界に揃っている
+    assert_eq!(vault.now_millis().unwrap(), 1111111109000);
+
+    let current = vault.code_at("example", 1111111109).unwrap();
+    let next = vault
+        .code_at("example", 1111111109 + current.remaining)
+        .unwrap();
+    assert_eq!(
+        auth::plain_line("example", &current, &next.code),
+        format!("example: 081804 (1s left, next {})", next.code)
+    );
+}
+
+#[test]
+fn once_prints_a_single_code_without_waiting() {
+    let (end, lines, waits) = watch(&["example"], ShowLimit::Codes(1), None);
+    assert_eq!(end, ShowEnd::Completed);
+    assert_eq!(lines, ["example: 081804 (1s left, next 050471)"]);
+    assert_eq!(waits, 0);
+}
+
+#[test]
+fn count_stops_after_that_many_code_changes() {
+    let (end, lines, waits) = watch(&["example"], ShowLimit::Codes(3), None);
+    assert_eq!(end, ShowEnd::Completed);
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("example: 081804 (1s left"));
+    assert!(lines[1].starts_with("example: 050471 (30s left"));
+    // 1秒後に最初の切り替わり、その30秒後に2回目の切り替わり
+    assert_eq!(waits, 31);
+}
+
+#[test]
+fn until_change_waits_for_every_credential_to_change() {
+    let (end, lines, waits) = watch(
+        &["example", "other"],
+        ShowLimit::from_args(false, None, true),
+        None,
+    );
+    assert_eq!(end, ShowEnd::Completed);
+    assert_eq!(lines.len(), 4);
+    assert!(lines[2].starts_with("example: 050471"));
+    assert!(lines[3].starts_with("other: 050471"));
+    assert_eq!(waits, 1);
+}
+
+#[test]
+fn interrupt_stops_showing_forever() {
+    let (end, lines, waits) = watch(&["example"], ShowLimit::Forever, Some(5));
+    assert_eq!(end, ShowEnd::Interrupted);
+    assert_eq!(lines.len(), 2);
+    assert_eq!(waits, 5);
+}
@@ -0,0 +1,126 @@
+use gauth::error::AppError;
+use gauth::otp::CredentialKind;
+use gauth::secrets::{Credential, SecretManager};
+use gauth::storage::{FileStorage, MemoryStorage, Storage};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn credential(name: &str) -> Credential {
+    Credential::new(
+        name.into(),
+        CredentialKind::Totp,
+        b"12345678901234567890".to_vec(),
+    )
+}
+
+fn temp_dir(label: &str) -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let dir =
+        std::env::temp_dir().join(format!("gauth-{}-{}-{}", label, std::process::id(), nanos));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn exercise_compare_and_swap(storage: &dyn Storage) {
+    assert!(storage.read().unwrap().is_none());
+
+    let first = storage.write(b"one", None).unwrap();
+    assert!(matches!(
+        storage.write(b"again", None),
+        Err(AppError::StorageConflict)
+    ));
+
+    let second = storage.write(b"two", Some(&first)).unwrap();
+    assert_ne!(first, second);
+    assert!(matches!(
+        storage.write(b"stale", Some(&first)),
+        Err(AppError::StorageConflict)
+    ));
+
+    let (data, version) = storage.read().unwrap().unwrap();
+    assert_eq!(data, b"two");
+    assert_eq!(version, second);
+}
+
+#[test]
+fn memory_compare_and_swap() {
+    exercise_compare_and_swap(&MemoryStorage::new());
+}
+
+#[test]
+fn file_compare_and_swap() {
+    let dir = temp_dir("cas");
+    exercise_compare_and_swap(&FileStorage::new(dir.join("credentials.toml")));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn lock_is_exclusive_until_released() {
+    let dir = temp_dir("lock");
+    let memory = MemoryStorage::new();
+    let file = FileStorage::new(dir.join("credentials.toml"));
+
+    for storage in [&memory as &dyn Storage, &file] {
+        let guard = storage.lock().unwrap();
+        assert!(matches!(storage.lock(), Err(AppError::StorageLocked(_))));
+        drop(guard);
+        drop(storage.lock().unwrap());
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn secret_manager_round_trip() {
+    let storage = MemoryStorage::new();
+    let mut manager = SecretManager::load_from(Arc::new(storage.clone()), "hunter2").unwrap();
+    manager.insert_credential(credential("example"));
+    manager.save_secrets("hunter2").unwrap();
+    manager.insert_credential(credential("second"));
+    manager.save_secrets("hunter2").unwrap();
+
+    let reloaded = SecretManager::load_from(Arc::new(storage), "hunter2").unwrap();
+    assert_eq!(reloaded.list_credentials().len(), 2);
+}
+
+#[test]
+fn concurrent_edit_is_merged() {
+    let storage = MemoryStorage::new();
+    let mut manager = SecretManager::load_from(Arc::new(storage.clone()), "hunter2").unwrap();
+    manager.insert_credential(credential("example"));
+    manager.save_secrets("hunter2").unwrap();
+
+    let mut first = SecretManager::load_from(Arc::new(storage.clone()), "hunter2").unwrap();
+    let mut second = SecretManager::load_from(Arc::new(storage.clone()), "hunter2").unwrap();
+    first.insert_credential(credential("from-first"));
+    first.save_secrets("hunter2").unwrap();
+    second.insert_credential(credential("from-second"));
+    second.save_secrets("hunter2").unwrap();
+
+    let reloaded = SecretManager::load_from(Arc::new(storage), "hunter2").unwrap();
+    assert!(reloaded.get_credential("from-first").is_some());
+    assert!(reloaded.get_credential("from-second").is_some());
+}
+
+#[test]
+fn file_storage_writes_the_vault() {
+    let dir = temp_dir("file");
+    let path = dir.join("nested").join("credentials.toml");
+    let storage = Arc::new(FileStorage::new(path.clone()));
+
+    let mut manager = SecretManager::load_from(storage.clone(), "hunter2").unwrap();
+    manager.insert_credential(credential("example"));
+    manager.save_secrets("hunter2").unwrap();
+    assert!(
+        std::fs::read_to_string(&path)
+            .unwrap()
+            .starts_with("version = 2")
+    );
+
+    let reloaded = SecretManager::load_from(storage, "hunter2").unwrap();
+    assert!(reloaded.get_credential("example").is_some());
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,463 @@
+use base64::{Engine, engine::general_purpose::STANDARD};
+use gauth::error::AppError;
+use gauth::merge::merge;
+use gauth::otp::CredentialKind;
+use gauth::secrets::{Credential, SecretManager, Snapshot};
+use gauth::storage::{MemoryStorage, Storage, WebDavStorage};
+use gauth::sync::{self, SyncStatus};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+const PASSWORD: &str = "hunter2";
+
+fn credential(name: &str, secret: &[u8]) -> Credential {
+    Credential::new(name.into(), CredentialKind::Totp, secret.to_vec())
+}
+
+fn snapshot(credentials: &[Credential]) -> Snapshot {
+    Snapshot {
+        credentials: credentials
+            .iter()
+            .map(|credential| (credential.name.clone(), credential.clone()))
+            .collect(),
+    }
+}
+
+#[test]
+fn merge_applies_one_sided_changes() {
+    let base = snapshot(&[
+        credential("a", b"aaaaaaaaaa"),
+        credential("b", b"bbbbbbbbbb"),
+    ]);
+    let local = snapshot(&[
+        credential("a", b"AAAAAAAAAA"),
+        credential("b", b"bbbbbbbbbb"),
+        credential("c", b"cccccccccc"),
+    ]);
+    let remote = snapshot(&[credential("a", b"aaaaaaaaaa")]);
+
+    let result = merge(&base, &local, &remote);
+    assert!(result.conflicts.is_empty());
+    let names: Vec<&str> = result
+        .merged
+        .credentials
+        .keys()
+        .map(String::as_str)
+        .collect();
+    assert_eq!(names, ["a", "c"]);
+    assert_eq!(result.merged.credentials["a"].secret, b"AAAAAAAAAA");
+}
+
+#[test]
+fn merge_keeps_modification_over_deletion() {
+    let base = snapshot(&[credential("a", b"aaaaaaaaaa")]);
+    let local = snapshot(&[]);
+    let remote = snapshot(&[credential("a", b"AAAAAAAAAA")]);
+
+    let result = merge(&base, &local, &remote);
+    assert!(result.conflicts.is_empty());
+    assert_eq!(result.merged.credentials["a"].secret, b"AAAAAAAAAA");
+}
+
+#[test]
+fn merge_combines_recovery_codes_used_on_both_sides() {
+    let mut base = credential("a", b"aaaaaaaaaa");
+    base.add_recovery_codes(["1111", "2222", "3333"]);
+    let mut local = base.clone();
+    local.use_recovery_code(Some("1111")).unwrap();
+    let mut remote = base.clone();
+    remote.use_recovery_code(Some("3333")).unwrap();
+
+    let result = merge(
+        &snapshot(&[base]),
+        &snapshot(&[local]),
+        &snapshot(&[remote]),
+    );
+    assert!(result.conflicts.is_empty());
+    let merged = &result.merged.credentials["a"];
+    let used: Vec<bool> = merged.recovery_codes.iter().map(|code| code.used).collect();
+    assert_eq!(used, [true, false, true]);
+    assert_eq!(merged.remaining_recovery_codes(), 1);
+}
+
+#[test]
+fn merge_keeps_both_sides_of_a_conflict() {
+    let base = snapshot(&[credential("a", b"aaaaaaaaaa")]);
+    let local = snapshot(&[credential("a", b"LLLLLLLLLL")]);
+    let remote = snapshot(&[credential("a", b"RRRRRRRRRR")]);
+
+    let result = merge(&base, &local, &remote);
+    assert_eq!(result.conflicts.len(), 1);
+    assert_eq!(result.conflicts[0].kept_as, "a (conflict)");
+    assert_eq!(result.merged.credentials["a"].secret, b"RRRRRRRRRR");
+    let kept = &result.merged.credentials["a (conflict)"];
+    assert_eq!(kept.name, "a (conflict)");
+    assert_eq!(kept.secret, b"LLLLLLLLLL");
+}
+
+#[test]
+fn merge_takes_the_latest_recorded_step() {
+    let base = snapshot(&[credential("a", b"aaaaaaaaaa")]);
+    let mut local = base.clone();
+    local.credentials.get_mut("a").unwrap().last_step = Some(10);
+    let mut remote = base.clone();
+    remote.credentials.get_mut("a").unwrap().last_step = Some(12);
+
+    let result = merge(&base, &local, &remote);
+    assert!(result.conflicts.is_empty());
+    assert_eq!(result.merged.credentials["a"].last_step, Some(12));
+}
+
+/// ETag と条件付き PUT に対応した最小限の WebDAV サーバー
+struct DavServer {
+    url: String,
+}
+
+#[derive(Default)]
+struct DavState {
+    files: HashMap<String, (Vec<u8>, u64)>,
+    revision: u64,
+}
+
+const AUTHORIZATION: &str = "Basic YWxpY2U6c2VjcmV0"; // alice:secret
+
+fn respond(stream: &mut TcpStream, status: &str, etag: Option<u64>, body: &[u8]) {
+    let mut head = format!(
+        "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        status,
+        body.len()
+    );
+    if let Some(revision) = etag {
+        head.push_str(&format!("ETag: \"{}\"\r\n", revision));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes()).unwrap();
+    stream.write_all(body).unwrap();
+}
+
+fn handle(mut stream: TcpStream, state: &Mutex<DavState>) {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).unwrap();
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+        }
+    }
+    let length: usize = headers
+        .get("content-length")
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(0);
+    let mut body = vec![0u8; length];
+    reader.read_exact(&mut body).unwrap();
+
+    if headers.get("authorization").map(String::as_str) != Some(AUTHORIZATION) {
+        return respond(&mut stream, "401 Unauthorized", None, b"");
+    }
+    let mut state = state.lock().unwrap();
+    let current = state.files.get(&path).cloned();
+    let current_etag = current
+        .as_ref()
+        .map(|(_, revision)| format!("\"{}\"", revision));
+    match method.as_str() {
+        "GET" | "HEAD" => match current {
+            Some((data, revision)) => {
+                let body = if method == "GET" { data } else { Vec::new() };
+                respond(&mut stream, "200 OK", Some(revision), &body)
+            }
+            None => respond(&mut stream, "404 Not Found", None, b""),
+        },
+        "PUT" => {
+            let matches = match (headers.get("if-match"), headers.get("if-none-match")) {
+                (Some(expected), _) => current_etag.as_ref() == Some(expected),
+                (None, Some(_)) => current.is_none(),
+                (None, None) => true,
+            };
+            if !matches {
+                return respond(&mut stream, "412 Precondition Failed", None, b"");
+            }
+            state.revision += 1;
+            let revision = state.revision;
+            state.files.insert(path, (body, revision));
+            respond(&mut stream, "201 Created", Some(revision), b"")
+        }
+        _ => respond(&mut stream, "405 Method Not Allowed", None, b""),
+    }
+}
+
+impl DavServer {
+    fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!(
+            "http://{}/gauth/credentials.toml",
+            listener.local_addr().unwrap()
+        );
+        let state = Arc::new(Mutex::new(DavState::default()));
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let state = Arc::clone(&state);
+                thread::spawn(move || handle(stream.unwrap(), &state));
+            }
+        });
+        DavServer { url }
+    }
+
+    fn storage(&self) -> WebDavStorage {
+        WebDavStorage::new(
+            self.url.clone(),
+            Some("alice".into()),
+            Some("secret".into()),
+        )
+    }
+}
+
+#[test]
+fn webdav_uses_etags_for_compare_and_swap() {
+    let server = DavServer::start();
+    let storage = server.storage();
+
+    assert!(storage.read().unwrap().is_none());
+    let first = storage.write(b"one", None).unwrap();
+    assert!(matches!(
+        storage.write(b"again", None),
+        Err(AppError::StorageConflict)
+    ));
+    let second = storage.write(b"two", Some(&first)).unwrap();
+    assert!(matches!(
+        storage.write(b"stale", Some(&first)),
+        Err(AppError::StorageConflict)
+    ));
+    let (data, version) = storage.read().unwrap().unwrap();
+    assert_eq!(data, b"two");
+    assert_eq!(version, second);
+}
+
+#[test]
+fn webdav_reports_rejected_credentials() {
+    let server = DavServer::start();
+    let storage = WebDavStorage::new(server.url.clone(), Some("alice".into()), None);
+    assert!(matches!(storage.read(), Err(AppError::SyncError(_))));
+}
+
+/// 1台の端末 (手元の保管庫と同期の記録)
+struct Machine {
+    local: MemoryStorage,
+    state: MemoryStorage,
+}
+
+impl Machine {
+    fn new() -> Self {
+        Machine {
+            local: MemoryStorage::new(),
+            state: MemoryStorage::new(),
+        }
+    }
+
+    fn edit(&self, change: impl FnOnce(&mut SecretManager)) {
+        let mut manager = SecretManager::load_from(Arc::new(self.local.clone()), PASSWORD).unwrap();
+        change(&mut manager);
+        manager.save_secrets(PASSWORD).unwrap();
+    }
+
+    fn names(&self) -> Vec<String> {
+        let manager = SecretManager::load_from(Arc::new(self.local.clone()), PASSWORD).unwrap();
+        manager
+            .credentials()
+            .iter()
+            .map(|credential| credential.name.clone())
+            .collect()
+    }
+
+    fn sync(&self, remote: &dyn Storage) -> sync::SyncReport {
+        sync::sync(&self.local, remote, &self.state, PASSWORD).unwrap()
+    }
+
+    fn status(&self, remote: &dyn Storage) -> SyncStatus {
+        sync::status(&self.local, remote, &self.state).unwrap()
+    }
+
+    fn time_offset(&self) -> i64 {
+        SecretManager::load_from(Arc::new(self.local.clone()), PASSWORD)
+            .unwrap()
+            .time_offset()
+    }
+}
+
+#[test]
+fn three_machines_converge_through_webdav() {
+    let server = DavServer::start();
+    let remote = server.storage();
+    let [laptop, desktop, server_box] = [Machine::new(), Machine::new(), Machine::new()];
+
+    laptop.edit(|m| m.insert_credential(credential("github", b"1234567890")));
+    assert_eq!(laptop.status(&remote), SyncStatus::NeverSynced);
+    assert!(laptop.sync(&remote).pushed);
+    assert_eq!(laptop.status(&remote), SyncStatus::UpToDate);
+
+    desktop.edit(|m| m.insert_credential(credential("aws", b"0987654321")));
+    let report = desktop.sync(&remote);
+    assert_eq!(report.pulled, 1);
+    assert!(report.pushed);
+    assert_eq!(laptop.status(&remote), SyncStatus::RemoteChanges);
+
+    server_box.sync(&remote);
+    assert_eq!(server_box.names(), ["aws", "github"]);
+
+    // 別々の端末で別々の認証情報を変更・削除してもマージされる
+    laptop.sync(&remote);
+    laptop.edit(|m| {
+        m.delete_credential("aws");
+    });
+    desktop.edit(|m| m.insert_credential(credential("gitlab", b"5555555555")));
+    assert_eq!(laptop.status(&remote), SyncStatus::LocalChanges);
+    desktop.sync(&remote);
+    assert_eq!(laptop.status(&remote), SyncStatus::Diverged);
+    let report = laptop.sync(&remote);
+    assert!(report.conflicts.is_empty());
+
+    for machine in [&laptop, &desktop, &server_box] {
+        machine.sync(&remote);
+        assert_eq!(machine.names(), ["github", "gitlab"]);
+        assert_eq!(machine.status(&remote), SyncStatus::UpToDate);
+    }
+}
+
+#[test]
+fn concurrent_save_merges_instead_of_overwriting() {
+    let server = DavServer::start();
+    let remote: Arc<dyn Storage> = Arc::new(server.storage());
+
+    let mut first = SecretManager::load_from(Arc::clone(&remote), PASSWORD).unwrap();
+    first.insert_credential(credential("github", b"1234567890"));
+    first.save_secrets(PASSWORD).unwrap();
+
+    let mut a = SecretManager::load_from(Arc::clone(&remote), PASSWORD).unwrap();
+    let mut b = SecretManager::load_from(Arc::clone(&remote), PASSWORD).unwrap();
+    a.insert_credential(credential("aws", b"0987654321"));
+    a.save_secrets(PASSWORD).unwrap();
+    b.delete_credential("github");
+    b.save_secrets(PASSWORD).unwrap();
+
+    let merged = SecretManager::load_from(remote, PASSWORD).unwrap();
+    let names: Vec<&str> = merged
+        .credentials()
+        .iter()
+        .map(|credential| credential.name.as_str())
+        .collect();
+    assert_eq!(names, ["aws"]);
+}
+
+#[test]
+fn time_offset_stays_on_each_machine() {
+    let remote = MemoryStorage::new();
+    let [laptop, desktop] = [Machine::new(), Machine::new()];
+    laptop.edit(|m| m.insert_credential(credential("github", b"1234567890")));
+    laptop.sync(&remote);
+    desktop.sync(&remote);
+
+    laptop.edit(|m| m.set_time_offset(-5));
+    desktop.edit(|m| m.set_time_offset(12));
+    // 時計のずれだけの変更は同期先に書き込まない
+    assert!(!laptop.sync(&remote).pushed);
+    assert!(!desktop.sync(&remote).pushed);
+    assert_eq!(laptop.status(&remote), SyncStatus::UpToDate);
+
+    // 認証情報の変更を取り込んでも、それぞれの端末の値のまま
+    desktop.edit(|m| m.insert_credential(credential("aws", b"0987654321")));
+    assert!(desktop.sync(&remote).pushed);
+    assert_eq!(laptop.sync(&remote).pulled, 1);
+    assert!(!laptop.sync(&remote).pushed);
+    assert_eq!(laptop.names(), ["aws", "github"]);
+    assert_eq!(laptop.time_offset(), -5);
+    assert_eq!(desktop.time_offset(), 12);
+}
+
+#[test]
+fn sync_carries_member_removals_to_other_machines() {
+    let remote = MemoryStorage::new();
+    let [laptop, desktop] = [Machine::new(), Machine::new()];
+    laptop.edit(|m| {
+        m.insert_credential(credential("github", b"1234567890"));
+        m.add_member_password("bob", "bob-pw", PASSWORD).unwrap();
+    });
+    laptop.sync(&remote);
+    desktop.sync(&remote);
+    assert!(SecretManager::load_from(Arc::new(desktop.local.clone()), "bob-pw").is_ok());
+
+    // laptop で bob を削除 (鍵を入れ替え) し、desktop では別の変更をしてから同期する
+    laptop.edit(|m| m.remove_member("bob", PASSWORD).unwrap());
+    laptop.sync(&remote);
+    desktop.edit(|m| m.insert_credential(credential("aws", b"0987654321")));
+    let report = desktop.sync(&remote);
+    assert!(report.pushed);
+
+    for storage in [&remote, &desktop.local] {
+        assert!(matches!(
+            SecretManager::load_from(Arc::new(storage.clone()), "bob-pw"),
+            Err(AppError::WrongMasterPassword)
+        ));
+    }
+    laptop.sync(&remote);
+    assert_eq!(laptop.names(), ["aws", "github"]);
+}
+
+#[test]
+fn sync_refuses_member_changes_on_both_sides() {
+    let remote = MemoryStorage::new();
+    let [laptop, desktop] = [Machine::new(), Machine::new()];
+    laptop.edit(|m| m.insert_credential(credential("github", b"1234567890")));
+    laptop.sync(&remote);
+    desktop.sync(&remote);
+
+    laptop.edit(|m| m.add_member_password("bob", "bob-pw", PASSWORD).unwrap());
+    laptop.sync(&remote);
+    desktop.edit(|m| {
+        m.add_member_password("carol", "carol-pw", PASSWORD)
+            .unwrap()
+    });
+    assert!(matches!(
+        sync::sync(&desktop.local, &remote, &desktop.state, PASSWORD),
+        Err(AppError::VaultKeyChanged)
+    ));
+}
+
+#[test]
+fn unreadable_sync_base_is_reported() {
+    let remote = MemoryStorage::new();
+    let laptop = Machine::new();
+    laptop.edit(|m| m.insert_credential(credential("github", b"1234567890")));
+    laptop.sync(&remote);
+
+    // 別のパスワードで暗号化された基準は、基準なしとして扱わずにエラーにする
+    let other = MemoryStorage::new();
+    let mut manager = SecretManager::load_from(Arc::new(other.clone()), "other-pw").unwrap();
+    manager.save_secrets("other-pw").unwrap();
+    let (base, _) = other.read().unwrap().unwrap();
+    let (_, version) = laptop.state.read().unwrap().unwrap();
+    let state = sync::SyncState {
+        remote_version: None,
+        base: Some(STANDARD.encode(base)),
+    };
+    laptop
+        .state
+        .write(toml::to_string(&state).unwrap().as_bytes(), Some(&version))
+        .unwrap();
+    assert!(matches!(
+        sync::sync(&laptop.local, &remote, &laptop.state, PASSWORD),
+        Err(AppError::SyncError(message)) if message.contains("cannot be decrypted")
+    ));
+}
@@ -0,0 +1,162 @@
+use gauth::error::AppError;
+use gauth::otp::CredentialKind;
+use gauth::random::OsRandom;
+use gauth::secrets::vault::{Identity, MemberKind, OWNER};
+use gauth::secrets::{Credential, SecretManager};
+use gauth::storage::MemoryStorage;
+use std::sync::Arc;
+
+fn shared_vault() -> (MemoryStorage, SecretManager) {
+    let storage = MemoryStorage::new();
+    let mut manager = SecretManager::load_from(Arc::new(storage.clone()), "owner-pw").unwrap();
+    manager.insert_credential(Credential::new(
+        "example".into(),
+        CredentialKind::Totp,
+        b"12345678901234567890".to_vec(),
+    ));
+    manager.save_secrets("owner-pw").unwrap();
+    (storage, manager)
+}
+
+fn open(storage: &MemoryStorage, secret: &str) -> Result<SecretManager, AppError> {
+    SecretManager::load_from(Arc::new(storage.clone()), secret)
+}
+
+#[test]
+fn password_member_can_open_the_vault() {
+    let (storage, mut manager) = shared_vault();
+    manager
+        .add_member_password("bob", "bob-pw", "owner-pw")
+        .unwrap();
+    manager
+        .save_with_message("owner-pw", "Add member bob")
+        .unwrap();
+
+    let bob = open(&storage, "bob-pw").unwrap();
+    assert!(bob.get_credential("example").is_some());
+    let names: Vec<String> = bob.members().into_iter().map(|m| m.name).collect();
+    assert_eq!(names, [OWNER, "bob"]);
+}
+
+#[test]
+fn public_key_member_can_open_the_vault() {
+    let (storage, mut manager) = shared_vault();
+    let identity = Identity::generate(&OsRandom);
+    manager
+        .add_member_public_key("carol", &identity.public_key(), "owner-pw")
+        .unwrap();
+    manager.save_secrets("owner-pw").unwrap();
+
+    let mut carol = open(&storage, &identity.encode()).unwrap();
+    assert!(carol.get_credential("example").is_some());
+    assert_eq!(
+        carol.members()[1].kind,
+        MemberKind::PublicKey(identity.public_key())
+    );
+
+    // 公開鍵のメンバーも変更を保存できる
+    carol.delete_credential("example");
+    carol.save_secrets(&identity.encode()).unwrap();
+    assert!(
+        open(&storage, "owner-pw")
+            .unwrap()
+            .get_credential("example")
+            .is_none()
+    );
+}
+
+#[test]
+fn removed_member_is_locked_out() {
+    let (storage, mut manager) = shared_vault();
+    let identity = Identity::generate(&OsRandom);
+    manager
+        .add_member_password("bob", "bob-pw", "owner-pw")
+        .unwrap();
+    manager
+        .add_member_public_key("carol", &identity.public_key(), "owner-pw")
+        .unwrap();
+    manager.save_secrets("owner-pw").unwrap();
+
+    manager.remove_member("bob", "owner-pw").unwrap();
+    manager.save_secrets("owner-pw").unwrap();
+
+    assert!(matches!(
+        open(&storage, "bob-pw"),
+        Err(AppError::WrongMasterPassword)
+    ));
+    assert!(open(&storage, "owner-pw").is_ok());
+    let carol = open(&storage, &identity.encode()).unwrap();
+    assert!(carol.get_credential("example").is_some());
+}
+
+#[test]
+fn member_changes_are_validated() {
+    let (_, mut manager) = shared_vault();
+    assert!(
+        manager
+            .add_member_password(OWNER, "other", "owner-pw")
+            .is_err()
+    );
+    assert!(
+        manager
+            .add_member_public_key("dave", "gauth-pk-invalid", "owner-pw")
+            .is_err()
+    );
+    assert!(manager.remove_member("nobody", "owner-pw").is_err());
+    // 自分自身のスロットは削除できない
+    assert!(manager.remove_member(OWNER, "owner-pw").is_err());
+}
+
+#[test]
+fn concurrent_save_does_not_undo_a_member_removal() {
+    let (storage, mut manager) = shared_vault();
+    manager
+        .add_member_password("bob", "bob-pw", "owner-pw")
+        .unwrap();
+    manager.save_secrets("owner-pw").unwrap();
+
+    // 別のプロセスが読み込んだ後で bob を削除して鍵を入れ替える
+    let mut other = open(&storage, "owner-pw").unwrap();
+    manager.remove_member("bob", "owner-pw").unwrap();
+    manager.save_secrets("owner-pw").unwrap();
+
+    other.insert_credential(Credential::new(
+        "second".into(),
+        CredentialKind::Totp,
+        b"12345678901234567890".to_vec(),
+    ));
+    assert!(matches!(
+        other.save_secrets("owner-pw"),
+        Err(AppError::VaultKeyChanged)
+    ));
+    assert!(matches!(
+        open(&storage, "bob-pw"),
+        Err(AppError::WrongMasterPassword)
+    ));
+
+    // 読み込み直せば変更を保存できる
+    let mut other = open(&storage, "owner-pw").unwrap();
+    other.insert_credential(Credential::new(
+        "second".into(),
+        CredentialKind::Totp,
+        b"12345678901234567890".to_vec(),
+    ));
+    other.save_secrets("owner-pw").unwrap();
+    assert!(open(&storage, "bob-pw").is_err());
+}
+
+#[test]
+fn concurrent_save_keeps_a_new_member() {
+    let (storage, mut manager) = shared_vault();
+    let mut other = open(&storage, "owner-pw").unwrap();
+    manager
+        .add_member_password("bob", "bob-pw", "owner-pw")
+        .unwrap();
+    manager.save_secrets("owner-pw").unwrap();
+
+    other.delete_credential("example");
+    other.save_secrets("owner-pw").unwrap();
+
+    let bob = open(&storage, "bob-pw").unwrap();
+    assert!(bob.get_credential("example").is_none());
+}
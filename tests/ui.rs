@@ -0,0 +1,160 @@
+use gauth::otp::CredentialKind;
+use gauth::random::SeededRandom;
+use gauth::secrets::{Credential, SecretManager, Sources};
+use gauth::time::{Clock, FixedClock};
+use gauth::ui::{App, render};
+use ratatui::Terminal;
+use ratatui::backend::TestBackend;
+use ratatui::buffer::Buffer;
+use ratatui::style::Color;
+use std::path::Path;
+use std::sync::Arc;
+
+const SECRET: &[u8] = b"12345678901234567890";
+
+/// 指定した時刻で止まった時計を持つ App
+fn app(names: &[&str], timestamp: u64) -> App {
+    let mut manager = SecretManager::with_sources(Sources::fixed(timestamp, 1));
+    for name in names {
+        manager.insert_credential(Credential::new(
+            name.to_string(),
+            CredentialKind::Totp,
+            SECRET.to_vec(),
+        ));
+    }
+    App::new(manager).unwrap()
+}
+
+fn draw(app: &mut App, width: u16, height: u16) -> Buffer {
+    let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+    terminal.draw(|f| render(f, app)).unwrap();
+    terminal.backend().buffer().clone()
+}
+
+/// 画面の文字だけを行ごとに取り出す
+fn text(buffer: &Buffer) -> String {
+    let area = buffer.area;
+    (area.top()..area.bottom())
+        .map(|y| {
+            let line: String = (area.left()..area.right())
+                .map(|x| buffer[(x, y)].symbol())
+                .collect();
+            line.trim_end().to_string() + "\n"
+        })
+        .collect()
+}
+
+/// tests/fixtures/ui/<name>.txt と比較する (UPDATE_SNAPSHOTS=1 で書き直す)
+fn assert_snapshot(name: &str, buffer: &Buffer) {
+    let path = Path::new("tests/fixtures/ui").join(format!("{}.txt", name));
+    let actual = text(buffer);
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, &actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(actual, expected, "snapshot {} differs", name);
+}
+
+/// 文字列が最初に現れるセルの位置
+fn find(buffer: &Buffer, needle: &str) -> Option<(u16, u16)> {
+    let area = buffer.area;
+    (area.top()..area.bottom()).find_map(|y| {
+        let line: String = (area.left()..area.right())
+            .map(|x| buffer[(x, y)].symbol())
+            .collect();
+        let column = line.find(needle)?;
+        Some((line[..column].chars().count() as u16, y))
+    })
+}
+
+#[test]
+fn empty_vault_shows_a_hint() {
+    let buffer = draw(&mut app(&[], 59), 50, 10);
+    assert_snapshot("empty", &buffer);
+}
+
+#[test]
+fn cards_show_codes_and_remaining_time() {
+    // RFC 6238 の 59 秒時点のコード (287082)、残り 1 秒
+    let buffer = draw(&mut app(&["example", "github"], 59), 60, 14);
+    assert_snapshot("cards", &buffer);
+}
+
+#[test]
+fn selection_scrolls_past_the_visible_cards() {
+    let mut app = app(&["alpha", "bravo", "charlie", "delta", "echo"], 59);
+    // ヘッダー3行 + カード2枚分
+    let (width, height) = (50, 3 + 2 * 5);
+    assert!(text(&draw(&mut app, width, height)).contains("alpha"));
+
+    for _ in 0..3 {
+        app.next();
+    }
+    let buffer = draw(&mut app, width, height);
+    assert_snapshot("scrolled", &buffer);
+    let visible = text(&buffer);
+    assert!(!visible.contains("bravo"));
+    assert!(visible.contains("charlie") && visible.contains("delta"));
+
+    // 先頭から戻ると末尾に回り込む
+    for _ in 0..4 {
+        app.previous();
+    }
+    let visible = text(&draw(&mut app, width, height));
+    assert!(visible.contains("echo") && !visible.contains("charlie"));
+    app.previous();
+    let visible = text(&draw(&mut app, width, height));
+    assert!(visible.contains("delta") && visible.contains("echo"));
+}
+
+#[test]
+fn codes_turn_red_when_about_to_expire() {
+    // (コードの色, ゲージの色)
+    let colors = |timestamp: u64| {
+        let buffer = draw(&mut app(&["example"], timestamp), 60, 8);
+        // コードは名前の次の行に表示される
+        let (x, y) = find(&buffer, "example").unwrap();
+        let (gauge_x, gauge_y) = find(&buffer, &format!("{}s", 30 - timestamp % 30)).unwrap();
+        (buffer[(x, y + 1)].fg, buffer[(gauge_x, gauge_y)].fg)
+    };
+
+    // 残り6秒までは青、5秒から赤
+    assert_eq!(colors(30 + 24), (Color::Blue, Color::Blue));
+    assert_eq!(colors(30 + 25), (Color::Red, Color::Red));
+}
+
+#[test]
+fn clock_ticks_update_the_codes() {
+    let clock = Arc::new(FixedClock::new(59));
+    let mut manager = SecretManager::with_sources(Sources {
+        clock: Arc::clone(&clock) as Arc<dyn Clock>,
+        random: Arc::new(SeededRandom::new(1)),
+    });
+    manager.insert_credential(Credential::new(
+        "example".into(),
+        CredentialKind::Totp,
+        SECRET.to_vec(),
+    ));
+    let mut app = App::new(manager).unwrap();
+    let before = text(&draw(&mut app, 60, 8));
+    assert!(before.contains("287 082") && before.contains("1s"));
+
+    app.tick().unwrap();
+    assert_eq!(text(&draw(&mut app, 60, 8)), before);
+
+    clock.advance(1);
+    app.tick().unwrap();
+    let after = text(&draw(&mut app, 60, 8));
+    assert!(!after.contains("287 082") && after.contains("30s"));
+}
+
+#[test]
+fn narrow_terminals_do_not_panic() {
+    let mut app = app(&["a-very-long-credential-name", "b"], 59);
+    assert_snapshot("narrow", &draw(&mut app, 24, 14));
+    for (width, height) in [(1, 1), (10, 4), (15, 7), (24, 3)] {
+        draw(&mut app, width, height);
+    }
+}
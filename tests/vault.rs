@@ -0,0 +1,286 @@
+use aes_gcm::{
+    Aes256Gcm, Key, KeyInit, Nonce,
+    aead::{Aead, OsRng, rand_core::RngCore},
+};
+use gauth::error::AppError;
+use gauth::otp::CredentialKind;
+use gauth::secrets::{Credential, SecretManager, unlock_existing_vault};
+use sha2::{Digest, Sha256};
+
+const SECRET: &[u8] = b"12345678901234567890";
+
+fn sample() -> SecretManager {
+    let mut manager = SecretManager::new();
+    manager.insert_credential(Credential::new(
+        "example".into(),
+        CredentialKind::Totp,
+        SECRET.to_vec(),
+    ));
+    let mut motp = Credential::new(
+        "legacy-vpn".into(),
+        CredentialKind::Motp,
+        b"0123456789abcdef".to_vec(),
+    );
+    motp.pin = Some("1234".into());
+    manager.insert_credential(motp);
+    manager.set_time_offset(-42);
+    manager
+}
+
+#[test]
+fn round_trip() {
+    let encoded = sample().encode("hunter2").unwrap();
+    let manager = SecretManager::decode(&encoded, "hunter2").unwrap();
+
+    assert_eq!(manager.secret("example"), Some(SECRET));
+    let motp = manager.get_credential("legacy-vpn").unwrap();
+    assert_eq!(motp.kind, CredentialKind::Motp);
+    assert_eq!(motp.pin.as_deref(), Some("1234"));
+    assert_eq!(manager.time_offset(), -42);
+    let names: Vec<&str> = manager
+        .credentials()
+        .iter()
+        .map(|credential| credential.name.as_str())
+        .collect();
+    assert_eq!(names, ["example", "legacy-vpn"]);
+}
+
+#[test]
+fn envelope_is_documented_toml_without_plaintext() {
+    let encoded = sample().encode("hunter2").unwrap();
+    let text = String::from_utf8(encoded).unwrap();
+    let value: toml::Table = toml::from_str(&text).unwrap();
+
+    assert_eq!(value["version"].as_integer(), Some(2));
+    let slot = &value["slots"].as_array().unwrap()[0];
+    assert_eq!(slot["type"].as_str(), Some("password"));
+    assert!(value["payload"]["ciphertext"].is_str());
+    assert!(!text.contains("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ"));
+    assert!(!text.contains("example"));
+}
+
+#[test]
+fn re_encoding_keeps_the_data_key() {
+    let encoded = sample().encode("hunter2").unwrap();
+    let mut manager = SecretManager::decode(&encoded, "hunter2").unwrap();
+    manager.delete_credential("legacy-vpn");
+    let reencoded = manager.encode("hunter2").unwrap();
+
+    let slots = |bytes: &[u8]| {
+        let value: toml::Table = toml::from_str(std::str::from_utf8(bytes).unwrap()).unwrap();
+        value["slots"].clone()
+    };
+    assert_eq!(slots(&encoded), slots(&reencoded));
+    let manager = SecretManager::decode(&reencoded, "hunter2").unwrap();
+    assert!(manager.get_credential("legacy-vpn").is_none());
+}
+
+#[test]
+fn recovery_codes_are_stored_in_the_vault() {
+    let mut manager = sample();
+    let credential = manager.get_credential_mut("example").unwrap();
+    assert_eq!(
+        credential.add_recovery_codes(["abcd-efgh", "ijkl-mnop", "ABCD EFGH", " "]),
+        2
+    );
+    assert_eq!(
+        credential.use_recovery_code(Some("IJKLMNOP")).unwrap(),
+        "ijkl-mnop"
+    );
+    assert!(credential.use_recovery_code(Some("ijkl-mnop")).is_err());
+    assert!(credential.use_recovery_code(Some("zzzz")).is_err());
+
+    let encoded = manager.encode("hunter2").unwrap();
+    assert!(!String::from_utf8_lossy(&encoded).contains("abcd-efgh"));
+    let mut reloaded = SecretManager::decode(&encoded, "hunter2").unwrap();
+    let credential = reloaded.get_credential_mut("example").unwrap();
+    assert_eq!(credential.remaining_recovery_codes(), 1);
+    assert_eq!(credential.use_recovery_code(None).unwrap(), "abcd-efgh");
+    assert!(credential.use_recovery_code(None).is_err());
+    assert!(
+        reloaded
+            .get_credential("legacy-vpn")
+            .unwrap()
+            .recovery_codes
+            .is_empty()
+    );
+}
+
+#[test]
+fn wrong_password_is_rejected() {
+    let encoded = sample().encode("hunter2").unwrap();
+    assert!(matches!(
+        SecretManager::decode(&encoded, "hunter3"),
+        Err(AppError::WrongMasterPassword)
+    ));
+}
+
+#[test]
+fn tampered_payload_is_rejected() {
+    let encoded = sample().encode("hunter2").unwrap();
+    let mut value: toml::Table = toml::from_str(std::str::from_utf8(&encoded).unwrap()).unwrap();
+    let payload = value["payload"].as_table_mut().unwrap();
+    let ciphertext = payload["ciphertext"].as_str().unwrap().to_string();
+    let flipped = if ciphertext.starts_with('A') {
+        "B"
+    } else {
+        "A"
+    };
+    payload.insert(
+        "ciphertext".into(),
+        format!("{}{}", flipped, &ciphertext[1..]).into(),
+    );
+    let tampered = toml::to_string(&value).unwrap();
+
+    assert!(matches!(
+        SecretManager::decode(tampered.as_bytes(), "hunter2"),
+        Err(AppError::VaultCorrupted(_))
+    ));
+}
+
+#[test]
+fn excessive_scrypt_parameters_are_rejected() {
+    let encoded = String::from_utf8(sample().encode("hunter2").unwrap()).unwrap();
+    for (from, to) in [
+        ("log_n = 15", "log_n = 30"),
+        ("r = 8", "r = 4096"),
+        ("p = 1", "p = 64"),
+    ] {
+        assert!(encoded.contains(from));
+        let tampered = encoded.replace(from, to);
+        assert!(matches!(
+            SecretManager::decode(tampered.as_bytes(), "hunter2"),
+            Err(AppError::VaultCorrupted(_))
+        ));
+    }
+}
+
+#[test]
+fn unknown_version_is_reported() {
+    let encoded = sample().encode("hunter2").unwrap();
+    let text = String::from_utf8(encoded)
+        .unwrap()
+        .replace("version = 2", "version = 3");
+
+    let error = SecretManager::decode(text.as_bytes(), "hunter2").unwrap_err();
+    assert!(error.to_string().contains("Unsupported vault version 3"));
+}
+
+#[test]
+fn truncated_header_is_reported_as_corruption() {
+    let encoded = String::from_utf8(sample().encode("hunter2").unwrap()).unwrap();
+    let truncated = &encoded[..encoded.find("[payload]").unwrap()];
+    assert!(matches!(
+        SecretManager::decode(truncated.as_bytes(), "hunter2"),
+        Err(AppError::VaultCorrupted(_))
+    ));
+    assert!(matches!(
+        SecretManager::decode(b"version = 2\nslots = [", "hunter2"),
+        Err(AppError::VaultCorrupted(_))
+    ));
+}
+
+#[test]
+fn unlock_retries_wrong_passwords() {
+    let encoded = sample().encode("hunter2").unwrap();
+    let mut answers = ["wrong", "hunter2"].into_iter();
+    let mut prompts = Vec::new();
+    let password = unlock_existing_vault(&encoded, 3, |attempt| {
+        prompts.push(attempt);
+        Ok(answers.next().unwrap().to_string())
+    })
+    .unwrap();
+    assert_eq!(password, "hunter2");
+    assert_eq!(prompts, [0, 1]);
+
+    let mut count = 0;
+    let result = unlock_existing_vault(&encoded, 3, |_| {
+        count += 1;
+        Ok("wrong".to_string())
+    });
+    assert!(matches!(result, Err(AppError::WrongMasterPassword)));
+    assert_eq!(count, 3);
+}
+
+#[test]
+fn unlock_stops_on_a_corrupted_vault() {
+    let mut count = 0;
+    let result = unlock_existing_vault(b"version = 2\n[payload", 3, |_| {
+        count += 1;
+        Ok("hunter2".to_string())
+    });
+    assert!(matches!(result, Err(AppError::VaultCorrupted(_))));
+    assert_eq!(count, 1);
+}
+
+/// version 1 の保管庫と同じ手順で暗号化する
+fn legacy_encrypt(password: &str, data: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    let key = Sha256::digest(password.as_bytes());
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+    let mut nonce = [0u8; 12];
+    OsRng.fill_bytes(&mut nonce);
+    let ciphertext = cipher.encrypt(Nonce::from_slice(&nonce), data).unwrap();
+    (ciphertext, nonce.to_vec())
+}
+
+#[test]
+fn legacy_credentials_without_a_kind_are_totp() {
+    let password = "hunter2";
+    let (ciphertext, nonce) = legacy_encrypt(password, SECRET);
+    let inner = format!(
+        r#"[credentials.example]
+name = "example"
+ciphertext = {:?}
+nonce = {:?}
+"#,
+        ciphertext, nonce
+    );
+    let (mut contents, nonce) = legacy_encrypt(password, inner.as_bytes());
+    contents.extend_from_slice(&nonce);
+
+    let manager = SecretManager::decode(&contents, password).unwrap();
+    let credential = manager.get_credential("example").unwrap();
+    assert_eq!(credential.kind, CredentialKind::Totp);
+    assert_eq!(credential.secret, SECRET);
+}
+
+#[test]
+fn migrates_legacy_vault() {
+    let password = "hunter2";
+    let (ciphertext, nonce) = legacy_encrypt(password, SECRET);
+    let (pin_ciphertext, pin_nonce) = legacy_encrypt(password, b"1234");
+    let inner = format!(
+        r#"time_offset = 5
+
+[credentials.example]
+name = "example"
+kind = "yandex"
+ciphertext = {:?}
+nonce = {:?}
+pin_ciphertext = {:?}
+pin_nonce = {:?}
+last_step = 12345
+"#,
+        ciphertext, nonce, pin_ciphertext, pin_nonce
+    );
+    let (mut contents, nonce) = legacy_encrypt(password, inner.as_bytes());
+    contents.extend_from_slice(&nonce);
+
+    let mut manager = SecretManager::decode(&contents, password).unwrap();
+    let credential = manager.get_credential("example").unwrap();
+    assert_eq!(credential.kind, CredentialKind::Yandex);
+    assert_eq!(credential.secret, SECRET);
+    assert_eq!(credential.pin.as_deref(), Some("1234"));
+    assert_eq!(credential.last_step, Some(12345));
+    assert_eq!(manager.time_offset(), 5);
+
+    let migrated = manager.encode(password).unwrap();
+    assert!(String::from_utf8_lossy(&migrated).starts_with("version = 2"));
+    let manager = SecretManager::decode(&migrated, password).unwrap();
+    assert_eq!(manager.secret("example"), Some(SECRET));
+
+    assert!(matches!(
+        SecretManager::decode(&contents, "wrong"),
+        Err(AppError::WrongMasterPassword)
+    ));
+}
@@ -0,0 +1,147 @@
+use clap::Parser;
+use gauth::args::Args;
+use gauth::error::AppError;
+use gauth::otp::CredentialKind;
+use gauth::secrets::{Credential, SecretManager, Sources};
+use gauth::storage::MemoryStorage;
+use gauth::verify::{self, VerifyOutcome};
+use std::sync::Arc;
+
+const PASSWORD: &str = "hunter2";
+
+/// RFC 6238 の SHA1 のテストベクタ (6桁) の時刻
+const NOW: u64 = 1111111109;
+/// `NOW` のステップ (37037036) のコード
+const CURRENT: &str = "081804";
+/// 次のステップ (1111111111 を含む) のコード
+const NEXT: &str = "050471";
+
+fn manager_at(timestamp: u64) -> SecretManager {
+    let mut manager = SecretManager::with_sources(Sources::fixed(timestamp, 1));
+    manager.insert_credential(Credential::new(
+        "example".into(),
+        CredentialKind::Totp,
+        b"12345678901234567890".to_vec(),
+    ));
+    manager
+}
+
+#[test]
+fn current_code_is_valid_without_a_window() {
+    let manager = manager_at(NOW);
+    let outcome = verify::check(&manager, "example", CURRENT, 0).unwrap();
+    assert_eq!(
+        outcome,
+        VerifyOutcome::Valid {
+            step: NOW / 30,
+            drift: 0
+        }
+    );
+    assert_eq!(outcome.exit_code(), 0);
+    // 空白や大文字小文字の違いは無視する
+    assert!(matches!(
+        verify::check(&manager, "example", " 081 804 ", 0).unwrap(),
+        VerifyOutcome::Valid { .. }
+    ));
+}
+
+#[test]
+fn codes_outside_the_window_are_invalid() {
+    let manager = manager_at(NOW);
+    let outcome = verify::check(&manager, "example", NEXT, 0).unwrap();
+    assert_eq!(outcome, VerifyOutcome::Invalid);
+    assert_eq!(outcome.exit_code(), 2);
+    assert_eq!(
+        verify::check(&manager, "example", "000000", 3).unwrap(),
+        VerifyOutcome::Invalid
+    );
+    assert!(matches!(
+        verify::check(&manager, "missing", CURRENT, 1),
+        Err(AppError::CredentialNotFound(_))
+    ));
+}
+
+#[test]
+fn drift_is_reported_in_steps() {
+    // 次のステップのコードは +1、1分後の時計から見た現在のコードは -2
+    let outcome = verify::check(&manager_at(NOW), "example", NEXT, 1).unwrap();
+    assert_eq!(
+        outcome,
+        VerifyOutcome::Valid {
+            step: NOW / 30 + 1,
+            drift: 1
+        }
+    );
+    let outcome = verify::check(&manager_at(NOW + 60), "example", CURRENT, 2).unwrap();
+    assert_eq!(
+        outcome,
+        VerifyOutcome::Valid {
+            step: NOW / 30,
+            drift: -2
+        }
+    );
+    assert_eq!(
+        verify::check(&manager_at(NOW + 60), "example", CURRENT, 1).unwrap(),
+        VerifyOutcome::Invalid
+    );
+}
+
+#[test]
+fn recorded_steps_are_stored_and_replays_rejected() {
+    let storage = MemoryStorage::new();
+    let (mut manager, _) =
+        SecretManager::open_with(Arc::new(storage.clone()), PASSWORD, Sources::fixed(NOW, 1))
+            .unwrap();
+    manager.insert_credential(Credential::new(
+        "example".into(),
+        CredentialKind::Totp,
+        b"12345678901234567890".to_vec(),
+    ));
+    let VerifyOutcome::Valid { step, .. } = verify::check(&manager, "example", CURRENT, 1).unwrap()
+    else {
+        panic!("the current code must be valid");
+    };
+    verify::record_step(&mut manager, "example", step).unwrap();
+    manager.save_secrets(PASSWORD).unwrap();
+
+    let (manager, _) =
+        SecretManager::open_with(Arc::new(storage), PASSWORD, Sources::fixed(NOW, 1)).unwrap();
+    assert_eq!(
+        manager.get_credential("example").unwrap().last_step,
+        Some(step)
+    );
+    let outcome = verify::check(&manager, "example", CURRENT, 1).unwrap();
+    assert_eq!(outcome, VerifyOutcome::Replayed { step, drift: 0 });
+    assert_eq!(outcome.exit_code(), 3);
+    // 記録したステップより後のコードは受け付ける
+    assert!(matches!(
+        verify::check(&manager, "example", NEXT, 1).unwrap(),
+        VerifyOutcome::Valid { drift: 1, .. }
+    ));
+}
+
+#[test]
+fn window_is_bounded() {
+    let manager = manager_at(NOW);
+    assert!(matches!(
+        verify::check(&manager, "example", CURRENT, verify::MAX_WINDOW + 1),
+        Err(AppError::InvalidInput(_))
+    ));
+    let parse = |window: &str| {
+        Args::try_parse_from(["gauth", "verify", "example", CURRENT, "--window", window])
+    };
+    assert!(parse("20").is_ok());
+    assert!(parse("21").is_err());
+    assert!(parse("18446744073709551615").is_err());
+}
+
+#[test]
+fn window_at_the_ends_of_time_does_not_overflow() {
+    for timestamp in [0, u64::MAX] {
+        let manager = manager_at(timestamp);
+        assert_eq!(
+            verify::check(&manager, "example", "000000", verify::MAX_WINDOW).unwrap(),
+            VerifyOutcome::Invalid
+        );
+    }
+}